@@ -0,0 +1,21 @@
+//! Captures build-time facts for `niri-spacer version --verbose`.
+
+fn main() {
+    // Enabled cargo features arrive as CARGO_FEATURE_<NAME> env vars.
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|name| name.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=NIRI_SPACER_FEATURES={}", features.join(","));
+    println!(
+        "cargo:rustc-env=NIRI_SPACER_PROFILE={}",
+        std::env::var("PROFILE").unwrap_or_default()
+    );
+    println!(
+        "cargo:rustc-env=NIRI_SPACER_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+}
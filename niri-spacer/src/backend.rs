@@ -0,0 +1,526 @@
+//! The backend abstraction for the actual spacer windows.
+//!
+//! The orchestrator in [`crate::spacer`] only cares that *something* can open
+//! and close tiny windows with a predictable app ID and title; in production
+//! that is the Wayland backend in [`crate::native`], and in tests it is
+//! [`MockBackend`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{NiriSpacerError, Result};
+
+/// An opaque RGB color for spacer backgrounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Parses `#rrggbb` or `rrggbb`.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(NiriSpacerError::ConfigParse(format!(
+                "invalid color {hex:?}: expected #rrggbb"
+            )));
+        }
+        let parse = |range| u8::from_str_radix(&digits[range], 16).expect("checked hex");
+        Ok(Self {
+            r: parse(0..2),
+            g: parse(2..4),
+            b: parse(4..6),
+        })
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// Muted palette cycled through when placing one spacer per workspace.
+pub const DEFAULT_PALETTE: [Color; 6] = [
+    Color::new(0x28, 0x2a, 0x36),
+    Color::new(0x1e, 0x32, 0x2a),
+    Color::new(0x32, 0x28, 0x2a),
+    Color::new(0x2a, 0x28, 0x32),
+    Color::new(0x32, 0x30, 0x26),
+    Color::new(0x26, 0x30, 0x32),
+];
+
+/// Configuration for the windows a backend creates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NativeConfig {
+    /// Wayland app ID; niri window rules and our own correlation key off it.
+    pub app_id: String,
+    /// Titles are `"<title_prefix>-<number>"`.
+    pub title_prefix: String,
+    /// Fallback background color when a placement does not override it.
+    pub color: Color,
+    /// Initial surface size before the compositor configures us; also the
+    /// fixed column width requested from niri after placement. Wider spacers
+    /// are easier to hit with the mouse at the cost of screen real estate in
+    /// the tiling layout.
+    pub width: u32,
+    pub height: u32,
+    /// Advertise a 1x1 minimum size instead of the defensive 100x60 and
+    /// drop the maximum-size cap; niri happily tiles 1-pixel-wide columns.
+    pub true_minimal: bool,
+    /// Vertical gradient (top, bottom) instead of the solid `color`.
+    pub gradient: Option<(Color, Color)>,
+    /// PNG rendered (nearest-neighbor scaled) as the background instead of
+    /// the solid fill; decoding failures fall back to the solid color.
+    pub background_image: Option<std::path::PathBuf>,
+    /// Title template with `{n}`, `{n:0K}` (zero-padded to width K), and
+    /// `{ws}` placeholders; `None` keeps the plain `<prefix>-<n>` titles.
+    /// Zero padding keeps titles lexically sortable in niri rules.
+    pub title_template: Option<String>,
+    /// Attempts for the initial Wayland connect + roundtrip, which can fail
+    /// transiently when spawned at compositor startup.
+    pub connect_attempts: u32,
+    /// Pause between those attempts.
+    pub connect_retry_delay: std::time::Duration,
+}
+
+impl Default for NativeConfig {
+    fn default() -> Self {
+        Self {
+            app_id: "niri-spacer".to_string(),
+            title_prefix: "niri-spacer".to_string(),
+            color: DEFAULT_PALETTE[0],
+            width: 1,
+            height: 1,
+            true_minimal: false,
+            gradient: None,
+            background_image: None,
+            title_template: None,
+            connect_attempts: 3,
+            connect_retry_delay: std::time::Duration::from_millis(500),
+        }
+    }
+}
+
+/// Whether a string is a well-formed Wayland/XDG app ID: 1-255 characters
+/// from `[a-zA-Z0-9._-]`, with no leading/trailing dot and no consecutive
+/// dots. A malformed app ID may be rejected or silently normalized by the
+/// compositor, which breaks correlation.
+pub fn validate_app_id(app_id: &str) -> bool {
+    if app_id.is_empty() || app_id.len() > 255 {
+        return false;
+    }
+    if !app_id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    {
+        return false;
+    }
+    if app_id.starts_with('.') || app_id.ends_with('.') || app_id.contains("..") {
+        return false;
+    }
+    true
+}
+
+impl NativeConfig {
+    /// Configuration for a named instance: windows carry
+    /// `niri-spacer-<name>` as both app ID and title prefix so concurrent
+    /// instances never match each other's windows. Fails when the name
+    /// would produce a malformed app ID.
+    pub fn for_instance(name: &str) -> Result<Self> {
+        let prefix = format!("niri-spacer-{name}");
+        if !validate_app_id(&prefix) {
+            return Err(NiriSpacerError::ConfigParse(format!(
+                "invalid app_id format: {prefix:?}"
+            )));
+        }
+        Ok(Self {
+            app_id: prefix.clone(),
+            title_prefix: prefix,
+            ..Self::default()
+        })
+    }
+
+    /// Sets the app ID (window class), validating the XDG format.
+    pub fn window_class(mut self, app_id: impl Into<String>) -> Result<Self> {
+        let app_id = app_id.into();
+        if !validate_app_id(&app_id) {
+            return Err(NiriSpacerError::ConfigParse(format!(
+                "invalid app_id format: {app_id:?}"
+            )));
+        }
+        self.app_id = app_id;
+        Ok(self)
+    }
+
+    /// The title a window with this number will carry. With a template,
+    /// `{ws}` is left literal here — use [`Self::title_for_workspace`]
+    /// where the workspace index is known, and keep correlation keyed on
+    /// this form.
+    pub fn title_for(&self, number: u32) -> String {
+        match &self.title_template {
+            Some(template) => render_title(template, number, None),
+            None => format!("{}-{}", self.title_prefix, number),
+        }
+    }
+
+    /// Like [`Self::title_for`] with the `{ws}` placeholder expanded.
+    pub fn title_for_workspace(&self, number: u32, workspace_idx: u8) -> String {
+        match &self.title_template {
+            Some(template) => render_title(template, number, Some(workspace_idx)),
+            None => format!("{}-{}", self.title_prefix, number),
+        }
+    }
+}
+
+/// Expands a title template: `{n}` is the window number, `{n:0K}` the
+/// number zero-padded to `K` digits, `{ws}` the workspace index (left
+/// literal when unknown).
+pub fn render_title(template: &str, number: u32, workspace_idx: Option<u8>) -> String {
+    let mut out = template.replace("{n}", &number.to_string());
+    // {n:0K} zero-padding forms.
+    while let Some(start) = out.find("{n:0") {
+        let Some(end) = out[start..].find('}') else {
+            break;
+        };
+        let end = start + end;
+        let width: usize = out[start + 4..end].parse().unwrap_or(0);
+        let replacement = format!("{number:0width$}");
+        out.replace_range(start..=end, &replacement);
+    }
+    if let Some(ws) = workspace_idx {
+        out = out.replace("{ws}", &ws.to_string());
+    }
+    out
+}
+
+/// Diagnostic snapshot of one backend-managed window.
+///
+/// `is_correlated` distinguishes windows niri has acknowledged from ones
+/// still stuck pending, without an IPC round trip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativeWindowInfo {
+    /// Backend sequence number.
+    pub window_id: u32,
+    pub app_id: String,
+    /// The niri window ID once correlation succeeded.
+    pub niri_window_id: Option<u64>,
+    /// The workspace the window was placed on, once placement succeeded.
+    pub workspace_id: Option<u64>,
+    pub is_correlated: bool,
+}
+
+/// Bookkeeping of backend-managed windows, maintained on the async side so
+/// diagnostics never have to wait on the compositor thread.
+#[derive(Debug, Default)]
+pub(crate) struct ManagedWindowRegistry {
+    entries: Vec<NativeWindowInfo>,
+}
+
+// The stub backend can never create a window, so only `snapshot` is
+// reachable without the native feature.
+#[cfg_attr(not(feature = "native"), allow(dead_code))]
+impl ManagedWindowRegistry {
+    pub(crate) fn insert(&mut self, window_id: u32, app_id: String) {
+        self.entries.push(NativeWindowInfo {
+            window_id,
+            app_id,
+            niri_window_id: None,
+            workspace_id: None,
+            is_correlated: false,
+        });
+    }
+
+    pub(crate) fn remove(&mut self, window_id: u32) {
+        self.entries.retain(|e| e.window_id != window_id);
+    }
+
+    pub(crate) fn note_correlated(&mut self, window_id: u32, niri_window_id: u64) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.window_id == window_id) {
+            entry.niri_window_id = Some(niri_window_id);
+            entry.is_correlated = true;
+        }
+    }
+
+    pub(crate) fn note_placed(&mut self, window_id: u32, workspace_id: u64) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.window_id == window_id) {
+            entry.workspace_id = Some(workspace_id);
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> Vec<NativeWindowInfo> {
+        self.entries.clone()
+    }
+}
+
+/// Handle to one window a backend has opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendWindowHandle {
+    /// Sequence number unique within the backend.
+    pub number: u32,
+    /// Title the window was created with; used to correlate with niri.
+    pub title: String,
+}
+
+/// Something that can open and close spacer windows.
+#[allow(async_fn_in_trait)]
+pub trait SpacerBackend {
+    /// Opens one spacer window with the given sequence number and color.
+    async fn create_window(&mut self, number: u32, color: Color) -> Result<BackendWindowHandle>;
+
+    /// Closes a previously created window.
+    async fn close_window(&mut self, handle: &BackendWindowHandle) -> Result<()>;
+
+    /// Updates a window's title. Backends without a title path may ignore
+    /// this; the default does nothing.
+    async fn set_window_title(&mut self, _number: u32, _title: String) -> Result<()> {
+        Ok(())
+    }
+
+    /// Repaints a window with the given color and opacity. Backends without
+    /// a repaint path may ignore this; the default does nothing.
+    async fn redraw_window(&mut self, _number: u32, _color: Color, _opacity: f64) -> Result<()> {
+        Ok(())
+    }
+
+    /// Tears the backend down, closing any remaining windows.
+    async fn shutdown(&mut self) -> Result<()>;
+
+    /// Records that a window was correlated with a niri window; backends may
+    /// use this for diagnostics. The default does nothing.
+    fn note_correlated(&mut self, _number: u32, _niri_window_id: u64) {}
+
+    /// Records the workspace a window landed on. The default does nothing.
+    fn note_placed(&mut self, _number: u32, _workspace_id: u64) {}
+}
+
+#[cfg(test)]
+mod title_template_tests {
+    use super::*;
+
+    #[test]
+    fn plain_number_expansion() {
+        assert_eq!(render_title("spacer-{n}", 7, None), "spacer-7");
+    }
+
+    #[test]
+    fn zero_padded_expansion_sorts_lexically() {
+        assert_eq!(render_title("spacer-{n:02}", 7, None), "spacer-07");
+        assert_eq!(render_title("spacer-{n:02}", 12, None), "spacer-12");
+        assert_eq!(render_title("{n:04}", 7, None), "0007");
+    }
+
+    #[test]
+    fn workspace_expansion() {
+        assert_eq!(render_title("ws{ws}-{n}", 2, Some(5)), "ws5-2");
+        // Unknown workspace leaves the placeholder literal rather than
+        // guessing.
+        assert_eq!(render_title("ws{ws}-{n}", 2, None), "ws{ws}-2");
+    }
+
+    #[test]
+    fn templated_config_titles_stay_consistent_for_correlation() {
+        let config = NativeConfig {
+            title_template: Some("pad-{n:03}".to_string()),
+            ..NativeConfig::default()
+        };
+        assert_eq!(config.title_for(4), "pad-004");
+        assert_eq!(config.title_for_workspace(4, 9), "pad-004");
+    }
+}
+
+#[cfg(test)]
+mod app_id_tests {
+    use super::*;
+
+    #[test]
+    fn valid_app_ids_pass() {
+        for id in ["niri-spacer", "org.niri.spacer", "a", "x_1-2.y"] {
+            assert!(validate_app_id(id), "{id:?} should be valid");
+        }
+    }
+
+    #[test]
+    fn malformed_app_ids_fail() {
+        let too_long = "a".repeat(256);
+        for id in ["", ".x", "x.", "a..b", "sp ace", "emoji\u{1f600}", too_long.as_str()] {
+            assert!(!validate_app_id(id), "{id:?} should be invalid");
+        }
+    }
+
+    #[test]
+    fn every_generated_instance_app_id_validates() {
+        // Property-style sweep over representative valid instance names.
+        let names: Vec<String> = ["a", "left-monitor", "x_1", "out.put-2", "0"]
+            .into_iter()
+            .map(String::from)
+            .chain((0..50).map(|i| format!("instance-{i}")))
+            .collect();
+        for name in names {
+            let config = NativeConfig::for_instance(&name).unwrap();
+            assert!(validate_app_id(&config.app_id), "{:?}", config.app_id);
+        }
+    }
+
+    #[test]
+    fn bad_instance_names_are_rejected() {
+        for name in ["has space", "tr\u{e9}ma", "dot..dot", "end."] {
+            assert!(NativeConfig::for_instance(name).is_err(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn window_class_validates() {
+        assert!(NativeConfig::default().window_class("org.niri.spacer").is_ok());
+        assert!(NativeConfig::default().window_class("not valid").is_err());
+    }
+}
+
+#[cfg(test)]
+mod registry_tests {
+    use super::*;
+
+    #[test]
+    fn new_windows_start_uncorrelated() {
+        let mut registry = ManagedWindowRegistry::default();
+        registry.insert(1, "niri-spacer".to_string());
+
+        let snapshot = registry.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert!(!snapshot[0].is_correlated);
+        assert_eq!(snapshot[0].niri_window_id, None);
+        assert_eq!(snapshot[0].workspace_id, None);
+    }
+
+    #[test]
+    fn correlation_and_placement_are_tracked() {
+        let mut registry = ManagedWindowRegistry::default();
+        registry.insert(1, "niri-spacer".to_string());
+        registry.note_correlated(1, 1001);
+        registry.note_placed(1, 3);
+
+        let info = &registry.snapshot()[0];
+        assert!(info.is_correlated);
+        assert_eq!(info.niri_window_id, Some(1001));
+        assert_eq!(info.workspace_id, Some(3));
+    }
+
+    #[test]
+    fn snapshot_is_detached_and_remove_works() {
+        let mut registry = ManagedWindowRegistry::default();
+        registry.insert(1, "a".to_string());
+        let snapshot = registry.snapshot();
+        registry.remove(1);
+        assert_eq!(snapshot.len(), 1, "snapshot must not reflect later changes");
+        assert!(registry.snapshot().is_empty());
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// Shared log of redraw requests `(number, color, opacity)`.
+    pub(crate) type RedrawLog = Arc<Mutex<Vec<(u32, Color, f64)>>>;
+
+    /// Test backend that records window operations instead of talking to a
+    /// compositor. When given a [`crate::test_support::MockNiri`] state, it
+    /// also inserts the matching niri window so correlation succeeds.
+    #[derive(Default)]
+    pub(crate) struct MockBackend {
+        pub config: NativeConfig,
+        pub created: Vec<BackendWindowHandle>,
+        /// Colors each window was created with, by number.
+        pub created_colors: Vec<(u32, Color)>,
+        pub closed: Vec<u32>,
+        pub shutdown_calls: u32,
+        pub niri_state: Option<Arc<Mutex<crate::test_support::MockState>>>,
+        /// When set, `create_window` fails for these numbers.
+        pub fail_numbers: Vec<u32>,
+        /// When set, these windows are created but never show up in the
+        /// mock niri's window list, so correlation times out.
+        pub skip_niri_insert_numbers: Vec<u32>,
+        /// Cancels the token once this many windows have been created,
+        /// simulating SIGTERM mid-batch.
+        pub cancel_after_creations: Option<(u32, tokio_util::sync::CancellationToken)>,
+        /// Redraw requests, shared so tests keep visibility after the
+        /// backend moves into an orchestrator.
+        pub redraws: RedrawLog,
+        /// Title updates, shared like `redraws`.
+        pub titles: Arc<Mutex<Vec<(u32, String)>>>,
+    }
+
+    impl MockBackend {
+        pub(crate) fn new() -> Self {
+            Self::default()
+        }
+
+        pub(crate) fn with_niri(state: Arc<Mutex<crate::test_support::MockState>>) -> Self {
+            Self {
+                niri_state: Some(state),
+                ..Self::default()
+            }
+        }
+    }
+
+    impl SpacerBackend for MockBackend {
+        async fn create_window(
+            &mut self,
+            number: u32,
+            color: Color,
+        ) -> Result<BackendWindowHandle> {
+            self.created_colors.push((number, color));
+            if self.fail_numbers.contains(&number) {
+                return Err(NiriSpacerError::Ipc(format!(
+                    "mock backend refusing window {number}"
+                )));
+            }
+            let handle = BackendWindowHandle {
+                number,
+                title: self.config.title_for(number),
+            };
+            if let Some(state) = &self.niri_state {
+                if !self.skip_niri_insert_numbers.contains(&number) {
+                    state.lock().unwrap().insert_window(
+                        self.config.app_id.clone(),
+                        handle.title.clone(),
+                    );
+                }
+            }
+            self.created.push(handle.clone());
+            if let Some((limit, token)) = &self.cancel_after_creations {
+                if self.created.len() as u32 >= *limit {
+                    token.cancel();
+                }
+            }
+            Ok(handle)
+        }
+
+        async fn close_window(&mut self, handle: &BackendWindowHandle) -> Result<()> {
+            self.closed.push(handle.number);
+            Ok(())
+        }
+
+        async fn set_window_title(&mut self, number: u32, title: String) -> Result<()> {
+            self.titles.lock().unwrap().push((number, title));
+            Ok(())
+        }
+
+        async fn redraw_window(&mut self, number: u32, color: Color, opacity: f64) -> Result<()> {
+            self.redraws.lock().unwrap().push((number, color, opacity));
+            Ok(())
+        }
+
+        async fn shutdown(&mut self) -> Result<()> {
+            self.shutdown_calls += 1;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Build-time facts captured by `build.rs`, for verbose version output.
+//!
+//! Downstream bug reports need to say whether the binary has the native
+//! backend, which features were on, and what niri was detected at runtime;
+//! this module owns the build-time half.
+
+/// Crate version.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Comma-separated cargo features the binary was built with.
+pub const FEATURES: &str = env!("NIRI_SPACER_FEATURES");
+/// Build profile (debug/release).
+pub const PROFILE: &str = env!("NIRI_SPACER_PROFILE");
+/// Target triple.
+pub const TARGET: &str = env!("NIRI_SPACER_TARGET");
+
+/// The multi-line verbose build description (without runtime probes).
+pub fn render() -> String {
+    format!(
+        "niri-spacer {VERSION}\nfeatures: {features}\nprofile: {PROFILE}\ntarget: {TARGET}\n",
+        features = if FEATURES.is_empty() { "(none)" } else { FEATURES },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_names_the_build_facts() {
+        let text = render();
+        assert!(text.starts_with(&format!("niri-spacer {VERSION}")));
+        assert!(text.contains("features: "));
+        assert!(text.contains("profile: "));
+        assert!(text.contains("target: "));
+        // The features string must reflect the cfg set this test was
+        // actually compiled with, whatever combination that is.
+        assert_eq!(
+            FEATURES.split(',').any(|f| f == "native"),
+            cfg!(feature = "native"),
+            "features: {FEATURES}"
+        );
+        assert_eq!(
+            FEATURES.split(',').any(|f| f == "metrics"),
+            cfg!(feature = "metrics"),
+            "features: {FEATURES}"
+        );
+    }
+}
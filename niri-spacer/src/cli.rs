@@ -0,0 +1,824 @@
+//! The command-line interface, as a library module.
+//!
+//! Everything the binary does lives in [`run_cli`] with output going through
+//! an injected [`CliIo`] sink, so CLI behavior (mode selection, error
+//! reporting, info output) is testable in-process instead of by spawning the
+//! binary. `main.rs` is a thin wrapper parsing [`Args`] and passing real
+//! stdio.
+
+use std::io::IsTerminal;
+use std::process::ExitCode;
+
+use clap::{CommandFactory, Parser, Subcommand};
+
+use crate::backend::Color;
+use crate::error::Result;
+use crate::spacer::{NiriSpacer, NiriSpacerConfig};
+use crate::SessionValidator;
+
+/// Keeps niri workspaces alive by parking tiny spacer windows on them.
+#[derive(Debug, Parser)]
+#[command(name = "niri-spacer", version)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Number of workspaces (lowest indices first) to cover; default all.
+    #[arg(long)]
+    pub count: Option<u8>,
+
+    /// Instance name; allows several concurrent niri-spacer instances with
+    /// distinct app IDs and lock files.
+    #[arg(long, default_value = "default")]
+    pub instance_name: String,
+
+    /// Spacer window width in pixels (1-100). Wider spacers are easier to
+    /// click; narrower ones give the layout more room.
+    #[arg(long, value_name = "PX", default_value_t = 1,
+          value_parser = clap::value_parser!(u32).range(1..=100))]
+    pub spacing: u32,
+
+    /// Column width the spacer reserves: a screen proportion (0.05) or
+    /// pixels (40px). Defaults to the --spacing pixel width.
+    #[arg(long, value_name = "PROPORTION|PX")]
+    pub column_width: Option<crate::spacer::ColumnWidth>,
+
+    /// How the minimized width is requested when --column-width is absent:
+    /// fixed-px, or proportion for niri configs that ignore 1px fixed.
+    #[arg(long, value_enum, default_value = "fixed-px")]
+    pub spacer_width_mode: crate::spacer::SpacerWidthMode,
+
+    /// Settle delay after workspace focus changes, in milliseconds;
+    /// separate from the generic operation delay.
+    #[arg(long, value_name = "MS", default_value_t = 50)]
+    pub delay_between_workspaces: u64,
+
+    /// Base background color as #rrggbb.
+    #[arg(long)]
+    pub color: Option<String>,
+
+    /// Title template with {n}, {n:02}-style zero padding, and {ws}
+    /// placeholders, for lexically sortable titles in niri rules.
+    #[arg(long, value_name = "TEMPLATE")]
+    pub title_template: Option<String>,
+
+    /// PNG to render as the spacer background (scaled to the window),
+    /// instead of the solid fill.
+    #[arg(long, value_name = "PATH")]
+    pub background_image: Option<std::path::PathBuf>,
+
+    /// Top color of a vertical gradient (RRGGBB); the base color fills the
+    /// other end if only one gradient color is given.
+    #[arg(long, value_name = "RRGGBB")]
+    pub gradient_top: Option<String>,
+
+    /// Bottom color of a vertical gradient (RRGGBB).
+    #[arg(long, value_name = "RRGGBB")]
+    pub gradient_bottom: Option<String>,
+
+    /// Policy when a target workspace already has windows.
+    #[arg(long, value_enum, default_value = "warn")]
+    pub on_occupied: crate::spacer::OccupiedPolicy,
+
+    /// Standalone mode: merge workspaces holding fewer than N windows onto
+    /// one consolidated workspace, then exit.
+    #[arg(long, value_name = "N")]
+    pub merge: Option<u32>,
+
+    /// Where to send focus when it lands on a spacer: focused-history
+    /// (back to the window the user was on) or column-left.
+    #[arg(long, value_enum, default_value = "focused-history")]
+    pub redirect_to: crate::focus::RedirectTarget,
+
+    /// Redirect a focused spacer to this monitor index (for spacers living
+    /// on a secondary output) instead of a window or column target.
+    #[arg(long, value_name = "INDEX")]
+    pub redirect_monitor: Option<u8>,
+
+    /// Redirect aggressiveness: always (full redirect) or unfocus-only
+    /// (just leave the spacer via the previous workspace).
+    #[arg(long, value_enum, default_value = "always")]
+    pub redirect_mode: crate::focus::RedirectMode,
+
+    /// What to do when a focused spacer is the only window on its
+    /// workspace.
+    #[arg(long, value_enum, default_value = "stay")]
+    pub empty_workspace_focus: crate::focus::EmptyWorkspaceFocus,
+
+    /// Spacer appearance while niri's overview is open.
+    #[arg(long, value_enum, default_value = "normal")]
+    pub overview_style: crate::spacer::OverviewStyle,
+
+    /// Advertise a true 1x1 minimum window size instead of the defensive
+    /// 100x60 floor.
+    #[arg(long)]
+    pub true_minimal: bool,
+
+    /// Append the niri window ID to each spacer's title after correlation,
+    /// for window rules and scripts that need the mapping.
+    #[arg(long)]
+    pub embed_id_in_title: bool,
+
+    /// Pre-create workspaces so --count never targets a workspace that
+    /// does not exist yet.
+    #[arg(long, requires = "count")]
+    pub ensure_workspaces: bool,
+
+    /// Clean up leftover spacers from a previous session at startup;
+    /// spacers are always removed again on clean exit.
+    #[arg(long)]
+    pub transient: bool,
+
+    /// Keep going past individual spacer failures, reporting which
+    /// workspaces failed instead of aborting the whole batch.
+    #[arg(long)]
+    pub best_effort: bool,
+
+    /// What to do when a created window cannot be correlated with niri
+    /// before the timeout.
+    #[arg(long, value_enum, default_value = "abort")]
+    pub on_correlation_fail: crate::spacer::CorrelationFailPolicy,
+
+    /// Fail the run when a spacer vanishes between creation and
+    /// verification instead of dropping it and continuing.
+    #[arg(long)]
+    pub strict_validation: bool,
+
+    /// Manage workspaces on every output, not only the focused one.
+    #[arg(long)]
+    pub all_outputs: bool,
+
+    /// Score respawn/add targets by proximity, density, and output instead
+    /// of taking the first vacant workspace.
+    #[arg(long)]
+    pub smart_placement: bool,
+
+    /// Creation order: forward (lowest workspace first) or reverse.
+    #[arg(long, value_enum, default_value = "forward")]
+    pub order: crate::spacer::PlacementOrder,
+
+    /// Standalone mode: print per-workspace occupancy as a table, then
+    /// exit.
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Standalone mode: list niri outputs with their geometry and the
+    /// workspaces on each, then exit.
+    #[arg(long)]
+    pub list_outputs: bool,
+
+    /// Emit machine-readable JSON instead of text for --list-outputs.
+    #[arg(long, requires = "list_outputs")]
+    pub json: bool,
+
+    /// Keep running after placement, maintaining the spacer count: respawn
+    /// externally closed spacers and reconcile every 30s.
+    #[arg(long)]
+    pub watch_mode: bool,
+
+    /// Spacer count watch mode maintains; defaults to the number placed.
+    #[arg(long, value_name = "N", requires = "watch_mode")]
+    pub watch_desired_count: Option<u32>,
+
+    /// Restore spacers from a state file written by a previous export
+    /// instead of computing a fresh plan.
+    #[arg(long, value_name = "PATH")]
+    pub restore: Option<std::path::PathBuf>,
+
+    /// Add a single spacer on the first uncovered workspace instead of
+    /// running the full placement plan.
+    #[arg(long)]
+    pub add_one: bool,
+
+    /// Only place spacers on workspaces whose index falls in this
+    /// inclusive range, e.g. 2-5.
+    #[arg(long, value_name = "A-B", value_parser = parse_range)]
+    pub workspace_range: Option<(u8, u8)>,
+
+    /// Write the process ID here after successful initialization (removed
+    /// again on exit), for systemd or other supervisors.
+    #[arg(long, value_name = "PATH")]
+    pub pid_file: Option<std::path::PathBuf>,
+
+    /// Take over an existing PID file instead of refusing to start.
+    #[arg(long, requires = "pid_file")]
+    pub force: bool,
+
+    /// Keep retrying niri discovery and connection for this long at
+    /// startup (e.g. 30s, 500ms) instead of failing immediately.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub wait_for_niri: Option<std::time::Duration>,
+
+    /// Serve Prometheus metrics on this address (requires a build with the
+    /// metrics feature).
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_listen: Option<std::net::SocketAddr>,
+
+    /// Record every niri event the focus monitor receives to this file,
+    /// for later --replay debugging.
+    #[arg(long, value_name = "FILE")]
+    pub dump_events_to: Option<std::path::PathBuf>,
+
+    /// Standalone mode: replay a recorded event file through the focus
+    /// monitor logic (no live niri) and print the actions it would fire.
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<std::path::PathBuf>,
+
+    /// Spacer window IDs to assume during --replay, comma separated.
+    #[arg(long, value_name = "IDS", value_delimiter = ',', requires = "replay")]
+    pub replay_spacer_ids: Vec<u64>,
+
+    /// Print the placement plan and exit without creating windows.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Print the placement plan and ask for confirmation before creating
+    /// windows. A non-interactive stdin counts as "no".
+    #[arg(long)]
+    pub interactive: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Emit a shell completion script on stdout.
+    Completions {
+        /// Shell to generate completions for.
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Print version information; --verbose adds build features, target,
+    /// and (when a socket is reachable) the probed niri version.
+    Version {
+        #[arg(long)]
+        verbose: bool,
+    },
+    /// Print a niri window-rule snippet tailored to the active
+    /// configuration.
+    SuggestConfig {
+        /// Append the snippet to this file (after a confirmation prompt)
+        /// instead of only printing it.
+        #[arg(long, value_name = "PATH")]
+        write: Option<std::path::PathBuf>,
+    },
+}
+
+/// Parses `30s`, `500ms`, or a bare number of seconds.
+fn parse_duration(s: &str) -> std::result::Result<std::time::Duration, String> {
+    let (digits, unit) = match s.strip_suffix("ms") {
+        Some(d) => (d, 1u64),
+        None => (s.strip_suffix('s').unwrap_or(s), 1000),
+    };
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid duration {s:?}: expected e.g. 30s or 500ms"))?;
+    Ok(std::time::Duration::from_millis(value * unit))
+}
+
+/// Parses an inclusive `A-B` workspace index range.
+fn parse_range(s: &str) -> std::result::Result<(u8, u8), String> {
+    let (a, b) = s
+        .split_once('-')
+        .ok_or_else(|| format!("invalid range {s:?}: expected A-B"))?;
+    let start: u8 = a.trim().parse().map_err(|_| format!("invalid range start {a:?}"))?;
+    let end: u8 = b.trim().parse().map_err(|_| format!("invalid range end {b:?}"))?;
+    if start > end {
+        return Err(format!("range start {start} is after end {end}"));
+    }
+    Ok((start, end))
+}
+
+/// Where `run_cli` reads answers and writes its output; real stdio in the
+/// binary, captured buffers in tests.
+pub trait CliIo {
+    fn stdout(&mut self, text: &str);
+    fn stderr(&mut self, text: &str);
+    /// One line of interactive input, or `None` when stdin is not a TTY.
+    fn read_line(&mut self) -> Option<String>;
+}
+
+/// [`CliIo`] over the process's real stdio.
+#[derive(Debug, Default)]
+pub struct StdIo;
+
+impl CliIo for StdIo {
+    fn stdout(&mut self, text: &str) {
+        use std::io::Write as _;
+        print!("{text}");
+        let _ = std::io::stdout().flush();
+    }
+
+    fn stderr(&mut self, text: &str) {
+        eprint!("{text}");
+    }
+
+    fn read_line(&mut self) -> Option<String> {
+        if !std::io::stdin().is_terminal() {
+            return None;
+        }
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).ok()?;
+        Some(line)
+    }
+}
+
+/// Runs the CLI to completion, reporting errors on `io` and returning the
+/// process exit code.
+pub async fn run_cli(args: Args, io: &mut dyn CliIo) -> ExitCode {
+    match run(args, io).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            io.stderr(&format!("error: {e}\n"));
+            if let crate::NiriSpacerError::WorkspaceIndexOutOfRange { available, .. } = &e {
+                io.stderr(&crate::spacer::AvailableWorkspaces(available).to_string());
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args, io: &mut dyn CliIo) -> Result<()> {
+    // Completions need no niri session; handle them before validation.
+    if let Some(Command::Completions { shell }) = args.command {
+        let mut command = Args::command();
+        let mut script = Vec::new();
+        clap_complete::generate(shell, &mut command, "niri-spacer", &mut script);
+        io.stdout(&String::from_utf8_lossy(&script));
+        return Ok(());
+    }
+
+    if let Some(Command::Version { verbose }) = args.command {
+        if !verbose {
+            io.stdout(&format!("niri-spacer {}\n", crate::buildinfo::VERSION));
+            return Ok(());
+        }
+        io.stdout(&crate::buildinfo::render());
+        // Runtime probes are best-effort: version must work with no socket.
+        match SessionValidator::from_env() {
+            Ok(validator) => {
+                let client = crate::niri::NiriClient::new(validator.socket_path());
+                match client.version().await {
+                    Ok(version) => io.stdout(&format!("niri: {version}\n")),
+                    Err(_) => io.stdout("niri: not reachable\n"),
+                }
+            }
+            Err(_) => io.stdout("niri: no socket configured\n"),
+        }
+        if let Ok(Some(version)) = SessionValidator::detect_compositor_version() {
+            io.stdout(&format!("niri binary: {version}\n"));
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::SuggestConfig { write }) = &args.command {
+        let mut native = if args.instance_name != "default" {
+            crate::backend::NativeConfig::for_instance(&args.instance_name)?
+        } else {
+            crate::backend::NativeConfig::default()
+        };
+        native.width = args.spacing;
+        let snippet = crate::suggest::window_rule_snippet(&native, args.column_width);
+        io.stdout(&snippet);
+
+        if let Some(path) = write {
+            io.stdout(&format!("Append to {}? [y/N] ", path.display()));
+            let answer = io.read_line().unwrap_or_default();
+            if matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+                use std::io::Write as _;
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                writeln!(file, "\n{}", snippet.trim_end())?;
+                io.stdout(&format!("appended to {}\n", path.display()));
+            } else {
+                io.stdout("not written\n");
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = &args.replay {
+        let options = crate::focus::FocusMonitorOptions {
+            redirect_target: args.redirect_to,
+            redirect_mode: args.redirect_mode,
+            redirect_monitor: args.redirect_monitor,
+            empty_workspace_focus: args.empty_workspace_focus,
+            ..Default::default()
+        };
+        let actions = crate::recorder::replay(
+            path,
+            args.replay_spacer_ids.iter().copied().collect(),
+            options,
+        )
+        .await?;
+        io.stdout(&format!("{} action(s) would fire:\n", actions.len()));
+        for action in actions {
+            io.stdout(&format!("  {action:?}\n"));
+        }
+        return Ok(());
+    }
+
+    if let Some(addr) = args.metrics_listen {
+        crate::metrics::install_prometheus(addr)?;
+    }
+
+    let validator = match args.wait_for_niri {
+        Some(wait) => {
+            crate::session::wait_for_niri(wait, &crate::clock::TokioClock).await?
+        }
+        None => {
+            let validator = SessionValidator::from_env()?;
+            validator.validate()?;
+            validator
+        }
+    };
+
+    let mut config = NiriSpacerConfig::new(validator.socket_path());
+    config.count = args.count;
+    config.on_occupied = args.on_occupied;
+    config.best_effort = args.best_effort;
+    config.on_correlation_fail = args.on_correlation_fail;
+    config.all_outputs = args.all_outputs;
+    config.smart_placement = args.smart_placement;
+    config.order = args.order;
+    config.transient = args.transient;
+    if args.strict_validation {
+        config.validation = crate::spacer::ValidationPolicy::Strict;
+    }
+    config.workspace_range = args.workspace_range;
+    config.embed_id_in_title = args.embed_id_in_title;
+    if args.instance_name != "default" {
+        config.native = crate::backend::NativeConfig::for_instance(&args.instance_name)?;
+    }
+    config.native.true_minimal = args.true_minimal;
+    config.native.background_image = args.background_image.clone();
+    config.native.title_template = args.title_template.clone();
+    config.native.width = args.spacing;
+    config.column_width = args.column_width;
+    config.width_mode = args.spacer_width_mode;
+    config.timings.workspace_switch_delay =
+        std::time::Duration::from_millis(args.delay_between_workspaces);
+    if let Some(hex) = &args.color {
+        config.native.color = Color::from_hex(hex)?;
+    }
+    if args.gradient_top.is_some() || args.gradient_bottom.is_some() {
+        let top = match &args.gradient_top {
+            Some(hex) => Color::from_hex(hex)?,
+            None => config.native.color,
+        };
+        let bottom = match &args.gradient_bottom {
+            Some(hex) => Color::from_hex(hex)?,
+            None => config.native.color,
+        };
+        config.native.gradient = Some((top, bottom));
+    }
+
+    if let Some(max_windows) = args.merge {
+        let client = crate::niri::NiriClient::new(validator.socket_path());
+        let mut manager = crate::workspace::WorkspaceManager::new(client)
+            .with_spacer_prefix(config.native.app_id.clone());
+        let report = manager.merge_sparse_workspaces(max_windows).await?;
+        io.stdout(&format!(
+            "merged {} window(s) from {} workspace(s) onto workspace {}\n",
+            report.moved_windows, report.freed_workspaces, report.target_workspace_id
+        ));
+        return Ok(());
+    }
+
+    if args.list_outputs {
+        let client = crate::niri::NiriClient::new(validator.socket_path());
+        let manager = crate::workspace::WorkspaceManager::new(client);
+        let listing = manager.list_outputs().await?;
+        if args.json {
+            let value: Vec<serde_json::Value> = listing
+                .iter()
+                .map(|(output, workspaces)| {
+                    serde_json::json!({
+                        "output": output,
+                        "workspaces": workspaces,
+                    })
+                })
+                .collect();
+            io.stdout(&format!("{}\n", serde_json::to_string_pretty(&value)?));
+        } else {
+            for (output, workspaces) in &listing {
+                let geometry = output
+                    .logical
+                    .map(|g| format!("{}x{} at {},{}", g.width, g.height, g.x, g.y))
+                    .unwrap_or_else(|| "no logical geometry".to_string());
+                io.stdout(&format!("{} ({geometry})\n", output.name));
+                for ws in workspaces {
+                    match &ws.name {
+                        Some(name) => io.stdout(&format!("  workspace {} ({name})\n", ws.idx)),
+                        None => io.stdout(&format!("  workspace {}\n", ws.idx)),
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    if args.stats {
+        let client = crate::niri::NiriClient::new(validator.socket_path());
+        let manager = crate::workspace::WorkspaceManager::new(client)
+            .with_spacer_prefix(config.native.app_id.clone());
+        let stats = manager.collect_stats().await?;
+        io.stdout(&format!("{}\n", stats.render_table(None)));
+        return Ok(());
+    }
+
+    if args.dry_run {
+        io.stdout("Placement plan (dry run):\n");
+        for placement in crate::spacer::compute_plan(&config).await? {
+            io.stdout(&format!("  {placement}\n"));
+        }
+        return Ok(());
+    }
+
+    let _lock = crate::instance::InstanceLock::acquire(&args.instance_name)?;
+    let _pid_file = match &args.pid_file {
+        Some(path) => Some(crate::instance::PidFile::create(path, args.force)?),
+        None => None,
+    };
+    // The Wayland display may race compositor startup exactly like the IPC
+    // socket; give it the same grace period.
+    let mut spacer = {
+        use crate::clock::Clock as _;
+        let clock = crate::clock::TokioClock;
+        let deadline = clock.now() + args.wait_for_niri.unwrap_or_default();
+        loop {
+            match NiriSpacer::connect(config.clone()).await {
+                Ok(spacer) => break spacer,
+                Err(e) if clock.now() >= deadline => return Err(e),
+                Err(e) => {
+                    tracing::info!(error = %e, "Wayland not ready yet; retrying");
+                    clock.sleep(std::time::Duration::from_millis(500)).await;
+                }
+            }
+        }
+    };
+    if args.ensure_workspaces {
+        if let Some(count) = args.count {
+            spacer.workspace_manager().ensure_workspaces(count).await?;
+        }
+    }
+    if let Some(path) = &args.restore {
+        let exported =
+            NiriSpacer::<crate::native::NativeWindowManager>::restore_from_export(path)?;
+        spacer.restore(&exported).await?.log();
+    }
+    let created = if args.add_one {
+        let added = spacer.add_spacer().await?;
+        tracing::info!("added {added}");
+        true
+    } else if args.restore.is_some() {
+        true
+    } else if args.interactive {
+        let stdin = std::io::stdin();
+        let is_tty = stdin.is_terminal();
+        spacer.run_with_confirmation(stdin.lock(), is_tty).await?
+    } else {
+        // SIGTERM during the batch cancels between windows and cleans up
+        // the partial set instead of waiting for the persistent loop.
+        let cancel = spacer.cancellation_token();
+        tokio::spawn(async move {
+            let mut sigterm = match tokio::signal::unix::signal(
+                tokio::signal::unix::SignalKind::terminate(),
+            ) {
+                Ok(signal) => signal,
+                Err(_) => return,
+            };
+            sigterm.recv().await;
+            cancel.cancel();
+        });
+        match spacer.run().await {
+            Ok(report) => {
+                report.log();
+                true
+            }
+            Err(crate::NiriSpacerError::Cancelled) => {
+                tracing::info!("terminated during creation; partial windows cleaned up");
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    if created {
+        // Watch mode consumes the respawn channel, so only wire the
+        // monitor's missed-close detection when something will act on it.
+        let (managed_workspaces, respawn_requests) = if args.watch_mode {
+            (
+                Some(spacer.managed_workspace_set()),
+                Some(spacer.respawn_request_sender()),
+            )
+        } else {
+            (None, None)
+        };
+        // The struct update covers the test-only injection field that only
+        // exists in cfg(test) builds of this crate.
+        #[allow(clippy::needless_update)]
+        let options = crate::focus::FocusMonitorOptions {
+            redirect_target: args.redirect_to,
+            redirect_mode: args.redirect_mode,
+            redirect_monitor: args.redirect_monitor,
+            managed_workspaces,
+            respawn_requests,
+            empty_workspace_focus: args.empty_workspace_focus,
+            spacer_app_id_prefix: Some(spacer.config_native_app_id()),
+            spacer_events: Some(spacer.spacer_event_sender()),
+            dump_events_to: args.dump_events_to.clone(),
+            ..Default::default()
+        };
+        let monitor = crate::focus::FocusMonitor::spawn_with_options(
+            spacer.client().clone(),
+            spacer.spacer_ids(),
+            options,
+        );
+        spacer.attach_monitor(monitor);
+        // The spacer windows live only as long as our Wayland connection, so
+        // stay resident until interrupted.
+        if args.watch_mode {
+            let desired = args
+                .watch_desired_count
+                .unwrap_or(spacer.active_spacers().len() as u32);
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                result = spacer.watch_mode(desired) => {
+                    if let Err(e) = result {
+                        tracing::warn!(error = %e, "watch mode stopped");
+                    }
+                    tokio::signal::ctrl_c().await?;
+                }
+            }
+        } else {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                result = spacer.watch_overview(args.overview_style) => {
+                    if let Err(e) = result {
+                        tracing::warn!(error = %e, "overview watcher stopped");
+                    }
+                    tokio::signal::ctrl_c().await?;
+                }
+            }
+        }
+        let report = spacer.cleanup().await?;
+        if report.abandoned > 0 {
+            return Err(crate::NiriSpacerError::Ipc(format!(
+                "{} spacer window(s) could not be confirmed closed",
+                report.abandoned
+            )));
+        }
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    crate::telemetry::shutdown();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockNiri;
+
+    /// Captured stdio for in-process CLI tests.
+    #[derive(Debug, Default)]
+    struct CapturedIo {
+        out: String,
+        err: String,
+        lines: Vec<String>,
+    }
+
+    impl CliIo for CapturedIo {
+        fn stdout(&mut self, text: &str) {
+            self.out.push_str(text);
+        }
+
+        fn stderr(&mut self, text: &str) {
+            self.err.push_str(text);
+        }
+
+        fn read_line(&mut self) -> Option<String> {
+            if self.lines.is_empty() {
+                None
+            } else {
+                Some(self.lines.remove(0))
+            }
+        }
+    }
+
+    fn parse(args: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("niri-spacer").chain(args.iter().copied())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn dry_run_prints_the_plan_in_process() {
+        let _env = crate::test_support::env_lock().await;
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        std::env::set_var("NIRI_SOCKET", niri.socket_path());
+
+        let mut io = CapturedIo::default();
+        let code = run_cli(parse(&["--dry-run", "--count", "2"]), &mut io).await;
+
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(io.out.contains("Placement plan (dry run):"), "{}", io.out);
+        assert_eq!(io.out.matches("workspace").count(), 2, "{}", io.out);
+        assert!(io.err.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stats_render_in_process() {
+        let _env = crate::test_support::env_lock().await;
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        std::env::set_var("NIRI_SOCKET", niri.socket_path());
+
+        let mut io = CapturedIo::default();
+        let code = run_cli(parse(&["--stats"]), &mut io).await;
+
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(io.out.contains("Workspace ID"), "{}", io.out);
+    }
+
+    #[tokio::test]
+    async fn session_errors_land_on_stderr_with_failure_code() {
+        let _env = crate::test_support::env_lock().await;
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var("NIRI_SOCKET", dir.path().join("missing.sock"));
+
+        let mut io = CapturedIo::default();
+        let code = run_cli(parse(&["--dry-run"]), &mut io).await;
+
+        assert_eq!(code, ExitCode::FAILURE);
+        assert!(io.err.contains("invalid socket path"), "{}", io.err);
+        assert!(io.out.is_empty());
+    }
+
+    #[tokio::test]
+    async fn out_of_range_errors_list_the_available_workspaces() {
+        let _env = crate::test_support::env_lock().await;
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        std::env::set_var("NIRI_SOCKET", niri.socket_path());
+
+        let mut io = CapturedIo::default();
+        let code = run_cli(parse(&["--dry-run", "--workspace-range", "10-20"]), &mut io).await;
+
+        assert_eq!(code, ExitCode::FAILURE);
+        assert!(io.err.contains("out of range"), "{}", io.err);
+        assert!(io.err.contains("available workspaces:"), "{}", io.err);
+        assert!(io.err.contains("  1"), "{}", io.err);
+    }
+
+    #[tokio::test]
+    async fn verbose_version_lists_build_facts_without_a_socket() {
+        let _env = crate::test_support::env_lock().await;
+        std::env::remove_var("NIRI_SOCKET");
+
+        let mut io = CapturedIo::default();
+        let code = run_cli(parse(&["version", "--verbose"]), &mut io).await;
+
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(io.out.contains("features: "), "{}", io.out);
+        assert!(io.out.contains("target: "), "{}", io.out);
+        assert!(io.out.contains("niri: no socket configured"), "{}", io.out);
+    }
+
+    #[tokio::test]
+    async fn terse_version_stays_terse() {
+        let mut io = CapturedIo::default();
+        let code = run_cli(parse(&["version"]), &mut io).await;
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert_eq!(
+            io.out.trim(),
+            format!("niri-spacer {}", crate::buildinfo::VERSION)
+        );
+    }
+
+    #[tokio::test]
+    async fn completions_write_to_the_injected_sink() {
+        let mut io = CapturedIo::default();
+        let code = run_cli(parse(&["completions", "bash"]), &mut io).await;
+
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(io.out.contains("niri-spacer"), "{}", io.out);
+    }
+
+    #[tokio::test]
+    async fn suggest_config_write_respects_a_declined_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri.kdl");
+
+        let mut io = CapturedIo {
+            lines: vec!["n\n".to_string()],
+            ..CapturedIo::default()
+        };
+        let code = run_cli(
+            parse(&["suggest-config", "--write", path.to_str().unwrap()]),
+            &mut io,
+        )
+        .await;
+
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(io.out.contains("not written"), "{}", io.out);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,103 @@
+//! Time abstraction for timing-dependent logic.
+//!
+//! Retry loops, verification deadlines, and debounce all need "now" and
+//! "sleep". Going through [`Clock`] instead of `tokio::time` directly lets
+//! tests drive those paths with [`TestClock`], where a sleep advances
+//! virtual time immediately, so timeout behavior runs in milliseconds
+//! instead of wall-clock seconds.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// A source of time and sleeps.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// Completes after `duration` (real or virtual, per implementation).
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+}
+
+/// The production clock: plain `tokio::time`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Virtual clock for tests: every sleep advances `now` by the requested
+/// duration and returns immediately (after yielding once, so concurrent
+/// tasks still make progress).
+#[derive(Debug, Clone)]
+pub struct TestClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl Default for TestClock {
+    fn default() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+}
+
+impl TestClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves virtual time forward without sleeping.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> {
+        let now = Arc::clone(&self.now);
+        Box::pin(async move {
+            *now.lock().unwrap() += duration;
+            tokio::task::yield_now().await;
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_clock_advances_on_sleep_without_waiting() {
+        let clock = TestClock::new();
+        let before = clock.now();
+        let wall = std::time::Instant::now();
+
+        clock.sleep(Duration::from_secs(3600)).await;
+
+        assert!(clock.now() >= before + Duration::from_secs(3600));
+        assert!(wall.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_clock_advance_is_visible_to_clones() {
+        let clock = TestClock::new();
+        let other = clock.clone();
+        let before = other.now();
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(other.now(), before + Duration::from_secs(5));
+    }
+}
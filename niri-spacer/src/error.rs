@@ -0,0 +1,84 @@
+//! Error types shared across the crate.
+
+use thiserror::Error;
+
+/// Convenience alias used by every fallible function in this crate.
+pub type Result<T> = std::result::Result<T, NiriSpacerError>;
+
+/// Top-level error type for niri-spacer.
+#[derive(Debug, Error)]
+pub enum NiriSpacerError {
+    /// The niri IPC socket path is missing, malformed, or unusable.
+    #[error("invalid socket path {path}: {reason}")]
+    InvalidSocketPath { path: String, reason: String },
+
+    /// A required environment variable was not set.
+    #[error("environment variable {0} is not set")]
+    MissingEnvVar(String),
+
+    /// Another live instance with the same instance name holds the lock.
+    #[error("instance {name:?} already running (pid {pid})")]
+    InstanceAlreadyRunning { name: String, pid: u32 },
+
+    /// Creating more spacers would exceed the library-wide limit.
+    #[error("spacer limit reached ({current} of {maximum})")]
+    MaxSpacerCountReached { current: u32, maximum: u32 },
+
+    /// A requested workspace range has no overlap with what exists. The
+    /// base message stays short; [`crate::spacer::AvailableWorkspaces`]
+    /// renders the detailed listing CLI output appends.
+    #[error(
+        "workspace range {requested_start}-{requested_end} is out of range \
+         (highest existing index is {available_max})"
+    )]
+    WorkspaceIndexOutOfRange {
+        requested_start: u8,
+        requested_end: u8,
+        available_max: u8,
+        /// Existing workspaces as (index, name) pairs, for the detailed
+        /// listing.
+        available: Vec<(u8, Option<String>)>,
+    },
+
+    /// A target workspace already holds windows and policy says abort.
+    #[error("workspace {idx} already has {windows} window(s)")]
+    WorkspaceOccupied { idx: u8, windows: usize },
+
+    /// This build has no native Wayland backend.
+    #[error("built without the native Wayland backend (enable the `native` feature)")]
+    NativeNotSupported,
+
+    /// The IPC connection died mid-request, typically because niri
+    /// restarted. Distinct from [`Self::Ipc`] so callers can attempt a
+    /// reconnect instead of failing outright.
+    #[error("niri connection lost: {0}")]
+    ConnectionLost(String),
+
+    /// A configuration value failed validation or parsing.
+    #[error("configuration error: {0}")]
+    ConfigParse(String),
+
+    /// A freshly created window never showed up in niri's window list.
+    #[error("window {0:?} never appeared in niri")]
+    CorrelationTimeout(String),
+
+    /// The operation was cancelled (e.g. SIGTERM during a batch).
+    #[error("operation cancelled")]
+    Cancelled,
+
+    /// A window we manage is no longer reported by niri.
+    #[error("window {0} not found")]
+    WindowNotFound(u64),
+
+    /// The niri IPC endpoint returned an error reply.
+    #[error("niri IPC error: {0}")]
+    Ipc(String),
+
+    /// Underlying I/O failure talking to the socket or filesystem.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// A reply from niri could not be decoded.
+    #[error("failed to decode niri reply: {0}")]
+    Decode(#[from] serde_json::Error),
+}
@@ -0,0 +1,981 @@
+//! Focus monitoring: keeps keyboard focus away from spacer windows.
+//!
+//! Spacers are not meant to be used; when niri reports focus landing on one
+//! (e.g. after the previously focused window closes), the monitor redirects
+//! focus back to the last real window it saw.
+
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+use crate::error::Result;
+use crate::health::{self, Health};
+use crate::niri::{Action, NiriClient, NiriEvent};
+
+/// How many times a crashed monitor loop is restarted before giving up.
+const MAX_RESTARTS: u32 = 3;
+/// How long a fetched window list stays good for; rapid alt-tabbing across
+/// a spacer can fire ten focus events per second, and each used to cost a
+/// full `get_windows`.
+const WINDOW_CACHE_TTL: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// Shared, mutable set of niri window IDs that are spacers.
+pub type SpacerIdSet = Arc<RwLock<HashSet<u64>>>;
+
+/// Where to send focus when it lands on a spacer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum RedirectTarget {
+    /// Back to the previously focused non-spacer window, as tracked from
+    /// `WindowFocusChanged` events. Returns the user where they were, which
+    /// is more intuitive than directional movement.
+    #[default]
+    FocusedHistory,
+    /// One column to the left, niri's directional fallback.
+    ColumnLeft,
+}
+
+impl std::str::FromStr for RedirectTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "focused-history" => Ok(Self::FocusedHistory),
+            "column-left" => Ok(Self::ColumnLeft),
+            other => Err(format!(
+                "unknown redirect target {other:?}: expected focused-history or column-left"
+            )),
+        }
+    }
+}
+
+/// What to do when a spacer gains focus while it is the only window on its
+/// workspace: with nothing to redirect to on that workspace, a normal
+/// redirect would just fail noisily.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum EmptyWorkspaceFocus {
+    /// Leave focus on the spacer; the user clearly wanted this workspace.
+    #[default]
+    Stay,
+    /// Bounce focus back to the previous workspace.
+    Previous,
+}
+
+impl std::str::FromStr for EmptyWorkspaceFocus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "stay" => Ok(Self::Stay),
+            "previous" => Ok(Self::Previous),
+            other => Err(format!(
+                "unknown empty-workspace focus {other:?}: expected stay or previous"
+            )),
+        }
+    }
+}
+
+/// How aggressively the monitor reacts when a spacer gains focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum RedirectMode {
+    /// Redirect focus per the configured target (default).
+    #[default]
+    Always,
+    /// Only take focus off the spacer by returning to the previous
+    /// workspace, without forcing any column direction. Gentler when the
+    /// user was deliberately navigating.
+    UnfocusOnly,
+}
+
+impl std::str::FromStr for RedirectMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(Self::Always),
+            "unfocus-only" => Ok(Self::UnfocusOnly),
+            other => Err(format!(
+                "unknown redirect mode {other:?}: expected always or unfocus-only"
+            )),
+        }
+    }
+}
+
+/// Options for the monitor loop, split out so tests can tweak them.
+#[derive(Debug, Clone, Default)]
+pub struct FocusMonitorOptions {
+    /// Where to redirect focus when a spacer ends up focused.
+    pub redirect_target: RedirectTarget,
+    /// Whether to redirect at all, or merely unfocus the spacer.
+    pub redirect_mode: RedirectMode,
+    /// Redirect across output boundaries: when set, a focused spacer sends
+    /// the user to this monitor index instead of a window/column target.
+    /// Useful when spacers live on a secondary monitor.
+    pub redirect_monitor: Option<u8>,
+    /// Workspaces that are supposed to hold a spacer. When set together
+    /// with `respawn_requests`, focus landing on an unknown window whose
+    /// workspace is managed but has no live spacer (an external close we
+    /// missed) schedules a respawn.
+    pub managed_workspaces: Option<Arc<RwLock<HashSet<u64>>>>,
+    /// Channel receiving the workspace IDs that need a respawned spacer.
+    pub respawn_requests: Option<tokio::sync::mpsc::Sender<u64>>,
+    /// Behavior when the focused spacer is alone on its workspace.
+    pub empty_workspace_focus: EmptyWorkspaceFocus,
+    /// App ID prefix identifying our spacers; used to notice when a tracked
+    /// window's app ID stops being ours.
+    pub spacer_app_id_prefix: Option<String>,
+    /// Emits SpacerFocused/SpacerFocusRedirected lifecycle events; wire the
+    /// orchestrator's sender here for external monitoring.
+    pub spacer_events: Option<tokio::sync::broadcast::Sender<crate::spacer::SpacerEvent>>,
+    /// Record every received event (with timestamps) to this file for
+    /// later replay debugging.
+    pub dump_events_to: Option<std::path::PathBuf>,
+    /// Test-only: panic when focus lands on this window ID, to exercise the
+    /// panic-recovery path.
+    #[cfg(test)]
+    pub panic_on_window_id: Option<u64>,
+}
+
+/// A supervised background task watching the niri event stream.
+pub struct FocusMonitor {
+    supervisor: JoinHandle<()>,
+    health: watch::Receiver<Health>,
+}
+
+impl FocusMonitor {
+    /// Spawns the monitor. The loop is restarted (up to [`MAX_RESTARTS`]
+    /// times) if it panics or fails, so a bad protocol assumption cannot
+    /// silently disable redirection for the rest of the session.
+    pub fn spawn(client: NiriClient, spacer_ids: SpacerIdSet) -> Self {
+        Self::spawn_with_options(client, spacer_ids, FocusMonitorOptions::default())
+    }
+
+    pub fn spawn_with_options(
+        client: NiriClient,
+        spacer_ids: SpacerIdSet,
+        options: FocusMonitorOptions,
+    ) -> Self {
+        let (health_tx, health) = watch::channel(Health::Running);
+        let supervisor = tokio::spawn(supervise(client, spacer_ids, options, health_tx));
+        Self { supervisor, health }
+    }
+
+    /// Channel reporting the monitor's current state.
+    pub fn health(&self) -> watch::Receiver<Health> {
+        self.health.clone()
+    }
+
+    /// Stops the monitor without waiting for in-flight work.
+    pub fn abort(&self) {
+        self.supervisor.abort();
+    }
+
+    /// Stops the monitor and waits (bounded) for its task to wind down, so
+    /// callers can sequence shutdown phases deterministically.
+    pub async fn stop(self) {
+        self.supervisor.abort();
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(2), self.supervisor).await;
+    }
+}
+
+async fn supervise(
+    client: NiriClient,
+    spacer_ids: SpacerIdSet,
+    options: FocusMonitorOptions,
+    health: watch::Sender<Health>,
+) {
+    let mut restarts = 0;
+    loop {
+        let task = tokio::spawn(monitor_loop(
+            client.clone(),
+            Arc::clone(&spacer_ids),
+            options.clone(),
+        ));
+        let failure = match task.await {
+            Ok(Ok(())) => {
+                info!("focus monitor stream ended");
+                let _ = health.send(Health::Stopped);
+                return;
+            }
+            Ok(Err(e)) => format!("focus monitor failed: {e}"),
+            Err(join_error) if join_error.is_panic() => {
+                health::record_panic();
+                let message = health::panic_message(join_error.into_panic().as_ref());
+                format!("focus monitor panicked: {message}")
+            }
+            Err(_) => {
+                // Cancelled from outside; nothing to restart.
+                let _ = health.send(Health::Stopped);
+                return;
+            }
+        };
+        error!("{failure}");
+
+        restarts += 1;
+        if restarts > MAX_RESTARTS {
+            error!("focus monitor exceeded {MAX_RESTARTS} restarts; giving up");
+            let _ = health.send(Health::Failed(failure));
+            return;
+        }
+        warn!(attempt = restarts, "restarting focus monitor");
+    }
+}
+
+async fn monitor_loop(
+    client: NiriClient,
+    spacer_ids: SpacerIdSet,
+    options: FocusMonitorOptions,
+) -> Result<()> {
+    // One connection serves the whole monitoring session: events arrive on
+    // the read half, redirects go out on the write half. Every helper below
+    // borrows this same client -- handlers must never construct a fresh
+    // NiriClient per focus event, which used to cost several short-lived
+    // connections per second on a busy desktop.
+    let (mut events, mut writer) = client.subscribe_with_writer().await?;
+    let mut last_real_focus: Option<u64> = None;
+    // Workspaces we already asked a respawn for; cleared once a live spacer
+    // shows up there again, so one missed close yields one request.
+    let mut respawn_requested: HashSet<u64> = HashSet::new();
+    // Spacer window -> neighbour to focus instead, recorded when a spacer
+    // silently became its workspace's active window. Correcting eagerly
+    // would steal the user's focus, so the fix waits for the moment they
+    // actually land on the spacer.
+    let mut pending_corrections: std::collections::HashMap<u64, u64> =
+        std::collections::HashMap::new();
+    let mut window_cache = WindowCache::new();
+    let mut recorder = match &options.dump_events_to {
+        Some(path) => Some(crate::recorder::EventRecorder::create(path)?),
+        None => None,
+    };
+
+    while let Some(event) = events.next_event().await? {
+        if let Some(recorder) = &mut recorder {
+            if let Err(e) = recorder.record(&event) {
+                warn!(error = %e, "event recording failed; disabling recorder");
+            }
+        }
+        if matches!(
+            event,
+            NiriEvent::WindowClosed { .. }
+                | NiriEvent::WindowOpenedOrChanged { .. }
+                | NiriEvent::WindowAppIdChanged { .. }
+        ) {
+            window_cache.invalidate();
+        }
+        if let NiriEvent::WindowAppIdChanged { id, app_id } = &event {
+            // A tracked window whose app ID is no longer ours is not a
+            // spacer anymore (the ID was reused or the window repurposed);
+            // keeping it in the set would misdirect future redirects.
+            let prefix = options
+                .spacer_app_id_prefix
+                .as_deref()
+                .unwrap_or("niri-spacer");
+            let mut ids = spacer_ids.write().expect("spacer id set poisoned");
+            if ids.contains(id) && !app_id.starts_with(prefix) {
+                warn!(id, %app_id, "tracked spacer changed app ID; dropping from the set");
+                ids.remove(id);
+            }
+            continue;
+        }
+        if let NiriEvent::WorkspaceActiveWindowChanged {
+            workspace_id,
+            active_window_id: Some(active),
+        } = event
+        {
+            let is_spacer = spacer_ids
+                .read()
+                .expect("spacer id set poisoned")
+                .contains(&active);
+            if is_spacer {
+                match neighbour_on_workspace(&client, &mut window_cache, workspace_id, active)
+                    .await
+                {
+                    Ok(Some(neighbour)) => {
+                        debug!(
+                            spacer = active,
+                            neighbour, "spacer became active window; queueing correction"
+                        );
+                        pending_corrections.insert(active, neighbour);
+                    }
+                    Ok(None) => {}
+                    Err(e) => debug!(error = %e, "could not inspect workspace"),
+                }
+            }
+            continue;
+        }
+        let NiriEvent::WindowFocusChanged { id: Some(id) } = event else {
+            continue;
+        };
+
+        #[cfg(test)]
+        if options.panic_on_window_id == Some(id) {
+            panic!("test-injected panic on window {id}");
+        }
+        #[cfg(not(test))]
+        let _ = &options;
+
+        let is_spacer = spacer_ids.read().expect("spacer id set poisoned").contains(&id);
+        if is_spacer {
+            if let Some(events) = &options.spacer_events {
+                let _ = events.send(crate::spacer::SpacerEvent::SpacerFocused(id));
+            }
+        }
+        if !is_spacer {
+            last_real_focus = Some(id);
+            if let (Some(managed), Some(respawn)) =
+                (&options.managed_workspaces, &options.respawn_requests)
+            {
+                let check = async {
+                    let windows = window_cache.get(&client).await?;
+                    check_for_missing_spacer(
+                        &windows,
+                        id,
+                        managed,
+                        &spacer_ids,
+                        &mut respawn_requested,
+                        respawn,
+                    )
+                    .await;
+                    Ok::<(), crate::NiriSpacerError>(())
+                };
+                if let Err(e) = check.await {
+                    debug!(error = %e, "missing-spacer check failed");
+                }
+            }
+            continue;
+        }
+
+        let alone = match window_cache.get(&client).await.map(|w| spacer_is_alone(&w, id)) {
+            Ok(alone) => alone,
+            Err(e) => {
+                debug!(error = %e, "could not count workspace windows");
+                false
+            }
+        };
+        let Some(action) = decide_redirect(
+            &options,
+            pending_corrections.remove(&id),
+            alone,
+            last_real_focus,
+        ) else {
+            debug!(spacer = id, "no redirect applicable");
+            continue;
+        };
+        debug!(spacer = id, ?action, "redirecting focus");
+        crate::metrics::focus_redirect();
+        let redirected = match writer.action(action).await {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(error = %e, "focus redirect failed");
+                false
+            }
+        };
+        if let Some(events) = &options.spacer_events {
+            let _ = events.send(crate::spacer::SpacerEvent::SpacerFocusRedirected {
+                from: id,
+                redirected,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The first non-spacer window sharing the given workspace, if any.
+///
+/// Fast path: the workspace response already names its active window, so
+/// when that is some other window we are done without a full window scan.
+async fn neighbour_on_workspace(
+    client: &NiriClient,
+    cache: &mut WindowCache,
+    workspace_id: u64,
+    spacer_id: u64,
+) -> Result<Option<u64>> {
+    let active = client
+        .get_workspaces()
+        .await?
+        .into_iter()
+        .find(|ws| ws.id == workspace_id)
+        .and_then(|ws| ws.active_window_id);
+    if let Some(active) = active {
+        if active != spacer_id {
+            return Ok(Some(active));
+        }
+    }
+
+    let windows = cache.get(client).await?;
+    Ok(windows
+        .iter()
+        .find(|w| w.workspace_id == Some(workspace_id) && w.id != spacer_id)
+        .map(|w| w.id))
+}
+
+/// Picks the corrective action (if any) for focus landing on a spacer.
+///
+/// Priorities, preserved from when this logic was inlined: a queued
+/// active-window correction targets a neighbour on the same workspace and
+/// beats everything; a spacer alone on its workspace obeys the
+/// empty-workspace policy (nothing to redirect to); the monitor override
+/// crosses output boundaries; unfocus-only merely leaves; otherwise the
+/// configured target decides. `None` means do nothing.
+fn decide_redirect(
+    options: &FocusMonitorOptions,
+    pending_correction: Option<u64>,
+    alone: bool,
+    last_real_focus: Option<u64>,
+) -> Option<Action> {
+    if let Some(neighbour) = pending_correction {
+        return Some(Action::FocusWindow { id: neighbour });
+    }
+    if alone {
+        return match options.empty_workspace_focus {
+            EmptyWorkspaceFocus::Stay => None,
+            EmptyWorkspaceFocus::Previous => Some(Action::FocusWorkspacePrevious {}),
+        };
+    }
+    if let Some(index) = options.redirect_monitor {
+        return Some(Action::FocusMonitorAtIndex { index });
+    }
+    if options.redirect_mode == RedirectMode::UnfocusOnly {
+        return Some(Action::FocusWorkspacePrevious {});
+    }
+    match options.redirect_target {
+        RedirectTarget::FocusedHistory => {
+            last_real_focus.map(|target| Action::FocusWindow { id: target })
+        }
+        RedirectTarget::ColumnLeft => Some(Action::FocusColumnLeft {}),
+    }
+}
+
+/// Short-lived memo of the window list: a burst of focus events shares one
+/// fetch instead of hammering niri, and relevant window events invalidate
+/// it early.
+struct WindowCache {
+    entry: Option<(tokio::time::Instant, Vec<crate::niri::Window>)>,
+}
+
+impl WindowCache {
+    fn new() -> Self {
+        Self { entry: None }
+    }
+
+    async fn get(&mut self, client: &NiriClient) -> Result<Vec<crate::niri::Window>> {
+        if let Some((fetched, windows)) = &self.entry {
+            if fetched.elapsed() < WINDOW_CACHE_TTL {
+                return Ok(windows.clone());
+            }
+        }
+        let windows = client.get_windows().await?;
+        self.entry = Some((tokio::time::Instant::now(), windows.clone()));
+        Ok(windows)
+    }
+
+    fn invalidate(&mut self) {
+        self.entry = None;
+    }
+}
+
+/// Whether the given window is the only one on its workspace.
+fn spacer_is_alone(windows: &[crate::niri::Window], window_id: u64) -> bool {
+    let Some(workspace) = windows
+        .iter()
+        .find(|w| w.id == window_id)
+        .and_then(|w| w.workspace_id)
+    else {
+        return false;
+    };
+    windows
+        .iter()
+        .filter(|w| w.workspace_id == Some(workspace))
+        .count()
+        == 1
+}
+
+/// Secondary check on focus changes to unknown windows: if the focused
+/// window sits on a workspace that should have a spacer but no live spacer
+/// window is there, ask for a respawn (once, until the spacer reappears).
+async fn check_for_missing_spacer(
+    windows: &[crate::niri::Window],
+    focused_id: u64,
+    managed: &Arc<RwLock<HashSet<u64>>>,
+    spacer_ids: &SpacerIdSet,
+    requested: &mut HashSet<u64>,
+    respawn: &tokio::sync::mpsc::Sender<u64>,
+) {
+    let Some(workspace) = windows
+        .iter()
+        .find(|w| w.id == focused_id)
+        .and_then(|w| w.workspace_id)
+    else {
+        return;
+    };
+    if !managed
+        .read()
+        .expect("managed workspace set poisoned")
+        .contains(&workspace)
+    {
+        return;
+    }
+
+    let has_live_spacer = {
+        let ids = spacer_ids.read().expect("spacer id set poisoned");
+        windows
+            .iter()
+            .any(|w| w.workspace_id == Some(workspace) && ids.contains(&w.id))
+    };
+    if has_live_spacer {
+        requested.remove(&workspace);
+        return;
+    }
+    if requested.insert(workspace) {
+        info!(workspace, "managed workspace lost its spacer; requesting respawn");
+        let _ = respawn.send(workspace).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockNiri;
+    use std::time::Duration;
+
+    fn ids(ids: &[u64]) -> SpacerIdSet {
+        Arc::new(RwLock::new(ids.iter().copied().collect()))
+    }
+
+    async fn wait_for_action_count(niri: &MockNiri, count: usize) {
+        tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                if niri.state().lock().unwrap().actions.len() >= count {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("mock niri never saw the expected actions");
+    }
+
+    #[tokio::test]
+    async fn redirects_focus_from_spacer_to_last_real_window() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let monitor = FocusMonitor::spawn(NiriClient::new(niri.socket_path()), ids(&[50]));
+
+        niri.wait_for_event_subscriber().await;
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(7) });
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+
+        wait_for_action_count(&niri, 1).await;
+        assert_eq!(
+            niri.state().lock().unwrap().actions,
+            vec![Action::FocusWindow { id: 7 }]
+        );
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn focused_history_redirects_to_latest_real_window() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let monitor = FocusMonitor::spawn(NiriClient::new(niri.socket_path()), ids(&[50]));
+
+        niri.wait_for_event_subscriber().await;
+        // The tracker must follow focus history: 7 then 8, so the redirect
+        // goes to 8, not 7.
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(7) });
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(8) });
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+
+        wait_for_action_count(&niri, 1).await;
+        assert_eq!(
+            niri.state().lock().unwrap().actions,
+            vec![Action::FocusWindow { id: 8 }]
+        );
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn focus_events_leave_no_lingering_connections() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let client = NiriClient::new(niri.socket_path());
+        let monitor = FocusMonitor::spawn(client.clone(), ids(&[50]));
+
+        niri.wait_for_event_subscriber().await;
+        for _ in 0..5 {
+            niri.send_event(NiriEvent::WindowFocusChanged { id: Some(7) });
+            niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+        }
+        wait_for_action_count(&niri, 5).await;
+
+        // Only the monitoring session's own stream stays open; redirects
+        // ride its write half and queries close their sockets immediately.
+        assert_eq!(client.open_connection_count(), 1);
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn monitor_redirect_crosses_output_boundaries() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let options = FocusMonitorOptions {
+            redirect_monitor: Some(1),
+            ..FocusMonitorOptions::default()
+        };
+        let monitor = FocusMonitor::spawn_with_options(
+            NiriClient::new(niri.socket_path()),
+            ids(&[50]),
+            options,
+        );
+
+        niri.wait_for_event_subscriber().await;
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+
+        wait_for_action_count(&niri, 1).await;
+        assert_eq!(
+            niri.state().lock().unwrap().actions,
+            vec![Action::FocusMonitorAtIndex { index: 1 }]
+        );
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn unfocus_only_mode_returns_to_the_previous_workspace() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let options = FocusMonitorOptions {
+            redirect_mode: RedirectMode::UnfocusOnly,
+            ..FocusMonitorOptions::default()
+        };
+        let monitor = FocusMonitor::spawn_with_options(
+            NiriClient::new(niri.socket_path()),
+            ids(&[50]),
+            options,
+        );
+
+        niri.wait_for_event_subscriber().await;
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(7) });
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+
+        wait_for_action_count(&niri, 1).await;
+        assert_eq!(
+            niri.state().lock().unwrap().actions,
+            vec![Action::FocusWorkspacePrevious {}],
+            "no directional or targeted focus change in unfocus-only mode"
+        );
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn column_left_redirect_issues_directional_action() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let options = FocusMonitorOptions {
+            redirect_target: RedirectTarget::ColumnLeft,
+            ..FocusMonitorOptions::default()
+        };
+        let monitor = FocusMonitor::spawn_with_options(
+            NiriClient::new(niri.socket_path()),
+            ids(&[50]),
+            options,
+        );
+
+        niri.wait_for_event_subscriber().await;
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+
+        wait_for_action_count(&niri, 1).await;
+        assert_eq!(
+            niri.state().lock().unwrap().actions,
+            vec![Action::FocusColumnLeft {}]
+        );
+        monitor.abort();
+    }
+
+    /// Mock with spacer window 50 alone on workspace 2 and a real window 7
+    /// on workspace 1.
+    async fn lone_spacer_fixture() -> MockNiri {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        {
+            let state = niri.state();
+            let mut state = state.lock().unwrap();
+            state.windows.push(crate::niri::Window {
+                id: 50,
+                title: Some("niri-spacer-1".to_string()),
+                app_id: Some("niri-spacer".to_string()),
+                workspace_id: Some(2),
+                is_focused: false,
+                pid: None,
+                layout: None,
+            });
+            state.windows.push(crate::niri::Window {
+                id: 7,
+                title: Some("editor".to_string()),
+                app_id: Some("emacs".to_string()),
+                workspace_id: Some(1),
+                is_focused: true,
+                pid: None,
+                layout: None,
+            });
+        }
+        niri
+    }
+
+    #[test]
+    fn decision_function_priorities() {
+        let defaults = FocusMonitorOptions::default();
+
+        // A queued correction beats everything, including alone-ness.
+        assert_eq!(
+            decide_redirect(&defaults, Some(8), true, Some(7)),
+            Some(Action::FocusWindow { id: 8 })
+        );
+        // Alone + stay: nothing; alone + previous: bounce.
+        assert_eq!(decide_redirect(&defaults, None, true, Some(7)), None);
+        let previous = FocusMonitorOptions {
+            empty_workspace_focus: EmptyWorkspaceFocus::Previous,
+            ..FocusMonitorOptions::default()
+        };
+        assert_eq!(
+            decide_redirect(&previous, None, true, Some(7)),
+            Some(Action::FocusWorkspacePrevious {})
+        );
+        // Monitor override beats mode and target.
+        let monitor = FocusMonitorOptions {
+            redirect_monitor: Some(2),
+            redirect_mode: RedirectMode::UnfocusOnly,
+            ..FocusMonitorOptions::default()
+        };
+        assert_eq!(
+            decide_redirect(&monitor, None, false, Some(7)),
+            Some(Action::FocusMonitorAtIndex { index: 2 })
+        );
+        // Unfocus-only leaves via the previous workspace.
+        let unfocus = FocusMonitorOptions {
+            redirect_mode: RedirectMode::UnfocusOnly,
+            ..FocusMonitorOptions::default()
+        };
+        assert_eq!(
+            decide_redirect(&unfocus, None, false, Some(7)),
+            Some(Action::FocusWorkspacePrevious {})
+        );
+        // History target needs history; column-left never does.
+        assert_eq!(
+            decide_redirect(&defaults, None, false, Some(7)),
+            Some(Action::FocusWindow { id: 7 })
+        );
+        assert_eq!(decide_redirect(&defaults, None, false, None), None);
+        let column = FocusMonitorOptions {
+            redirect_target: RedirectTarget::ColumnLeft,
+            ..FocusMonitorOptions::default()
+        };
+        assert_eq!(
+            decide_redirect(&column, None, false, None),
+            Some(Action::FocusColumnLeft {})
+        );
+    }
+
+    #[tokio::test]
+    async fn focus_bursts_share_one_window_fetch() {
+        let niri = lone_spacer_fixture().await;
+        let monitor = FocusMonitor::spawn(NiriClient::new(niri.socket_path()), ids(&[50]));
+
+        niri.wait_for_event_subscriber().await;
+        niri.state().lock().unwrap().windows_requests = 0;
+
+        for _ in 0..5 {
+            niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+        }
+        // Give the loop time to drain the burst (well under the cache TTL).
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        let fetches = niri.state().lock().unwrap().windows_requests;
+        assert!(fetches <= 1, "burst of five events caused {fetches} fetches");
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn lone_spacer_with_stay_does_not_redirect() {
+        let niri = lone_spacer_fixture().await;
+        let monitor = FocusMonitor::spawn(NiriClient::new(niri.socket_path()), ids(&[50]));
+
+        niri.wait_for_event_subscriber().await;
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(7) });
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+
+        // Give the monitor time to (not) act.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(
+            niri.state().lock().unwrap().actions.is_empty(),
+            "stay must issue no redirect"
+        );
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn lone_spacer_with_previous_bounces_workspace() {
+        let niri = lone_spacer_fixture().await;
+        let options = FocusMonitorOptions {
+            empty_workspace_focus: EmptyWorkspaceFocus::Previous,
+            ..FocusMonitorOptions::default()
+        };
+        let monitor = FocusMonitor::spawn_with_options(
+            NiriClient::new(niri.socket_path()),
+            ids(&[50]),
+            options,
+        );
+
+        niri.wait_for_event_subscriber().await;
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+
+        wait_for_action_count(&niri, 1).await;
+        assert_eq!(
+            niri.state().lock().unwrap().actions,
+            vec![Action::FocusWorkspacePrevious {}]
+        );
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn app_id_change_away_from_ours_untracks_the_window() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let spacer_ids = ids(&[50]);
+        let monitor = FocusMonitor::spawn(
+            NiriClient::new(niri.socket_path()),
+            Arc::clone(&spacer_ids),
+        );
+
+        niri.wait_for_event_subscriber().await;
+        niri.send_event(NiriEvent::WindowAppIdChanged {
+            id: 50,
+            app_id: "org.mozilla.firefox".to_string(),
+        });
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while spacer_ids.read().unwrap().contains(&50) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("window 50 should have been dropped from the spacer set");
+
+        // Focus landing on it now behaves like any real window: no redirect.
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(niri.state().lock().unwrap().actions.is_empty());
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn silently_activated_spacer_is_corrected_on_arrival() {
+        // Workspace 2 holds spacer 50 and a real window 8. The neighbour of
+        // the previously active window closes, niri silently makes the
+        // spacer the active window, and the user switches there.
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        {
+            let state = niri.state();
+            let mut state = state.lock().unwrap();
+            for (id, app, ws) in [(50, "niri-spacer", 2), (8, "foot", 2)] {
+                state.windows.push(crate::niri::Window {
+                    id,
+                    title: Some(app.to_string()),
+                    app_id: Some(app.to_string()),
+                    workspace_id: Some(ws),
+                    is_focused: false,
+                    pid: None,
+                    layout: None,
+                });
+            }
+        }
+        let monitor = FocusMonitor::spawn(NiriClient::new(niri.socket_path()), ids(&[50]));
+
+        niri.wait_for_event_subscriber().await;
+        niri.send_event(NiriEvent::WorkspaceActiveWindowChanged {
+            workspace_id: 2,
+            active_window_id: Some(50),
+        });
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+
+        wait_for_action_count(&niri, 1).await;
+        // The correction targets the neighbour on the same workspace, not
+        // some stale focus history.
+        assert_eq!(
+            niri.state().lock().unwrap().actions,
+            vec![Action::FocusWindow { id: 8 }]
+        );
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn missed_close_then_focus_schedules_one_respawn() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        // Workspace 2 should hold spacer window 50, but it was closed behind
+        // our back; the user's window 7 lives there too.
+        {
+            let state = niri.state();
+            let mut state = state.lock().unwrap();
+            let id = state.insert_window("foot".to_string(), "shell".to_string());
+            state.windows.iter_mut().find(|w| w.id == id).unwrap().workspace_id = Some(2);
+        }
+        let user_window = niri.state().lock().unwrap().windows[0].id;
+
+        let (respawn_tx, mut respawn_rx) = tokio::sync::mpsc::channel(4);
+        let options = FocusMonitorOptions {
+            managed_workspaces: Some(Arc::new(RwLock::new([2u64].into_iter().collect()))),
+            respawn_requests: Some(respawn_tx),
+            ..FocusMonitorOptions::default()
+        };
+        let monitor = FocusMonitor::spawn_with_options(
+            NiriClient::new(niri.socket_path()),
+            ids(&[50]),
+            options,
+        );
+
+        niri.wait_for_event_subscriber().await;
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(user_window) });
+        // A second focus event must not queue a duplicate request.
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(user_window) });
+
+        let workspace = tokio::time::timeout(Duration::from_secs(2), respawn_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(workspace, 2);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), respawn_rx.recv())
+                .await
+                .is_err(),
+            "duplicate respawn request"
+        );
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn recovers_from_injected_panic() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let options = FocusMonitorOptions {
+            panic_on_window_id: Some(666),
+            ..FocusMonitorOptions::default()
+        };
+        let monitor = FocusMonitor::spawn_with_options(
+            NiriClient::new(niri.socket_path()),
+            ids(&[50]),
+            options,
+        );
+
+        let panics_before = crate::health::panics_caught();
+        niri.wait_for_event_subscriber().await;
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(666) });
+
+        // The loop panics, the supervisor restarts it, and a fresh
+        // subscription shows up at the mock.
+        niri.wait_for_event_subscribers(2).await;
+        assert!(crate::health::panics_caught() > panics_before);
+        assert_eq!(*monitor.health().borrow(), Health::Running);
+
+        // The restarted loop still redirects.
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(7) });
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(50) });
+        wait_for_action_count(&niri, 1).await;
+        assert_eq!(
+            niri.state().lock().unwrap().actions,
+            vec![Action::FocusWindow { id: 7 }]
+        );
+        monitor.abort();
+    }
+}
@@ -0,0 +1,40 @@
+//! Health reporting for the long-running tasks.
+//!
+//! Both the Wayland backend thread and the focus monitor publish their state
+//! on a [`tokio::sync::watch`] channel so the rest of the process can notice
+//! when one of them dies instead of silently losing functionality.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// State of a supervised task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Health {
+    Running,
+    /// The task died (panic or error) and is not coming back.
+    Failed(String),
+    /// The task finished on request.
+    Stopped,
+}
+
+static PANICS_CAUGHT: AtomicU64 = AtomicU64::new(0);
+
+/// Records one caught panic in the process-wide counter.
+pub fn record_panic() {
+    PANICS_CAUGHT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Number of panics caught and converted to errors since startup.
+pub fn panics_caught() -> u64 {
+    PANICS_CAUGHT.load(Ordering::Relaxed)
+}
+
+/// Renders a panic payload into something loggable.
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
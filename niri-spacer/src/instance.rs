@@ -0,0 +1,187 @@
+//! Per-instance locking.
+//!
+//! Multiple niri-spacer instances (e.g. one per output) are legitimate as
+//! long as they use different instance names; two instances with the *same*
+//! name would fight over the same app ID pattern. A pid file per name under
+//! `$XDG_RUNTIME_DIR` catches the accidental-duplicate case.
+
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::error::{NiriSpacerError, Result};
+
+/// Holds the pid-file lock for one instance name; released on drop.
+#[derive(Debug)]
+pub struct InstanceLock {
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Acquires the lock for `name` under `$XDG_RUNTIME_DIR` (or the system
+    /// temp directory when unset). Fails if another live process holds it;
+    /// stale pid files from crashed instances are reclaimed.
+    pub fn acquire(name: &str) -> Result<Self> {
+        let dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        Self::acquire_in(&dir, name)
+    }
+
+    fn acquire_in(dir: &Path, name: &str) -> Result<Self> {
+        let path = dir.join(format!("niri-spacer-{name}.pid"));
+
+        if let Ok(contents) = std::fs::read_to_string(&path) {
+            if let Ok(pid) = contents.trim().parse::<u32>() {
+                if Path::new(&format!("/proc/{pid}")).exists() {
+                    return Err(NiriSpacerError::InstanceAlreadyRunning {
+                        name: name.to_string(),
+                        pid,
+                    });
+                }
+                debug!(pid, path = %path.display(), "reclaiming stale pid file");
+            }
+        }
+
+        std::fs::write(&path, std::process::id().to_string())?;
+        Ok(Self { path })
+    }
+
+    /// The pid file backing this lock.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// An operator-facing PID file (e.g. for systemd or a process supervisor),
+/// written atomically after successful initialization and removed on drop.
+///
+/// Distinct from [`InstanceLock`]: the lock guards against duplicate
+/// instances of the same name, while the PID file lives wherever the
+/// operator pointed it and exists purely so external tooling can signal us.
+#[derive(Debug)]
+pub struct PidFile {
+    path: std::path::PathBuf,
+}
+
+impl PidFile {
+    /// Writes the current process ID to `path` (write-to-temp then rename,
+    /// so readers never see a partial file). Fails if the file already
+    /// exists unless `force` is set, since that suggests another instance
+    /// may still be running.
+    pub fn create(path: &Path, force: bool) -> Result<Self> {
+        if path.exists() && !force {
+            let holder = std::fs::read_to_string(path)
+                .ok()
+                .and_then(|s| s.trim().parse::<u32>().ok());
+            return Err(NiriSpacerError::ConfigParse(format!(
+                "PID file {} already exists{}; is another instance running? \
+                 (use --force to take it over)",
+                path.display(),
+                holder.map(|pid| format!(" (pid {pid})")).unwrap_or_default(),
+            )));
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("pid.tmp");
+        std::fs::write(&tmp, format!("{}\n", std::process::id()))?;
+        std::fs::rename(&tmp, path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_live_instance_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let _lock = InstanceLock::acquire_in(dir.path(), "default").unwrap();
+
+        // Our own pid is in the file, and we are definitely alive.
+        let err = InstanceLock::acquire_in(dir.path(), "default").unwrap_err();
+        match err {
+            NiriSpacerError::InstanceAlreadyRunning { name, pid } => {
+                assert_eq!(name, "default");
+                assert_eq!(pid, std::process::id());
+            }
+            other => panic!("expected InstanceAlreadyRunning, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn stale_pid_file_is_reclaimed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri-spacer-default.pid");
+        std::fs::write(&path, "999999999").unwrap();
+
+        let lock = InstanceLock::acquire_in(dir.path(), "default").unwrap();
+        assert_eq!(lock.path(), path);
+    }
+
+    #[test]
+    fn different_names_do_not_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let _a = InstanceLock::acquire_in(dir.path(), "a").unwrap();
+        let _b = InstanceLock::acquire_in(dir.path(), "b").unwrap();
+    }
+
+    #[test]
+    fn pid_file_holds_our_pid_and_cleans_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri-spacer.pid");
+
+        let pid_file = PidFile::create(&path, false).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.trim().parse::<u32>().unwrap(), std::process::id());
+        assert!(!path.with_extension("pid.tmp").exists());
+
+        drop(pid_file);
+        assert!(!path.exists(), "pid file must be removed on shutdown");
+    }
+
+    #[test]
+    fn existing_pid_file_requires_force() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri-spacer.pid");
+        std::fs::write(&path, "12345\n").unwrap();
+
+        let err = PidFile::create(&path, false).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+        assert!(err.to_string().contains("12345"), "{err}");
+
+        let _forced = PidFile::create(&path, true).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.trim().parse::<u32>().unwrap(), std::process::id());
+    }
+
+    #[test]
+    fn lock_is_released_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = InstanceLock::acquire_in(dir.path(), "default").unwrap();
+        let path = lock.path().to_path_buf();
+        drop(lock);
+        assert!(!path.exists());
+        let _again = InstanceLock::acquire_in(dir.path(), "default").unwrap();
+    }
+}
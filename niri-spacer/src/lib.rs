@@ -0,0 +1,39 @@
+//! niri-spacer keeps [niri](https://github.com/YaLTeR/niri) workspaces alive
+//! by parking tiny "spacer" windows on them.
+//!
+//! The crate is organized as a library so the behavior can be tested against
+//! a mock niri IPC endpoint; the binary is a thin CLI wrapper. Compositor
+//! access is split between the [`niri`] IPC client and the [`native`] Wayland
+//! backend, with [`spacer::NiriSpacer`] orchestrating the two.
+
+pub mod backend;
+pub mod buildinfo;
+pub mod cli;
+pub mod clock;
+pub mod error;
+pub mod focus;
+pub mod health;
+pub mod instance;
+pub mod metrics;
+#[cfg(feature = "native")]
+pub mod native;
+#[cfg(not(feature = "native"))]
+#[path = "native_stub.rs"]
+pub mod native;
+pub mod niri;
+pub mod recorder;
+pub mod report;
+pub mod session;
+pub mod spacer;
+pub mod suggest;
+#[cfg(feature = "opentelemetry")]
+pub mod telemetry;
+pub mod window;
+pub mod workspace;
+
+#[cfg(test)]
+pub(crate) mod test_support;
+
+pub use error::{NiriSpacerError, Result};
+pub use session::SessionValidator;
+pub use spacer::{NiriSpacer, NiriSpacerConfig};
@@ -0,0 +1,40 @@
+use clap::Parser;
+
+use niri_spacer::cli::{run_cli, Args, StdIo};
+
+/// Installs the tracing subscriber: stderr logging, plus OTLP span export
+/// when built with the `opentelemetry` feature and
+/// `$OTEL_EXPORTER_OTLP_ENDPOINT` is set.
+fn init_tracing(_service_name: &str) {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "niri_spacer=info".into());
+
+    #[cfg(feature = "opentelemetry")]
+    if let Ok(endpoint) = std::env::var(niri_spacer::telemetry::OTLP_ENDPOINT_ENV) {
+        match niri_spacer::telemetry::otlp_tracer_provider(_service_name, &endpoint) {
+            Ok(provider) => {
+                use tracing_subscriber::layer::SubscriberExt;
+                use tracing_subscriber::util::SubscriberInitExt;
+                tracing_subscriber::registry()
+                    .with(filter)
+                    .with(tracing_subscriber::fmt::layer())
+                    .with(niri_spacer::telemetry::layer(&provider))
+                    .init();
+                niri_spacer::telemetry::install(provider);
+                return;
+            }
+            Err(e) => eprintln!("{e}; falling back to stderr logging only"),
+        }
+    }
+
+    tracing_subscriber::fmt().with_env_filter(filter).init();
+}
+
+#[tokio::main]
+async fn main() -> std::process::ExitCode {
+    init_tracing("niri-spacer");
+    // Help and version are handled by clap::parse exactly as before the
+    // cli-module split.
+    let args = Args::parse();
+    run_cli(args, &mut StdIo).await
+}
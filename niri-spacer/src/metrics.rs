@@ -0,0 +1,95 @@
+//! Operational metrics, no-ops unless built with the `metrics` feature.
+//!
+//! The call sites always exist so the hot paths read naturally; without the
+//! feature each function compiles to nothing. With it, the counters,
+//! histograms, and gauge feed a Prometheus exporter started by
+//! [`install_prometheus`].
+
+use crate::error::Result;
+
+/// One spacer window successfully created and correlated.
+pub fn window_created() {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!("niri_spacer.windows_created").increment(1);
+}
+
+/// How long one correlation took, in milliseconds.
+pub fn correlation_duration(elapsed_ms: f64) {
+    #[cfg(feature = "metrics")]
+    ::metrics::histogram!("niri_spacer.correlation_duration_ms").record(elapsed_ms);
+    #[cfg(not(feature = "metrics"))]
+    let _ = elapsed_ms;
+}
+
+/// One focus redirect fired.
+pub fn focus_redirect() {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!("niri_spacer.focus_redirects").increment(1);
+}
+
+/// Current number of active spacers.
+pub fn active_spacers(count: usize) {
+    #[cfg(feature = "metrics")]
+    ::metrics::gauge!("niri_spacer.active_spacers").set(count as f64);
+    #[cfg(not(feature = "metrics"))]
+    let _ = count;
+}
+
+/// Starts the Prometheus exporter serving `/metrics` on `addr`.
+#[cfg(feature = "metrics")]
+pub fn install_prometheus(addr: std::net::SocketAddr) -> Result<()> {
+    metrics_exporter_prometheus::PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .install()
+        .map_err(|e| {
+            crate::NiriSpacerError::ConfigParse(format!("metrics exporter failed: {e}"))
+        })
+}
+
+/// Without the feature there is nothing to install.
+#[cfg(not(feature = "metrics"))]
+pub fn install_prometheus(_addr: std::net::SocketAddr) -> Result<()> {
+    Err(crate::NiriSpacerError::ConfigParse(
+        "this build has no metrics support (enable the `metrics` feature)".to_string(),
+    ))
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn exporter_serves_the_instrumented_names() {
+        let port = {
+            let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap().port()
+        };
+        let addr: std::net::SocketAddr = format!("127.0.0.1:{port}").parse().unwrap();
+        install_prometheus(addr).unwrap();
+
+        window_created();
+        correlation_duration(12.5);
+        focus_redirect();
+        active_spacers(3);
+
+        // Plain HTTP/1.0 GET; no client dependency needed.
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.0\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut body = String::new();
+        stream.read_to_string(&mut body).await.unwrap();
+
+        for name in [
+            "niri_spacer_windows_created",
+            "niri_spacer_correlation_duration_ms",
+            "niri_spacer_focus_redirects",
+            "niri_spacer_active_spacers",
+        ] {
+            assert!(body.contains(name), "missing {name} in:\n{body}");
+        }
+    }
+}
@@ -0,0 +1,731 @@
+//! Native Wayland backend: opens the actual spacer windows.
+//!
+//! Wayland dispatch is blocking, so all compositor work runs on a dedicated
+//! thread owning the connection; the async side talks to it over a command
+//! channel and gets results back on oneshot channels.
+
+pub mod surface;
+
+use std::collections::HashMap;
+use std::os::fd::AsRawFd;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+use tokio::sync::{oneshot, watch};
+use tracing::{debug, error, warn};
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{
+    wl_buffer, wl_compositor, wl_registry, wl_shm, wl_shm_pool, wl_surface,
+};
+use wayland_client::{Connection, Dispatch, QueueHandle};
+use wayland_protocols::xdg::shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base};
+
+use crate::backend::{
+    BackendWindowHandle, Color, ManagedWindowRegistry, NativeConfig, NativeWindowInfo,
+    SpacerBackend,
+};
+use crate::error::{NiriSpacerError, Result};
+use crate::health::{self, Health};
+use surface::{BackgroundImage, SurfaceManager};
+
+/// How long the Wayland thread sleeps in poll when idle, so it notices new
+/// commands promptly without spinning.
+const POLL_INTERVAL_MS: i32 = 50;
+/// How long `shutdown` waits for the Wayland thread to terminate.
+const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+enum Command {
+    Create {
+        number: u32,
+        color: Color,
+        reply: oneshot::Sender<Result<BackendWindowHandle>>,
+    },
+    Close {
+        number: u32,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    Redraw {
+        number: u32,
+        color: Color,
+        opacity: f64,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    /// Shutdown that confirms completion on a synchronous channel, for the
+    /// Drop path which cannot await.
+    ShutdownWithAck(mpsc::Sender<()>),
+    SetTitle {
+        number: u32,
+        title: String,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    Shutdown,
+}
+
+/// Spawns and owns the Wayland thread; implements [`SpacerBackend`].
+pub struct NativeWindowManager {
+    config: NativeConfig,
+    commands: mpsc::Sender<Command>,
+    thread: Option<JoinHandle<()>>,
+    health: watch::Receiver<Health>,
+    managed: ManagedWindowRegistry,
+}
+
+impl NativeWindowManager {
+    /// Connects to the Wayland display and starts the backend thread.
+    pub async fn connect(config: NativeConfig) -> Result<Self> {
+        let (commands, command_rx) = mpsc::channel();
+        let (init_tx, init_rx) = oneshot::channel();
+        let (health_tx, health) = watch::channel(Health::Running);
+        let thread_config = config.clone();
+        let thread = std::thread::Builder::new()
+            .name("niri-spacer-wayland".to_string())
+            .spawn(move || wayland_thread(thread_config, command_rx, init_tx, health_tx))?;
+
+        match init_rx.await {
+            Ok(Ok(())) => Ok(Self {
+                config,
+                commands,
+                thread: Some(thread),
+                health,
+                managed: ManagedWindowRegistry::default(),
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(NiriSpacerError::Ipc(
+                "Wayland thread exited before initialization".to_string(),
+            )),
+        }
+    }
+
+    /// The window configuration this backend was started with.
+    pub fn config(&self) -> &NativeConfig {
+        &self.config
+    }
+
+    /// Snapshot of every window this backend manages, including whether
+    /// each has been correlated with a niri window yet. Detached from the
+    /// live state, and served without any IPC round trip.
+    pub fn get_managed_windows(&self) -> Vec<NativeWindowInfo> {
+        self.managed.snapshot()
+    }
+
+    /// Channel reporting the backend thread's state. Flips to
+    /// [`Health::Failed`] if the thread dies, at which point every command
+    /// errors out and the process should reconnect or exit.
+    pub fn health(&self) -> watch::Receiver<Health> {
+        self.health.clone()
+    }
+
+    async fn request<T>(
+        &self,
+        command: Command,
+        reply: oneshot::Receiver<Result<T>>,
+    ) -> Result<T> {
+        self.commands
+            .send(command)
+            .map_err(|_| NiriSpacerError::Ipc("Wayland thread is gone".to_string()))?;
+        reply
+            .await
+            .map_err(|_| NiriSpacerError::Ipc("Wayland thread dropped the reply".to_string()))?
+    }
+}
+
+impl SpacerBackend for NativeWindowManager {
+    async fn create_window(&mut self, number: u32, color: Color) -> Result<BackendWindowHandle> {
+        let (tx, rx) = oneshot::channel();
+        let handle = self
+            .request(
+                Command::Create {
+                    number,
+                    color,
+                    reply: tx,
+                },
+                rx,
+            )
+            .await?;
+        self.managed.insert(number, self.config.app_id.clone());
+        Ok(handle)
+    }
+
+    async fn close_window(&mut self, handle: &BackendWindowHandle) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.request(
+            Command::Close {
+                number: handle.number,
+                reply: tx,
+            },
+            rx,
+        )
+        .await?;
+        self.managed.remove(handle.number);
+        Ok(())
+    }
+
+    async fn set_window_title(&mut self, number: u32, title: String) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.request(
+            Command::SetTitle {
+                number,
+                title,
+                reply: tx,
+            },
+            rx,
+        )
+        .await
+    }
+
+    async fn redraw_window(&mut self, number: u32, color: Color, opacity: f64) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.request(
+            Command::Redraw {
+                number,
+                color,
+                opacity,
+                reply: tx,
+            },
+            rx,
+        )
+        .await
+    }
+
+    fn note_correlated(&mut self, number: u32, niri_window_id: u64) {
+        self.managed.note_correlated(number, niri_window_id);
+    }
+
+    fn note_placed(&mut self, number: u32, workspace_id: u64) {
+        self.managed.note_placed(number, workspace_id);
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        let _ = self.commands.send(Command::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            if !join_thread_with_timeout(thread, SHUTDOWN_TIMEOUT).await {
+                warn!("Wayland thread did not terminate within {SHUTDOWN_TIMEOUT:?}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Joins a thread off the async runtime, bounded by `timeout`. Returns
+/// whether the thread terminated cleanly in time; on timeout the join is
+/// abandoned (the detached thread will still exit once it notices its
+/// command channel is gone, but we stop waiting for it).
+async fn join_thread_with_timeout(thread: JoinHandle<()>, timeout: std::time::Duration) -> bool {
+    let join = tokio::task::spawn_blocking(move || thread.join());
+    matches!(tokio::time::timeout(timeout, join).await, Ok(Ok(Ok(()))))
+}
+
+/// How long `Drop` waits for the Wayland thread to acknowledge shutdown.
+const DROP_ACK_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Signals shutdown and waits (bounded) for the thread's acknowledgement.
+/// Returns whether the ack arrived. Safe from any context, including a
+/// tokio runtime that is itself tearing down: the send never blocks (the
+/// command channel is unbounded) and the wait is hard-capped, so the worst
+/// case is one worker thread pausing half a second.
+fn signal_shutdown_with_ack(
+    commands: &mpsc::Sender<Command>,
+    timeout: std::time::Duration,
+) -> bool {
+    let (ack_tx, ack_rx) = mpsc::channel();
+    if commands.send(Command::ShutdownWithAck(ack_tx)).is_err() {
+        // Thread already gone; nothing to wait for.
+        return true;
+    }
+    ack_rx.recv_timeout(timeout).is_ok()
+}
+
+impl Drop for NativeWindowManager {
+    fn drop(&mut self) {
+        // Dropping without shutdown() (e.g. after a correlation timeout
+        // bubbles an error up) must not leak a live event loop. The thread
+        // may also never see the command if the process is tearing down
+        // around us, so the wait is bounded and merely logged on failure.
+        if self.thread.is_some() && !signal_shutdown_with_ack(&self.commands, DROP_ACK_TIMEOUT) {
+            warn!("Wayland thread did not acknowledge shutdown within {DROP_ACK_TIMEOUT:?}");
+        }
+    }
+}
+
+/// Wayland-side state dispatched by the event queue.
+pub struct AppState {
+    config: NativeConfig,
+    /// Background image decoded once at startup, shared by all surfaces.
+    background: Option<std::sync::Arc<BackgroundImage>>,
+    compositor: wl_compositor::WlCompositor,
+    shm: wl_shm::WlShm,
+    wm_base: xdg_wm_base::XdgWmBase,
+    windows: HashMap<u32, SurfaceManager>,
+    /// Windows waiting for their first configure before we report success.
+    pending: HashMap<u32, oneshot::Sender<Result<BackendWindowHandle>>>,
+}
+
+impl AppState {
+    fn create_window(&mut self, qh: &QueueHandle<Self>, number: u32, color: Color) -> SurfaceManager {
+        let surface = self.compositor.create_surface(qh, ());
+        let xdg_surface = self.wm_base.get_xdg_surface(&surface, qh, number);
+        let toplevel = xdg_surface.get_toplevel(qh, number);
+        toplevel.set_app_id(self.config.app_id.clone());
+        toplevel.set_title(self.config.title_for(number));
+        if self.config.true_minimal {
+            // niri tiles 1-pixel columns just fine; no max-size cap either.
+            toplevel.set_min_size(1, 1);
+        } else if self.config.width > 1 {
+            // The user explicitly asked for a wider spacer; honor it rather
+            // than forcing the defensive floor.
+            toplevel.set_min_size(self.config.width as i32, 1);
+        } else {
+            // Defensive floor for compositors that mishandle tiny toplevels.
+            toplevel.set_min_size(100, 60);
+            toplevel.set_max_size(0, 0);
+        }
+        surface.commit();
+        SurfaceManager {
+            number,
+            surface,
+            xdg_surface,
+            toplevel,
+            shm: self.shm.clone(),
+            color,
+            gradient: self.config.gradient,
+            image: self.background.clone(),
+            opacity: 1.0,
+            width: self.config.width,
+            height: self.config.height,
+            configured: false,
+            last_drawn: None,
+        }
+    }
+}
+
+fn wayland_thread(
+    config: NativeConfig,
+    commands: mpsc::Receiver<Command>,
+    init: oneshot::Sender<Result<()>>,
+    health: watch::Sender<Health>,
+) {
+    // Launched from `spawn-at-startup`, the first connect or roundtrip can
+    // race the compositor's own startup; retry within the configured bounds
+    // before declaring failure.
+    let attempts = config.connect_attempts.max(1);
+    let retry_delay = config.connect_retry_delay;
+    let init_result = retry_blocking(attempts, retry_delay, || init_wayland(config.clone()));
+    let (conn, mut queue, mut state) = match init_result {
+        Ok(parts) => {
+            let _ = init.send(Ok(()));
+            parts
+        }
+        Err(e) => {
+            let _ = health.send(Health::Failed(e.to_string()));
+            let _ = init.send(Err(e));
+            return;
+        }
+    };
+
+    // A panic anywhere in dispatch (e.g. an unwrap on a protocol assumption)
+    // must not take the whole process down, but it also must not die
+    // silently: the daemon would keep running yet never be able to create or
+    // close windows again. Catch it, log it, and flip the health channel so
+    // callers can see the backend is gone.
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        run_event_loop(&conn, &mut queue, &mut state, &commands)
+    }));
+    match result {
+        Ok(Ok(())) => {
+            let _ = health.send(Health::Stopped);
+        }
+        Ok(Err(e)) => {
+            error!(error = %e, "Wayland event loop failed");
+            let _ = health.send(Health::Failed(e.to_string()));
+        }
+        Err(payload) => {
+            health::record_panic();
+            let message = health::panic_message(payload.as_ref());
+            error!(panic = %message, "Wayland event loop panicked");
+            let _ = health.send(Health::Failed(format!("panic: {message}")));
+        }
+    }
+}
+
+/// The blocking command/dispatch loop owning the Wayland connection.
+fn run_event_loop(
+    conn: &Connection,
+    queue: &mut wayland_client::EventQueue<AppState>,
+    state: &mut AppState,
+    commands: &mpsc::Receiver<Command>,
+) -> Result<()> {
+    let qh = queue.handle();
+
+    loop {
+        // Drain all pending commands before going back to the socket.
+        loop {
+            match commands.try_recv() {
+                Ok(Command::Create {
+                    number,
+                    color,
+                    reply,
+                }) => {
+                    let window = state.create_window(&qh, number, color);
+                    state.windows.insert(number, window);
+                    state.pending.insert(number, reply);
+                }
+                Ok(Command::Close { number, reply }) => {
+                    let result = match state.windows.remove(&number) {
+                        Some(mut window) => {
+                            window.destroy();
+                            Ok(())
+                        }
+                        None => Err(NiriSpacerError::Ipc(format!(
+                            "no native window numbered {number}"
+                        ))),
+                    };
+                    let _ = reply.send(result);
+                }
+                Ok(Command::Redraw {
+                    number,
+                    color,
+                    opacity,
+                    reply,
+                }) => {
+                    let result = match state.windows.get_mut(&number) {
+                        Some(window) => {
+                            window.set_appearance(color, opacity);
+                            if window.configured {
+                                window.draw(&qh)
+                            } else {
+                                Ok(())
+                            }
+                        }
+                        None => Err(NiriSpacerError::Ipc(format!(
+                            "no native window numbered {number}"
+                        ))),
+                    };
+                    let _ = reply.send(result);
+                }
+                Ok(Command::SetTitle {
+                    number,
+                    title,
+                    reply,
+                }) => {
+                    let result = match state.windows.get_mut(&number) {
+                        Some(window) => {
+                            window.toplevel.set_title(title);
+                            window.surface.commit();
+                            Ok(())
+                        }
+                        None => Err(NiriSpacerError::Ipc(format!(
+                            "no native window numbered {number}"
+                        ))),
+                    };
+                    let _ = reply.send(result);
+                }
+                Ok(Command::ShutdownWithAck(ack)) => {
+                    for (_, mut window) in state.windows.drain() {
+                        window.destroy();
+                    }
+                    let _ = conn.flush();
+                    let _ = ack.send(());
+                    debug!("Wayland thread shutting down (acked)");
+                    return Ok(());
+                }
+                Ok(Command::Shutdown) | Err(mpsc::TryRecvError::Disconnected) => {
+                    for (_, mut window) in state.windows.drain() {
+                        window.destroy();
+                    }
+                    let _ = conn.flush();
+                    debug!("Wayland thread shutting down");
+                    return Ok(());
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+            }
+        }
+
+        pump_events(conn, queue, state)?;
+    }
+}
+
+type WaylandParts = (Connection, wayland_client::EventQueue<AppState>, AppState);
+
+/// Runs `attempt` up to `attempts` times with `delay` between failures,
+/// returning the first success or the last error. Blocking by design: it
+/// runs on the dedicated Wayland thread.
+fn retry_blocking<T>(
+    attempts: u32,
+    delay: std::time::Duration,
+    mut attempt: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let mut last_err = None;
+    for n in 1..=attempts {
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                if n < attempts {
+                    warn!(attempt = n, error = %e, "Wayland init failed; retrying");
+                    std::thread::sleep(delay);
+                }
+                last_err = Some(e);
+            }
+        }
+    }
+    Err(last_err.expect("at least one attempt ran"))
+}
+
+fn init_wayland(config: NativeConfig) -> Result<WaylandParts> {
+    let conn = Connection::connect_to_env()
+        .map_err(|e| NiriSpacerError::Ipc(format!("cannot connect to Wayland display: {e}")))?;
+    let (globals, queue) = registry_queue_init::<AppState>(&conn)
+        .map_err(|e| NiriSpacerError::Ipc(format!("Wayland registry init failed: {e}")))?;
+    let qh = queue.handle();
+
+    let bind_err =
+        |name: &str| NiriSpacerError::Ipc(format!("compositor does not advertise {name}"));
+    let compositor = globals
+        .bind(&qh, 4..=6, ())
+        .map_err(|_| bind_err("wl_compositor"))?;
+    let shm = globals.bind(&qh, 1..=2, ()).map_err(|_| bind_err("wl_shm"))?;
+    let wm_base = globals
+        .bind(&qh, 1..=6, ())
+        .map_err(|_| bind_err("xdg_wm_base"))?;
+
+    // Decode the background image once, up front; a bad file demotes the
+    // surfaces to the solid-color fill rather than failing startup.
+    let background = config.background_image.as_deref().and_then(|path| {
+        match surface::load_background_image(path) {
+            Ok(image) => Some(std::sync::Arc::new(image)),
+            Err(e) => {
+                warn!(error = %e, "falling back to solid color");
+                None
+            }
+        }
+    });
+
+    let state = AppState {
+        config,
+        background,
+        compositor,
+        shm,
+        wm_base,
+        windows: HashMap::new(),
+        pending: HashMap::new(),
+    };
+    Ok((conn, queue, state))
+}
+
+/// One flush/poll/read/dispatch cycle with a short poll timeout.
+fn pump_events(
+    conn: &Connection,
+    queue: &mut wayland_client::EventQueue<AppState>,
+    state: &mut AppState,
+) -> Result<()> {
+    let io_err = |e: wayland_client::backend::WaylandError| {
+        NiriSpacerError::Ipc(format!("Wayland connection error: {e}"))
+    };
+
+    queue.flush().map_err(io_err)?;
+    queue.dispatch_pending(state).map_err(|e| {
+        NiriSpacerError::Ipc(format!("Wayland dispatch error: {e}"))
+    })?;
+
+    if let Some(guard) = conn.prepare_read() {
+        let mut pollfd = libc::pollfd {
+            fd: guard.connection_fd().as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: pollfd points at a single valid struct for the call.
+        let ready = unsafe { libc::poll(&mut pollfd, 1, POLL_INTERVAL_MS) };
+        if ready > 0 && pollfd.revents & libc::POLLIN != 0 {
+            guard.read().map_err(io_err)?;
+            queue.dispatch_pending(state).map_err(|e| {
+                NiriSpacerError::Ipc(format!("Wayland dispatch error: {e}"))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for AppState {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<xdg_wm_base::XdgWmBase, ()> for AppState {
+    fn event(
+        _: &mut Self,
+        wm_base: &xdg_wm_base::XdgWmBase,
+        event: xdg_wm_base::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<xdg_surface::XdgSurface, u32> for AppState {
+    fn event(
+        state: &mut Self,
+        xdg_surface: &xdg_surface::XdgSurface,
+        event: xdg_surface::Event,
+        number: &u32,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_surface::Event::Configure { serial } = event {
+            xdg_surface.ack_configure(serial);
+            if let Some(window) = state.windows.get_mut(number) {
+                let first = !window.configured;
+                window.configured = true;
+                let drawn = window.draw(qh);
+                if first {
+                    if let Some(reply) = state.pending.remove(number) {
+                        let result = drawn.map(|()| BackendWindowHandle {
+                            number: *number,
+                            title: state.config.title_for(*number),
+                        });
+                        let _ = reply.send(result);
+                    }
+                } else if let Err(e) = drawn {
+                    warn!(number, error = %e, "redraw after configure failed");
+                }
+            }
+        }
+    }
+}
+
+impl Dispatch<xdg_toplevel::XdgToplevel, u32> for AppState {
+    fn event(
+        state: &mut Self,
+        _: &xdg_toplevel::XdgToplevel,
+        event: xdg_toplevel::Event,
+        number: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            xdg_toplevel::Event::Configure { width, height, .. } => {
+                if let Some(window) = state.windows.get_mut(number) {
+                    window.set_pending_size(width, height);
+                }
+            }
+            xdg_toplevel::Event::Close => {
+                debug!(number, "compositor asked spacer window to close");
+                if let Some(mut window) = state.windows.remove(number) {
+                    window.destroy();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_buffer::WlBuffer, ()> for AppState {
+    fn event(
+        _: &mut Self,
+        buffer: &wl_buffer::WlBuffer,
+        event: wl_buffer::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            buffer.destroy();
+        }
+    }
+}
+
+wayland_client::delegate_noop!(AppState: ignore wl_compositor::WlCompositor);
+wayland_client::delegate_noop!(AppState: ignore wl_surface::WlSurface);
+wayland_client::delegate_noop!(AppState: ignore wl_shm::WlShm);
+wayland_client::delegate_noop!(AppState: ignore wl_shm_pool::WlShmPool);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn retry_succeeds_after_transient_failures() {
+        let mut calls = 0;
+        let result = retry_blocking(3, Duration::from_millis(1), || {
+            calls += 1;
+            if calls < 2 {
+                Err(NiriSpacerError::Ipc("compositor not ready".to_string()))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[test]
+    fn retry_gives_up_after_the_configured_attempts() {
+        let mut calls = 0;
+        let result: Result<()> = retry_blocking(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(NiriSpacerError::Ipc("still not ready".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn drop_signal_is_acked_by_a_live_drain_loop() {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            // Mimics the event loop's command drain.
+            while let Ok(command) = rx.recv() {
+                if let Command::ShutdownWithAck(ack) = command {
+                    let _ = ack.send(());
+                    return;
+                }
+            }
+        });
+        let start = std::time::Instant::now();
+        assert!(signal_shutdown_with_ack(&tx, Duration::from_millis(500)));
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn drop_signal_never_hangs_on_a_wedged_thread() {
+        // A receiver that exists but never drains: the wait must cap out.
+        let (tx, _rx) = mpsc::channel();
+        let start = std::time::Instant::now();
+        assert!(!signal_shutdown_with_ack(&tx, Duration::from_millis(100)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn drop_signal_returns_immediately_when_the_thread_is_gone() {
+        let (tx, rx) = mpsc::channel();
+        drop(rx);
+        let start = std::time::Instant::now();
+        assert!(signal_shutdown_with_ack(&tx, Duration::from_millis(500)));
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn join_returns_true_for_a_terminating_thread() {
+        let thread = std::thread::spawn(|| {});
+        assert!(join_thread_with_timeout(thread, Duration::from_secs(2)).await);
+    }
+
+    #[tokio::test]
+    async fn join_gives_up_on_a_stuck_thread() {
+        // Simulates an event loop wedged in dispatch: shutdown must stop
+        // waiting after the timeout instead of hanging.
+        let thread = std::thread::spawn(|| std::thread::sleep(Duration::from_secs(2)));
+        let start = std::time::Instant::now();
+        assert!(!join_thread_with_timeout(thread, Duration::from_millis(100)).await);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,542 @@
+//! Per-window Wayland surface state and shm drawing.
+
+use std::os::fd::{AsFd, OwnedFd};
+
+use wayland_client::protocol::{wl_shm, wl_surface};
+use wayland_client::QueueHandle;
+use wayland_protocols::xdg::shell::client::{xdg_surface, xdg_toplevel};
+
+use crate::backend::Color;
+use crate::error::{NiriSpacerError, Result};
+use crate::native::AppState;
+
+/// Size and byte layout of one ARGB8888 shm buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct BufferSpec {
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+    pub size: usize,
+}
+
+impl BufferSpec {
+    /// Layout for a surface of the given dimensions; degenerate zero sizes
+    /// are clamped to one pixel.
+    pub(super) fn for_size(width: u32, height: u32) -> Self {
+        let width = width.max(1);
+        let height = height.max(1);
+        let stride = width * 4;
+        Self {
+            width,
+            height,
+            stride,
+            size: (stride * height) as usize,
+        }
+    }
+
+    /// Layout actually drawn for a surface of the given dimensions: widths
+    /// under four pixels get a plain 1x1 buffer. There is nothing to see at
+    /// that width, and attaching 1x1 keeps the surface mapped without any
+    /// risk of stride or division trouble on degenerate sizes.
+    pub(super) fn for_drawing(width: u32, height: u32) -> Self {
+        if width < 4 {
+            Self::for_size(1, 1)
+        } else {
+            Self::for_size(width, height)
+        }
+    }
+}
+
+/// Whether a configure requires attaching a freshly allocated buffer: the
+/// first draw always does, and so does any size change since the last draw.
+pub(super) fn needs_redraw(last_drawn: Option<BufferSpec>, next: BufferSpec) -> bool {
+    last_drawn != Some(next)
+}
+
+/// A decoded RGBA background image, shared across surfaces.
+#[derive(Debug, Clone)]
+pub struct BackgroundImage {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed RGBA rows.
+    pub rgba: Vec<u8>,
+}
+
+/// Decodes a PNG into RGBA, ready for scaling at draw time.
+pub(super) fn load_background_image(path: &std::path::Path) -> Result<BackgroundImage> {
+    let decoded = image::open(path)
+        .map_err(|e| {
+            NiriSpacerError::ConfigParse(format!(
+                "cannot decode background image {}: {e}",
+                path.display()
+            ))
+        })?
+        .to_rgba8();
+    Ok(BackgroundImage {
+        width: decoded.width(),
+        height: decoded.height(),
+        rgba: decoded.into_raw(),
+    })
+}
+
+/// Nearest-neighbor sample of the image for target pixel (x, y) in a
+/// target of `width` x `height`, as straight (non-premultiplied) RGBA.
+pub(super) fn sample_nearest(
+    img: &BackgroundImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> [u8; 4] {
+    let sx = (u64::from(x) * u64::from(img.width) / u64::from(width.max(1))) as u32;
+    let sy = (u64::from(y) * u64::from(img.height) / u64::from(height.max(1))) as u32;
+    let sx = sx.min(img.width.saturating_sub(1));
+    let sy = sy.min(img.height.saturating_sub(1));
+    let offset = ((sy * img.width + sx) * 4) as usize;
+    img.rgba[offset..offset + 4].try_into().expect("4-byte pixel")
+}
+
+/// Color of one row of a vertical linear gradient: each channel moves
+/// linearly from `top` at y = 0 to `bottom` at y = height - 1.
+pub(super) fn gradient_color_at(top: Color, bottom: Color, y: u32, height: u32) -> Color {
+    if height <= 1 {
+        return top;
+    }
+    let lerp = |a: u8, b: u8| {
+        let a = i32::from(a);
+        let b = i32::from(b);
+        (a + (b - a) * y as i32 / (height as i32 - 1)) as u8
+    };
+    Color::new(
+        lerp(top.r, bottom.r),
+        lerp(top.g, bottom.g),
+        lerp(top.b, bottom.b),
+    )
+}
+
+/// Owns one spacer window's Wayland objects and redraws it on configure.
+pub struct SurfaceManager {
+    pub(super) number: u32,
+    pub(super) surface: wl_surface::WlSurface,
+    pub(super) xdg_surface: xdg_surface::XdgSurface,
+    pub(super) toplevel: xdg_toplevel::XdgToplevel,
+    pub(super) shm: wl_shm::WlShm,
+    pub(super) color: Color,
+    /// Vertical gradient overriding the solid color when set.
+    pub(super) gradient: Option<(Color, Color)>,
+    /// Decoded background image; wins over gradient and solid fills.
+    pub(super) image: Option<std::sync::Arc<BackgroundImage>>,
+    /// 0.0 = fully transparent, 1.0 = opaque. Premultiplied into the pixels.
+    pub(super) opacity: f64,
+    pub(super) width: u32,
+    pub(super) height: u32,
+    pub(super) configured: bool,
+    /// Layout of the most recently attached buffer, to detect resizes that
+    /// need a reallocation.
+    pub(super) last_drawn: Option<BufferSpec>,
+}
+
+/// Applies an `xdg_toplevel` configure size to the current dimensions:
+/// zero or negative values mean "pick your own" and leave that axis alone.
+pub(super) fn apply_configure_size(current: (u32, u32), width: i32, height: i32) -> (u32, u32) {
+    let (mut w, mut h) = current;
+    if width > 0 {
+        w = width as u32;
+    }
+    if height > 0 {
+        h = height as u32;
+    }
+    (w, h)
+}
+
+impl SurfaceManager {
+    /// Records a size from an `xdg_toplevel` configure.
+    pub(super) fn set_pending_size(&mut self, width: i32, height: i32) {
+        (self.width, self.height) = apply_configure_size((self.width, self.height), width, height);
+    }
+
+    /// The buffer layout the current size calls for.
+    pub(super) fn buffer_spec(&self) -> BufferSpec {
+        BufferSpec::for_drawing(self.width, self.height)
+    }
+
+    /// Attaches a freshly drawn buffer and commits the surface. A resize
+    /// since the last draw reallocates the buffer at the new size; a
+    /// configure that changed nothing is a no-op so we do not thrash shm
+    /// pools on state-only configures.
+    pub(super) fn draw(&mut self, qh: &QueueHandle<AppState>) -> Result<()> {
+        let spec = self.buffer_spec();
+        if !needs_redraw(self.last_drawn, spec) {
+            return Ok(());
+        }
+
+        let fd = create_shm_fd(spec.size)?;
+        if let Some(image) = &self.image {
+            fill_image(&fd, spec, image, self.opacity)?;
+        } else {
+            match self.gradient {
+                Some((top, bottom)) => {
+                    fill_gradient(&fd, spec, top, bottom, self.opacity)?;
+                }
+                None => fill_pixels(
+                    &fd,
+                    spec.size,
+                    premultiplied_pixel_bytes(self.color, self.opacity),
+                )?,
+            }
+        }
+
+        let pool = self.shm.create_pool(fd.as_fd(), spec.size as i32, qh, ());
+        let buffer = pool.create_buffer(
+            0,
+            spec.width as i32,
+            spec.height as i32,
+            spec.stride as i32,
+            wl_shm::Format::Argb8888,
+            qh,
+            (),
+        );
+        pool.destroy();
+
+        self.surface.attach(Some(&buffer), 0, 0);
+        self.surface
+            .damage_buffer(0, 0, spec.width as i32, spec.height as i32);
+        self.surface.commit();
+        self.last_drawn = Some(spec);
+        Ok(())
+    }
+
+    /// Changes the window's appearance and forces the next draw to attach
+    /// a fresh buffer even if the size is unchanged.
+    pub(super) fn set_appearance(&mut self, color: Color, opacity: f64) {
+        self.color = color;
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self.last_drawn = None;
+    }
+
+    /// Destroys the window's Wayland objects.
+    pub(super) fn destroy(&mut self) {
+        tracing::debug!(number = self.number, "destroying native window");
+        self.toplevel.destroy();
+        self.xdg_surface.destroy();
+        self.surface.destroy();
+    }
+}
+
+/// Creates an anonymous sealed memfd of the given size.
+fn create_shm_fd(size: usize) -> Result<OwnedFd> {
+    use std::os::fd::FromRawFd;
+
+    // SAFETY: memfd_create returns a fresh fd we immediately take ownership
+    // of; the name is a valid NUL-terminated string.
+    let raw = unsafe {
+        libc::memfd_create(
+            c"niri-spacer-shm".as_ptr(),
+            libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+        )
+    };
+    if raw < 0 {
+        return Err(NiriSpacerError::Io(std::io::Error::last_os_error()));
+    }
+    // SAFETY: raw is a valid, owned fd as checked above.
+    let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+    // SAFETY: ftruncate on an fd we own.
+    if unsafe { libc::ftruncate(raw, size as libc::off_t) } < 0 {
+        return Err(NiriSpacerError::Io(std::io::Error::last_os_error()));
+    }
+    Ok(fd)
+}
+
+/// Fills the whole shm file with copies of the given pixel.
+fn fill_pixels(fd: &OwnedFd, size: usize, pixel: [u8; 4]) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: mapping a region we just sized with ftruncate; unmapped below.
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd.as_raw_fd(),
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(NiriSpacerError::Io(std::io::Error::last_os_error()));
+    }
+
+    // SAFETY: ptr is a valid mapping of `size` bytes, and size is a multiple
+    // of 4 by construction (stride = width * 4).
+    unsafe {
+        let bytes = std::slice::from_raw_parts_mut(ptr.cast::<u8>(), size);
+        for chunk in bytes.chunks_exact_mut(4) {
+            chunk.copy_from_slice(&pixel);
+        }
+        libc::munmap(ptr, size);
+    }
+    Ok(())
+}
+
+/// Fills the shm file with a vertical linear gradient, row by row.
+fn fill_gradient(
+    fd: &OwnedFd,
+    spec: BufferSpec,
+    top: Color,
+    bottom: Color,
+    opacity: f64,
+) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: mapping a region we just sized with ftruncate; unmapped below.
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            spec.size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd.as_raw_fd(),
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(NiriSpacerError::Io(std::io::Error::last_os_error()));
+    }
+
+    // SAFETY: ptr is a valid mapping of `spec.size` bytes laid out as
+    // `height` rows of `stride` bytes.
+    unsafe {
+        let bytes = std::slice::from_raw_parts_mut(ptr.cast::<u8>(), spec.size);
+        for (y, row) in bytes.chunks_exact_mut(spec.stride as usize).enumerate() {
+            let color = gradient_color_at(top, bottom, y as u32, spec.height);
+            let pixel = premultiplied_pixel_bytes(color, opacity);
+            for chunk in row.chunks_exact_mut(4) {
+                chunk.copy_from_slice(&pixel);
+            }
+        }
+        libc::munmap(ptr, spec.size);
+    }
+    Ok(())
+}
+
+/// Blits the image into the shm file, nearest-neighbor scaled to the
+/// buffer size, premultiplying both the image's own alpha and the surface
+/// opacity.
+fn fill_image(fd: &OwnedFd, spec: BufferSpec, img: &BackgroundImage, opacity: f64) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: mapping a region we just sized with ftruncate; unmapped below.
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            spec.size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd.as_raw_fd(),
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(NiriSpacerError::Io(std::io::Error::last_os_error()));
+    }
+
+    // SAFETY: ptr is a valid mapping of `spec.size` bytes laid out as
+    // `height` rows of `stride` bytes.
+    unsafe {
+        let bytes = std::slice::from_raw_parts_mut(ptr.cast::<u8>(), spec.size);
+        for (y, row) in bytes.chunks_exact_mut(spec.stride as usize).enumerate() {
+            for (x, chunk) in row.chunks_exact_mut(4).enumerate() {
+                let [r, g, b, a] = sample_nearest(img, x as u32, y as u32, spec.width, spec.height);
+                let alpha = (f64::from(a) * opacity.clamp(0.0, 1.0)).round() as u16;
+                let mul = |c: u8| ((u16::from(c) * alpha) / 255) as u8;
+                chunk.copy_from_slice(&[mul(b), mul(g), mul(r), alpha as u8]);
+            }
+        }
+        libc::munmap(ptr, spec.size);
+    }
+    Ok(())
+}
+
+/// The in-memory bytes of one opaque `wl_shm` `Argb8888` pixel.
+///
+/// Wayland shm formats are defined in *little-endian* 32-bit order
+/// regardless of host endianness, so the value 0xAARRGGBB must land in
+/// memory as the byte sequence [B, G, R, A]. Writing the bytes explicitly
+/// (rather than a host-endian `u32` store) keeps this correct on big-endian
+/// hosts too.
+#[cfg(test)] // production fills go through the premultiplied variant
+fn pixel_bytes(color: Color) -> [u8; 4] {
+    [color.b, color.g, color.r, 0xff]
+}
+
+/// Like [`pixel_bytes`] but with an alpha channel, premultiplied as Wayland
+/// expects. Opacity 1.0 is identical to [`pixel_bytes`]; 0.0 is fully
+/// transparent black.
+fn premultiplied_pixel_bytes(color: Color, opacity: f64) -> [u8; 4] {
+    let alpha = (opacity.clamp(0.0, 1.0) * 255.0).round() as u16;
+    let mul = |c: u8| ((u16::from(c) * alpha) / 255) as u8;
+    [mul(color.b), mul(color.g), mul(color.r), alpha as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_configure_draws_a_new_buffer_of_the_right_size() {
+        // Mapped at 1x1...
+        let first = BufferSpec::for_size(1, 1);
+        assert!(needs_redraw(None, first));
+
+        // ...then niri resizes the column to 4x600: the old buffer must be
+        // thrown away and a new one allocated at the new size.
+        let resized = BufferSpec::for_size(4, 600);
+        assert!(needs_redraw(Some(first), resized));
+        assert_eq!(resized.stride, 16);
+        assert_eq!(resized.size, 16 * 600);
+    }
+
+    #[test]
+    fn tiny_png_decodes_into_the_expected_buffer() {
+        // A 2x1 image: opaque red next to semi-transparent blue.
+        let mut img = image::RgbaImage::new(2, 1);
+        img.put_pixel(0, 0, image::Rgba([0xff, 0x00, 0x00, 0xff]));
+        img.put_pixel(1, 0, image::Rgba([0x00, 0x00, 0xff, 0x80]));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bg.png");
+        img.save(&path).unwrap();
+
+        let decoded = load_background_image(&path).unwrap();
+        assert_eq!((decoded.width, decoded.height), (2, 1));
+        assert_eq!(
+            decoded.rgba,
+            vec![0xff, 0x00, 0x00, 0xff, 0x00, 0x00, 0xff, 0x80]
+        );
+    }
+
+    #[test]
+    fn undecodable_image_is_a_config_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bg.png");
+        std::fs::write(&path, b"not a png").unwrap();
+        match load_background_image(&path).unwrap_err() {
+            NiriSpacerError::ConfigParse(message) => {
+                assert!(message.contains("cannot decode"), "{message}");
+            }
+            other => panic!("expected ConfigParse, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn nearest_neighbor_scaling_picks_the_right_source_pixels() {
+        let img = BackgroundImage {
+            width: 2,
+            height: 1,
+            rgba: vec![0xff, 0x00, 0x00, 0xff, 0x00, 0x00, 0xff, 0xff],
+        };
+        // Scaled to 4 wide: left half red, right half blue.
+        assert_eq!(sample_nearest(&img, 0, 0, 4, 2), [0xff, 0, 0, 0xff]);
+        assert_eq!(sample_nearest(&img, 1, 0, 4, 2), [0xff, 0, 0, 0xff]);
+        assert_eq!(sample_nearest(&img, 2, 0, 4, 2), [0, 0, 0xff, 0xff]);
+        assert_eq!(sample_nearest(&img, 3, 1, 4, 2), [0, 0, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn gradient_endpoints_hit_the_requested_colors() {
+        let top = Color::new(0x10, 0x20, 0x30);
+        let bottom = Color::new(0x50, 0x40, 0x00);
+        let height = 600;
+
+        assert_eq!(gradient_color_at(top, bottom, 0, height), top);
+        assert_eq!(gradient_color_at(top, bottom, height - 1, height), bottom);
+
+        // Midpoint lands halfway per channel.
+        let mid = gradient_color_at(top, bottom, (height - 1) / 2, height);
+        assert!(mid.r.abs_diff(0x30) <= 1, "mid {mid}");
+        assert!(mid.g.abs_diff(0x30) <= 1, "mid {mid}");
+    }
+
+    #[test]
+    fn degenerate_gradient_heights_stay_at_top() {
+        let top = Color::new(1, 2, 3);
+        let bottom = Color::new(9, 9, 9);
+        assert_eq!(gradient_color_at(top, bottom, 0, 1), top);
+        assert_eq!(gradient_color_at(top, bottom, 0, 0), top);
+    }
+
+    #[test]
+    fn argb8888_bytes_are_little_endian_bgra_in_memory() {
+        // For (r,g,b) = (0x12,0x34,0x56), Argb8888 (= LE 0xFF123456) must be
+        // the byte sequence B,G,R,A in the buffer.
+        assert_eq!(
+            pixel_bytes(Color::new(0x12, 0x34, 0x56)),
+            [0x56, 0x34, 0x12, 0xff]
+        );
+    }
+
+    #[test]
+    fn premultiplied_pixels_cover_the_opacity_range() {
+        let color = Color::new(0x12, 0x34, 0x56);
+        assert_eq!(premultiplied_pixel_bytes(color, 1.0), pixel_bytes(color));
+        assert_eq!(premultiplied_pixel_bytes(color, 0.0), [0, 0, 0, 0]);
+        // Half opacity: every channel scaled by the same alpha.
+        let half = premultiplied_pixel_bytes(Color::new(0xff, 0xff, 0xff), 0.5);
+        assert_eq!(half, [0x80, 0x80, 0x80, 0x80]);
+    }
+
+    #[test]
+    fn one_by_one_buffer_math() {
+        let spec = BufferSpec::for_size(1, 1);
+        assert_eq!((spec.width, spec.height, spec.stride, spec.size), (1, 1, 4, 4));
+    }
+
+    #[test]
+    fn one_by_tall_buffer_math() {
+        let spec = BufferSpec::for_size(1, 1080);
+        assert_eq!(spec.stride, 4);
+        assert_eq!(spec.size, 4 * 1080);
+    }
+
+    #[test]
+    fn sub_four_pixel_widths_draw_a_placeholder_pixel() {
+        // A 1x900 or 3x900 column is invisible anyway; attach 1x1 to keep
+        // the surface mapped.
+        assert_eq!(BufferSpec::for_drawing(1, 900), BufferSpec::for_size(1, 1));
+        assert_eq!(BufferSpec::for_drawing(3, 900), BufferSpec::for_size(1, 1));
+        assert_eq!(BufferSpec::for_drawing(4, 900), BufferSpec::for_size(4, 900));
+    }
+
+    #[test]
+    fn zero_sizes_are_clamped() {
+        let spec = BufferSpec::for_size(0, 0);
+        assert_eq!((spec.width, spec.height), (1, 1));
+    }
+
+    #[test]
+    fn configure_sizes_only_override_positive_axes() {
+        assert_eq!(apply_configure_size((1, 1), 4, 600), (4, 600));
+        // Zero means "pick your own": keep the current value per axis.
+        assert_eq!(apply_configure_size((4, 600), 0, 0), (4, 600));
+        assert_eq!(apply_configure_size((4, 600), 8, 0), (8, 600));
+        assert_eq!(apply_configure_size((4, 600), 0, 300), (4, 300));
+        assert_eq!(apply_configure_size((4, 600), -1, -1), (4, 600));
+    }
+
+    #[test]
+    fn configure_then_spec_then_redraw_roundtrip() {
+        // The full surface-lifecycle state machine, minus the Wayland
+        // objects: a resize configure flows into a new buffer spec which
+        // flows into a redraw decision.
+        let size = apply_configure_size((1, 1), 6, 480);
+        let spec = BufferSpec::for_drawing(size.0, size.1);
+        assert_eq!((spec.width, spec.height), (6, 480));
+        assert!(needs_redraw(Some(BufferSpec::for_drawing(1, 1)), spec));
+        assert!(!needs_redraw(Some(spec), spec));
+    }
+
+    #[test]
+    fn state_only_configure_does_not_redraw() {
+        let spec = BufferSpec::for_size(2, 300);
+        assert!(!needs_redraw(Some(spec), spec));
+    }
+}
@@ -0,0 +1,74 @@
+//! Stub native backend, compiled when the `native` feature is off.
+//!
+//! Headless CI machines and library consumers that only need the IPC layer
+//! can build without any Wayland dependency; everything that would open a
+//! real window reports [`NiriSpacerError::NativeNotSupported`] instead of
+//! failing at link or deep inside a runtime connect.
+
+use crate::backend::{
+    BackendWindowHandle, Color, ManagedWindowRegistry, NativeConfig, NativeWindowInfo,
+    SpacerBackend,
+};
+use crate::error::{NiriSpacerError, Result};
+use crate::health::Health;
+use tokio::sync::watch;
+
+/// Placeholder for the Wayland backend; every operation fails with
+/// [`NiriSpacerError::NativeNotSupported`].
+#[derive(Debug)]
+pub struct NativeWindowManager {
+    config: NativeConfig,
+    health: watch::Receiver<Health>,
+    managed: ManagedWindowRegistry,
+}
+
+impl NativeWindowManager {
+    /// Always fails: this build has no Wayland backend.
+    pub async fn connect(_config: NativeConfig) -> Result<Self> {
+        Err(NiriSpacerError::NativeNotSupported)
+    }
+
+    /// The window configuration this backend was started with.
+    pub fn config(&self) -> &NativeConfig {
+        &self.config
+    }
+
+    /// Mirrors the real backend's managed-window snapshot; always empty
+    /// since no window can ever be created.
+    pub fn get_managed_windows(&self) -> Vec<NativeWindowInfo> {
+        self.managed.snapshot()
+    }
+
+    /// Mirrors the real backend's health channel.
+    pub fn health(&self) -> watch::Receiver<Health> {
+        self.health.clone()
+    }
+}
+
+impl SpacerBackend for NativeWindowManager {
+    async fn create_window(&mut self, _number: u32, _color: Color) -> Result<BackendWindowHandle> {
+        Err(NiriSpacerError::NativeNotSupported)
+    }
+
+    async fn close_window(&mut self, _handle: &BackendWindowHandle) -> Result<()> {
+        Err(NiriSpacerError::NativeNotSupported)
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn connect_reports_native_not_supported() {
+        match NativeWindowManager::connect(NativeConfig::default()).await {
+            Err(NiriSpacerError::NativeNotSupported) => {}
+            Err(other) => panic!("expected NativeNotSupported, got {other:?}"),
+            Ok(_) => panic!("stub connect must not succeed"),
+        }
+    }
+}
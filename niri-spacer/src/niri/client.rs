@@ -0,0 +1,628 @@
+//! Async client for the niri IPC socket.
+//!
+//! niri serves one request per connection, so the client holds only the
+//! socket path and dials a fresh connection for each request. The event
+//! stream is the exception: it keeps its connection open for the lifetime of
+//! the subscription.
+
+use std::path::{Path, PathBuf};
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use tracing::trace;
+
+use crate::error::{NiriSpacerError, Result};
+use crate::niri::types::{
+    Action, NiriEvent, Reply, Request, Response, Window, Workspace, WorkspaceReference,
+};
+
+/// Upper bound on one reply or event line. A session with hundreds of
+/// windows legitimately produces replies of several hundred kilobytes, so
+/// the cap is generous; what it protects against is a garbled or malicious
+/// stream feeding an unbounded line.
+pub const MAX_LINE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Reads one newline-terminated line, bounded by `max` bytes. `None` means
+/// end of stream. Exceeding the cap fails with a clear error instead of
+/// buffering without limit; if a real session ever hits it, the remedy is
+/// raising [`MAX_LINE_BYTES`], not trusting unbounded input.
+async fn read_bounded_line<R>(reader: &mut R, max: usize) -> Result<Option<String>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = String::new();
+    let read = reader
+        .take(max as u64 + 1)
+        .read_line(&mut line)
+        .await
+        .map_err(map_connection_loss)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if read > max {
+        return Err(NiriSpacerError::Ipc(format!(
+            "response exceeded {max} bytes; refusing to buffer further"
+        )));
+    }
+    Ok(Some(line))
+}
+
+/// Client for niri's IPC endpoint.
+#[derive(Debug, Clone)]
+pub struct NiriClient {
+    socket_path: PathBuf,
+    /// Live connections opened by this client and its clones. Makes fd
+    /// leaks from connect-per-call loops observable; hot paths should
+    /// clone and reuse one client rather than constructing new ones.
+    open_connections: std::sync::Arc<std::sync::atomic::AtomicU64>,
+}
+
+/// Decrements the owning client's connection count when the connection it
+/// tracks goes away.
+#[derive(Debug)]
+struct ConnectionGuard {
+    counter: std::sync::Arc<std::sync::atomic::AtomicU64>,
+}
+
+impl ConnectionGuard {
+    fn open(counter: &std::sync::Arc<std::sync::atomic::AtomicU64>) -> Self {
+        counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Self {
+            counter: std::sync::Arc::clone(counter),
+        }
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+        trace!("niri connection closed");
+    }
+}
+
+impl NiriClient {
+    /// Creates a client for the given socket path. No connection is made
+    /// until the first request.
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+            open_connections: std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0)),
+        }
+    }
+
+    /// Number of currently open connections across this client and all of
+    /// its clones (per-request connections plus live event streams).
+    pub fn open_connection_count(&self) -> u64 {
+        self.open_connections
+            .load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// The socket path this client dials.
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Sends one request over a fresh connection and decodes the reply.
+    pub async fn send(&self, request: &Request) -> Result<Response> {
+        let stream = UnixStream::connect(&self.socket_path).await?;
+        let _guard = ConnectionGuard::open(&self.open_connections);
+        let mut stream = BufReader::new(stream);
+
+        let mut line = serde_json::to_string(request)?;
+        trace!(request = %line, "sending niri request");
+        line.push('\n');
+        stream
+            .get_mut()
+            .write_all(line.as_bytes())
+            .await
+            .map_err(map_connection_loss)?;
+
+        let Some(reply) = read_bounded_line(&mut stream, MAX_LINE_BYTES).await? else {
+            // niri went away (restart) after accepting the connection.
+            return Err(NiriSpacerError::ConnectionLost(
+                "connection closed before a reply arrived".to_string(),
+            ));
+        };
+        match serde_json::from_str::<Reply>(&reply)? {
+            Reply::Ok(response) => Ok(response),
+            Reply::Err(message) => Err(NiriSpacerError::Ipc(message)),
+        }
+    }
+
+    /// Fetches all workspaces.
+    pub async fn get_workspaces(&self) -> Result<Vec<Workspace>> {
+        match self.send(&Request::Workspaces).await? {
+            Response::Workspaces(workspaces) => Ok(workspaces),
+            other => Err(unexpected("Workspaces", &other)),
+        }
+    }
+
+    /// Fetches all windows.
+    pub async fn get_windows(&self) -> Result<Vec<Window>> {
+        match self.send(&Request::Windows).await? {
+            Response::Windows(windows) => Ok(windows),
+            other => Err(unexpected("Windows", &other)),
+        }
+    }
+
+    /// Fetches all outputs.
+    pub async fn get_outputs(&self) -> Result<Vec<crate::niri::types::Output>> {
+        match self.send(&Request::Outputs).await? {
+            Response::Outputs(outputs) => Ok(outputs),
+            other => Err(unexpected("Outputs", &other)),
+        }
+    }
+
+    /// Fetches the compositor version string.
+    pub async fn version(&self) -> Result<String> {
+        match self.send(&Request::Version).await? {
+            Response::Version(version) => Ok(version),
+            other => Err(unexpected("Version", &other)),
+        }
+    }
+
+    /// Sends an action, mapping an error reply through `map_err`.
+    ///
+    /// Every action helper below goes through here, so the Ok/Err matching
+    /// and error construction live in exactly one place instead of being
+    /// repeated (slightly differently) per method.
+    pub async fn request_action(
+        &self,
+        action: Action,
+        map_err: impl Fn(String) -> NiriSpacerError,
+    ) -> Result<()> {
+        match self.send(&Request::Action(action)).await {
+            Ok(Response::Handled) => Ok(()),
+            Ok(other) => Err(map_err(format!("expected Handled reply, got {other:?}"))),
+            Err(NiriSpacerError::Ipc(message)) => Err(map_err(message)),
+            Err(other) => Err(other),
+        }
+    }
+
+    /// Asks niri to perform an action.
+    pub async fn action(&self, action: Action) -> Result<()> {
+        self.request_action(action, NiriSpacerError::Ipc).await
+    }
+
+    /// Focuses a window by ID.
+    pub async fn focus_window(&self, id: u64) -> Result<()> {
+        self.action(Action::FocusWindow { id }).await
+    }
+
+    /// Returns focus to the previously focused workspace.
+    pub async fn focus_workspace_previous(&self) -> Result<()> {
+        self.action(Action::FocusWorkspacePrevious {}).await
+    }
+
+    /// Moves a window to the referenced workspace.
+    pub async fn move_window_to_workspace(
+        &self,
+        window_id: u64,
+        reference: WorkspaceReference,
+    ) -> Result<()> {
+        self.action(Action::MoveWindowToWorkspace {
+            window_id: Some(window_id),
+            reference,
+        })
+        .await
+    }
+
+    /// Waits until niri reports `window_id` on the workspace at
+    /// `expected_workspace_idx`, polling with exponential backoff (10ms
+    /// doubling, capped at 160ms) up to `timeout`.
+    ///
+    /// Replaces the old fixed post-move sleep: correct under any load
+    /// instead of relying on an empirically chosen delay, and instant when
+    /// the move has already landed. The total wait is logged at debug so
+    /// slow systems are easy to diagnose.
+    pub async fn wait_for_workspace_move(
+        &self,
+        window_id: u64,
+        expected_workspace_idx: u8,
+        timeout: std::time::Duration,
+    ) -> Result<()> {
+        let started = tokio::time::Instant::now();
+        let deadline = started + timeout;
+        let mut backoff = std::time::Duration::from_millis(10);
+
+        loop {
+            let workspace_id = self
+                .get_workspaces()
+                .await?
+                .into_iter()
+                .find(|ws| ws.idx == expected_workspace_idx)
+                .map(|ws| ws.id);
+            if let Some(workspace_id) = workspace_id {
+                let on_target = self
+                    .get_windows()
+                    .await?
+                    .into_iter()
+                    .find(|w| w.id == window_id)
+                    .is_some_and(|w| w.workspace_id == Some(workspace_id));
+                if on_target {
+                    tracing::debug!(
+                        window = window_id,
+                        waited_ms = started.elapsed().as_millis() as u64,
+                        "workspace move confirmed"
+                    );
+                    return Ok(());
+                }
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(NiriSpacerError::Ipc(format!(
+                    "window {window_id} did not land on workspace index \
+                     {expected_workspace_idx} within {timeout:?}"
+                )));
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(std::time::Duration::from_millis(160));
+        }
+    }
+
+    /// Sets a window's column width to a proportion of the screen.
+    pub async fn set_window_width_proportion(&self, window_id: u64, proportion: f64) -> Result<()> {
+        self.action(Action::SetWindowWidth {
+            id: Some(window_id),
+            change: crate::niri::SizeChange::SetProportion(proportion),
+        })
+        .await
+    }
+
+    /// Closes a window by ID.
+    pub async fn close_window(&self, id: u64) -> Result<()> {
+        self.action(Action::CloseWindow { id: Some(id) }).await
+    }
+
+    /// Focuses the monitor to the left of the current one.
+    pub async fn focus_monitor_left(&self) -> Result<()> {
+        self.action(Action::FocusMonitorLeft {}).await
+    }
+
+    /// Focuses the monitor to the right of the current one.
+    pub async fn focus_monitor_right(&self) -> Result<()> {
+        self.action(Action::FocusMonitorRight {}).await
+    }
+
+    /// Focuses the monitor above the current one.
+    pub async fn focus_monitor_up(&self) -> Result<()> {
+        self.action(Action::FocusMonitorUp {}).await
+    }
+
+    /// Focuses the monitor below the current one.
+    pub async fn focus_monitor_down(&self) -> Result<()> {
+        self.action(Action::FocusMonitorDown {}).await
+    }
+
+    /// Focuses a monitor by index.
+    pub async fn focus_monitor_at_index(&self, index: u8) -> Result<()> {
+        self.action(Action::FocusMonitorAtIndex { index }).await
+    }
+
+    /// Configures whether the given window opens fullscreen. Not used by
+    /// spacers themselves, but keeps the action binding complete for user
+    /// scripts built on this crate.
+    pub async fn set_window_open_fullscreen(&self, window_id: u64, fullscreen: bool) -> Result<()> {
+        self.action(Action::SetWindowOpenFullscreen {
+            id: Some(window_id),
+            fullscreen,
+        })
+        .await
+    }
+}
+
+impl NiriClient {
+    /// Opens a long-lived event subscription without consuming the client;
+    /// the same `NiriClient` can keep issuing requests on fresh connections.
+    ///
+    /// Subscriptions and requests deliberately live on separate sockets: an
+    /// event stream that errors can simply be reopened from the same client
+    /// (as the focus monitor's restart loop does) without disturbing the
+    /// request path, and vice versa.
+    pub async fn subscribe_to_events(&self) -> Result<EventStream> {
+        let (stream, writer) = self.subscribe_with_writer().await?;
+        // Keep the write half alive inside the stream so the connection is
+        // not half-closed under the compositor.
+        Ok(EventStream {
+            _writer: Some(writer),
+            ..stream
+        })
+    }
+
+    /// Opens one event-stream connection and splits it: the read half
+    /// delivers events, the returned [`NiriClientWriter`] sends actions on
+    /// the very same socket. A monitoring session therefore needs one
+    /// connection instead of one for events plus one for actions.
+    pub async fn subscribe_with_writer(&self) -> Result<(EventStream, NiriClientWriter)> {
+        let stream = UnixStream::connect(&self.socket_path).await?;
+        let guard = ConnectionGuard::open(&self.open_connections);
+        let (read_half, mut write_half) = stream.into_split();
+        let mut reader = BufReader::new(read_half);
+
+        let mut line = serde_json::to_string(&Request::EventStream)?;
+        line.push('\n');
+        write_half.write_all(line.as_bytes()).await?;
+
+        let reply = read_bounded_line(&mut reader, MAX_LINE_BYTES)
+            .await?
+            .unwrap_or_default();
+        match serde_json::from_str::<Reply>(&reply)? {
+            Reply::Ok(_) => Ok((
+                EventStream {
+                    reader,
+                    _writer: None,
+                    _guard: guard,
+                },
+                NiriClientWriter { write: write_half },
+            )),
+            Reply::Err(message) => Err(NiriSpacerError::Ipc(message)),
+        }
+    }
+}
+
+/// The write half of an event-stream connection, for sending actions
+/// without opening another socket. Actions are fire-and-forget: replies
+/// would interleave with events, so the stream layer skips them.
+pub struct NiriClientWriter {
+    write: tokio::net::unix::OwnedWriteHalf,
+}
+
+impl NiriClientWriter {
+    /// Sends an action on the shared connection.
+    pub async fn action(&mut self, action: Action) -> Result<()> {
+        let mut line = serde_json::to_string(&Request::Action(action))?;
+        line.push('\n');
+        self.write.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+}
+
+/// A live subscription to niri's event stream.
+pub struct EventStream {
+    reader: BufReader<tokio::net::unix::OwnedReadHalf>,
+    /// Present when the caller did not ask for the writer; keeps the write
+    /// half open.
+    _writer: Option<NiriClientWriter>,
+    /// Keeps the owning client's connection count honest.
+    _guard: ConnectionGuard,
+}
+
+impl EventStream {
+    /// Waits for the next event we model. Unknown event kinds (and action
+    /// replies interleaved by a shared-connection writer) are skipped;
+    /// `None` means the compositor closed the stream.
+    pub async fn next_event(&mut self) -> Result<Option<NiriEvent>> {
+        loop {
+            let Some(line) = read_bounded_line(&mut self.reader, MAX_LINE_BYTES).await? else {
+                return Ok(None);
+            };
+            match serde_json::from_str::<NiriEvent>(&line) {
+                Ok(event) => return Ok(Some(event)),
+                Err(_) => trace!(line = line.trim(), "skipping unmodeled niri event"),
+            }
+        }
+    }
+}
+
+fn unexpected(wanted: &str, got: &Response) -> NiriSpacerError {
+    NiriSpacerError::Ipc(format!("expected {wanted} reply, got {got:?}"))
+}
+
+/// Distinguishes "niri went away" I/O failures from everything else.
+fn map_connection_loss(e: std::io::Error) -> NiriSpacerError {
+    use std::io::ErrorKind;
+    match e.kind() {
+        ErrorKind::BrokenPipe | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted => {
+            NiriSpacerError::ConnectionLost(e.to_string())
+        }
+        _ => NiriSpacerError::Io(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockNiri;
+
+    #[tokio::test]
+    async fn request_action_maps_error_replies_through_the_caller() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        niri.state()
+            .lock()
+            .unwrap()
+            .reject_action_kinds
+            .push("FocusWorkspacePrevious".to_string());
+        let client = NiriClient::new(niri.socket_path());
+
+        let err = client
+            .request_action(Action::FocusWorkspacePrevious {}, |message| {
+                NiriSpacerError::Ipc(format!("during restore: {message}"))
+            })
+            .await
+            .unwrap_err();
+        match err {
+            NiriSpacerError::Ipc(message) => {
+                assert!(message.starts_with("during restore:"), "got {message:?}");
+            }
+            other => panic!("expected mapped Ipc error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn events_and_actions_share_one_connection() {
+        use crate::niri::types::NiriEvent;
+
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let client = NiriClient::new(niri.socket_path());
+
+        let (mut events, mut writer) = client.subscribe_with_writer().await.unwrap();
+        niri.wait_for_event_subscriber().await;
+
+        writer.action(Action::FocusWindow { id: 7 }).await.unwrap();
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(7) });
+
+        let event = tokio::time::timeout(std::time::Duration::from_secs(2), events.next_event())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event, Some(NiriEvent::WindowFocusChanged { id: Some(7) }));
+
+        // The action arrived over the same socket.
+        tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            loop {
+                if !niri.state().lock().unwrap().actions.is_empty() {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            niri.state().lock().unwrap().actions,
+            vec![Action::FocusWindow { id: 7 }]
+        );
+
+        // And the plain subscription no longer consumes the client.
+        let _still_usable = client.get_workspaces().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn oversized_replies_fail_with_a_clear_error() {
+        // A raw server feeding an endless unterminated line.
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bad.sock");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                use tokio::io::AsyncWriteExt as _;
+                let junk = vec![b'a'; MAX_LINE_BYTES + 64];
+                let _ = stream.write_all(&junk).await;
+            }
+        });
+
+        let client = NiriClient::new(&path);
+        let err = client.get_workspaces().await.unwrap_err();
+        assert!(err.to_string().contains("exceeded"), "got: {err}");
+    }
+
+    #[tokio::test]
+    async fn legitimately_large_window_lists_still_decode() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        {
+            let state = niri.state();
+            let mut state = state.lock().unwrap();
+            // A few hundred KB of windows: long titles, thousands of rows.
+            for i in 0..2000 {
+                state.insert_window(
+                    "some.app.with.a.long.reverse.dns.id".to_string(),
+                    format!("window {i} with a fairly long descriptive title padding {}", "x".repeat(120)),
+                );
+            }
+        }
+        let client = NiriClient::new(niri.socket_path());
+        assert_eq!(client.get_windows().await.unwrap().len(), 2000);
+    }
+
+    #[tokio::test]
+    async fn connection_count_returns_to_zero() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let client = NiriClient::new(niri.socket_path());
+        assert_eq!(client.open_connection_count(), 0);
+
+        // Per-request connections are closed by the time the call returns.
+        client.get_workspaces().await.unwrap();
+        assert_eq!(client.open_connection_count(), 0);
+
+        // Event streams count while alive, including through clones.
+        let stream = client.clone().subscribe_to_events().await.unwrap();
+        assert_eq!(client.open_connection_count(), 1);
+        drop(stream);
+        assert_eq!(client.open_connection_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn dropped_connection_maps_to_connection_lost() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        niri.state().lock().unwrap().drop_request_numbers.push(1);
+        let client = NiriClient::new(niri.socket_path());
+
+        match client.get_workspaces().await.unwrap_err() {
+            NiriSpacerError::ConnectionLost(_) => {}
+            other => panic!("expected ConnectionLost, got {other:?}"),
+        }
+        // The next request goes through again.
+        assert_eq!(client.get_workspaces().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn event_streams_can_be_reopened_without_touching_the_request_path() {
+        use crate::niri::types::NiriEvent;
+
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let client = NiriClient::new(niri.socket_path());
+
+        // First subscription dies (dropped, as after a stream error)...
+        let first = client.subscribe_to_events().await.unwrap();
+        niri.wait_for_event_subscriber().await;
+        drop(first);
+
+        // ...and the same client reopens a fresh stream while its request
+        // path keeps working throughout.
+        assert_eq!(client.get_workspaces().await.unwrap().len(), 3);
+        let mut second = client.subscribe_to_events().await.unwrap();
+        niri.wait_for_event_subscribers(2).await;
+
+        niri.send_event(NiriEvent::WindowFocusChanged { id: Some(1) });
+        let event = tokio::time::timeout(
+            std::time::Duration::from_secs(2),
+            second.next_event(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(event, Some(NiriEvent::WindowFocusChanged { id: Some(1) }));
+        assert_eq!(client.get_windows().await.unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn instant_moves_confirm_immediately() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let id = niri
+            .state()
+            .lock()
+            .unwrap()
+            .insert_window("niri-spacer".to_string(), "niri-spacer-1".to_string());
+        let client = NiriClient::new(niri.socket_path());
+        client
+            .move_window_to_workspace(id, WorkspaceReference::Index(2))
+            .await
+            .unwrap();
+
+        let started = std::time::Instant::now();
+        client
+            .wait_for_workspace_move(id, 2, std::time::Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert!(
+            started.elapsed() < std::time::Duration::from_millis(100),
+            "an already-landed move must confirm without backing off"
+        );
+    }
+
+    #[tokio::test]
+    async fn action_helpers_share_the_same_path() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let client = NiriClient::new(niri.socket_path());
+
+        client.focus_window(7).await.unwrap();
+        client
+            .move_window_to_workspace(7, WorkspaceReference::Index(2))
+            .await
+            .unwrap();
+        client.close_window(7).await.unwrap();
+
+        let actions = niri.state().lock().unwrap().actions.clone();
+        assert_eq!(actions.len(), 3);
+        assert_eq!(actions[0], Action::FocusWindow { id: 7 });
+    }
+}
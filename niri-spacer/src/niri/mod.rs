@@ -0,0 +1,12 @@
+//! Talking to niri over its IPC socket.
+
+pub mod client;
+pub mod pool;
+pub mod types;
+
+pub use client::{EventStream, NiriClient};
+pub use pool::{NiriClientPool, PooledClient};
+pub use types::{
+    Action, LogicalOutput, NiriEvent, Output, Reply, Request, Response, SizeChange, Window,
+    WindowLayout, Workspace, WorkspaceReference,
+};
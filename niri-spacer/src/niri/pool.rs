@@ -0,0 +1,165 @@
+//! A bounded pool of IPC clients for parallel batch operations.
+//!
+//! niri's protocol is one request per connection, so a client object is
+//! cheap — what the pool actually bounds is *concurrency*: a batch fanning
+//! out over 50 windows must not open 50 sockets at once. Acquisition waits
+//! for one of `max_size` permits; dropping the [`PooledClient`] returns the
+//! client (and permit) for reuse.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::error::Result;
+use crate::niri::NiriClient;
+
+/// Pool handing out up to `max_size` concurrently usable clients.
+#[derive(Debug, Clone)]
+pub struct NiriClientPool {
+    socket_path: PathBuf,
+    available: Arc<Mutex<Vec<NiriClient>>>,
+    permits: Arc<tokio::sync::Semaphore>,
+    max_size: usize,
+}
+
+impl NiriClientPool {
+    pub fn new(socket_path: impl Into<PathBuf>, max_size: usize) -> Self {
+        let max_size = max_size.max(1);
+        Self {
+            socket_path: socket_path.into(),
+            available: Arc::new(Mutex::new(Vec::with_capacity(max_size))),
+            permits: Arc::new(tokio::sync::Semaphore::new(max_size)),
+            max_size,
+        }
+    }
+
+    /// The concurrency bound this pool enforces.
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Waits for a free slot and hands out a client, reusing a returned one
+    /// when available.
+    pub async fn acquire(&self) -> Result<PooledClient> {
+        let permit = Arc::clone(&self.permits)
+            .acquire_owned()
+            .await
+            .expect("pool semaphore never closes");
+        let client = self
+            .available
+            .lock()
+            .expect("pool lock poisoned")
+            .pop()
+            .unwrap_or_else(|| NiriClient::new(&self.socket_path));
+        Ok(PooledClient {
+            client: Some(client),
+            available: Arc::clone(&self.available),
+            _permit: permit,
+        })
+    }
+
+    /// Clients currently parked in the pool (for tests/diagnostics).
+    pub fn idle_count(&self) -> usize {
+        self.available.lock().expect("pool lock poisoned").len()
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+}
+
+/// A client checked out of the pool; derefs to [`NiriClient`] and returns
+/// itself on drop.
+pub struct PooledClient {
+    client: Option<NiriClient>,
+    available: Arc<Mutex<Vec<NiriClient>>>,
+    _permit: tokio::sync::OwnedSemaphorePermit,
+}
+
+impl std::ops::Deref for PooledClient {
+    type Target = NiriClient;
+
+    fn deref(&self) -> &Self::Target {
+        self.client.as_ref().expect("client present until drop")
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            self.available
+                .lock()
+                .expect("pool lock poisoned")
+                .push(client);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockNiri;
+
+    #[tokio::test]
+    async fn pool_reuses_returned_clients_and_bounds_concurrency() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let pool = NiriClientPool::new(niri.socket_path(), 3);
+
+        {
+            let a = pool.acquire().await.unwrap();
+            let b = pool.acquire().await.unwrap();
+            a.get_workspaces().await.unwrap();
+            b.get_windows().await.unwrap();
+        }
+        assert_eq!(pool.idle_count(), 2, "dropped clients return to the pool");
+
+        // With all permits held, another acquire must wait.
+        let _held: Vec<PooledClient> = futures_acquire_all(&pool, 3).await;
+        let waiting = tokio::time::timeout(
+            std::time::Duration::from_millis(100),
+            pool.acquire(),
+        )
+        .await;
+        assert!(waiting.is_err(), "4th acquire should block at max_size 3");
+    }
+
+    async fn futures_acquire_all(pool: &NiriClientPool, n: usize) -> Vec<PooledClient> {
+        let mut held = Vec::new();
+        for _ in 0..n {
+            held.push(pool.acquire().await.unwrap());
+        }
+        held
+    }
+
+    /// Not a real benchmark, but a sanity comparison for a 9-request batch:
+    /// both paths complete, and the pool never exceeds its bound.
+    #[tokio::test]
+    async fn pooled_batch_completes_like_per_operation_clients() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let pool = NiriClientPool::new(niri.socket_path(), 3);
+
+        let pooled = std::time::Instant::now();
+        let mut tasks = Vec::new();
+        for _ in 0..9 {
+            let pool = pool.clone();
+            tasks.push(tokio::spawn(async move {
+                let client = pool.acquire().await.unwrap();
+                client.get_workspaces().await.unwrap().len()
+            }));
+        }
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), 3);
+        }
+        let pooled = pooled.elapsed();
+
+        let fresh = std::time::Instant::now();
+        for _ in 0..9 {
+            let client = NiriClient::new(niri.socket_path());
+            assert_eq!(client.get_workspaces().await.unwrap().len(), 3);
+        }
+        let fresh = fresh.elapsed();
+
+        // Informational only; mock-socket timings are too noisy to assert.
+        eprintln!("9-request batch: pooled {pooled:?}, per-operation {fresh:?}");
+        assert!(pool.idle_count() <= pool.max_size());
+    }
+}
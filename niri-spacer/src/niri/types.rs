@@ -0,0 +1,371 @@
+//! Wire types for the niri IPC protocol.
+//!
+//! These mirror the JSON shapes niri speaks on `$NIRI_SOCKET`: one request
+//! per line, one reply per line, externally tagged enums throughout. Only the
+//! subset niri-spacer needs is modeled here.
+
+use serde::{Deserialize, Serialize};
+
+/// A niri workspace as reported by the `Workspaces` request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: u64,
+    /// Index of the workspace on its output, starting at 1.
+    pub idx: u8,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub output: Option<String>,
+    pub is_active: bool,
+    pub is_focused: bool,
+    #[serde(default)]
+    pub active_window_id: Option<u64>,
+}
+
+/// A niri window as reported by the `Windows` request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Window {
+    pub id: u64,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub app_id: Option<String>,
+    #[serde(default)]
+    pub workspace_id: Option<u64>,
+    pub is_focused: bool,
+    #[serde(default)]
+    pub pid: Option<u32>,
+    #[serde(default)]
+    pub layout: Option<WindowLayout>,
+}
+
+/// Scrolling-layout position data for a window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct WindowLayout {
+    /// `(column, tile)` in the scrolling layout; `None` for floating
+    /// windows.
+    #[serde(default)]
+    pub pos_in_scrolling_layout: Option<(usize, usize)>,
+}
+
+/// A niri output as reported by the `Outputs` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+    pub name: String,
+    #[serde(default)]
+    pub make: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub logical: Option<LogicalOutput>,
+}
+
+/// Position and size of an output in the logical layout.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LogicalOutput {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A request sent to niri.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+    Workspaces,
+    Windows,
+    Outputs,
+    Version,
+    EventStream,
+    Action(Action),
+}
+
+/// The success payload of a reply.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Response {
+    /// The action was carried out; no data comes back.
+    Handled,
+    Workspaces(Vec<Workspace>),
+    Windows(Vec<Window>),
+    Outputs(Vec<Output>),
+    Version(String),
+}
+
+// niri's success replies for actions come in several shapes depending on
+// version: `{"Ok":"Handled"}`, `{"Ok":{"Handled":null}}`, and plain
+// `{"Ok":null}`. A derived externally-tagged Deserialize only accepts the
+// first, so decode by hand and fold every data-free success shape --
+// including payload variants we do not model -- into `Handled`.
+impl<'de> serde::Deserialize<'de> for Response {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let value = serde_json::Value::deserialize(deserializer)?;
+        match value {
+            serde_json::Value::Null => Ok(Response::Handled),
+            serde_json::Value::String(tag) if tag == "Handled" => Ok(Response::Handled),
+            serde_json::Value::String(tag) => {
+                Err(D::Error::custom(format!("unknown response tag {tag:?}")))
+            }
+            serde_json::Value::Object(map) => {
+                if let Some(inner) = map.get("Workspaces") {
+                    serde_json::from_value(inner.clone())
+                        .map(Response::Workspaces)
+                        .map_err(D::Error::custom)
+                } else if let Some(inner) = map.get("Outputs") {
+                    serde_json::from_value(inner.clone())
+                        .map(Response::Outputs)
+                        .map_err(D::Error::custom)
+                } else if let Some(inner) = map.get("Windows") {
+                    serde_json::from_value(inner.clone())
+                        .map(Response::Windows)
+                        .map_err(D::Error::custom)
+                } else if let Some(inner) = map.get("Version") {
+                    serde_json::from_value(inner.clone())
+                        .map(Response::Version)
+                        .map_err(D::Error::custom)
+                } else {
+                    // "Handled", or a success payload we have no use for.
+                    Ok(Response::Handled)
+                }
+            }
+            other => Err(D::Error::custom(format!(
+                "unexpected response shape: {other}"
+            ))),
+        }
+    }
+}
+
+/// A reply from niri: either a [`Response`] or an error message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Reply {
+    Ok(Response),
+    Err(String),
+}
+
+/// Compositor events delivered on the event stream.
+///
+/// niri emits more event kinds than we model; unknown ones are skipped at
+/// the stream layer rather than failing the subscription.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NiriEvent {
+    WindowFocusChanged {
+        #[serde(default)]
+        id: Option<u64>,
+    },
+    WorkspaceCreated {
+        workspace: Workspace,
+    },
+    WorkspaceDestroyed {
+        id: u64,
+    },
+    OverviewOpenedOrClosed {
+        is_open: bool,
+    },
+    WindowOpenedOrChanged {
+        window: Window,
+    },
+    WindowClosed {
+        id: u64,
+    },
+    WorkspaceActiveWindowChanged {
+        workspace_id: u64,
+        #[serde(default)]
+        active_window_id: Option<u64>,
+    },
+    WindowAppIdChanged {
+        id: u64,
+        app_id: String,
+    },
+    ConfigLoaded {
+        #[serde(default)]
+        failed: bool,
+    },
+}
+
+/// Ways of naming a workspace in an action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WorkspaceReference {
+    Id(u64),
+    Index(u8),
+    Name(String),
+}
+
+/// A width/height change in a resize action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SizeChange {
+    SetFixed(i32),
+    SetProportion(f64),
+}
+
+/// Compositor actions niri-spacer issues.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Action {
+    FocusWorkspace {
+        reference: WorkspaceReference,
+    },
+    FocusWindow {
+        id: u64,
+    },
+    FocusColumnLeft {},
+    FocusWorkspacePrevious {},
+    MoveWindowToWorkspace {
+        #[serde(default)]
+        window_id: Option<u64>,
+        reference: WorkspaceReference,
+    },
+    CloseWindow {
+        #[serde(default)]
+        id: Option<u64>,
+    },
+    SetWindowWidth {
+        #[serde(default)]
+        id: Option<u64>,
+        change: SizeChange,
+    },
+    SetWindowOpenFullscreen {
+        #[serde(default)]
+        id: Option<u64>,
+        fullscreen: bool,
+    },
+    ConsumeOrExpelWindowRight {
+        #[serde(default)]
+        id: Option<u64>,
+    },
+    MoveWorkspaceUp {},
+    MoveWorkspaceDown {},
+    FocusMonitorLeft {},
+    FocusMonitorRight {},
+    FocusMonitorUp {},
+    FocusMonitorDown {},
+    FocusMonitorAtIndex {
+        index: u8,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_serializes_externally_tagged() {
+        assert_eq!(
+            serde_json::to_string(&Request::Workspaces).unwrap(),
+            "\"Workspaces\""
+        );
+        let action = Request::Action(Action::MoveWindowToWorkspace {
+            window_id: Some(7),
+            reference: WorkspaceReference::Index(2),
+        });
+        assert_eq!(
+            serde_json::to_string(&action).unwrap(),
+            r#"{"Action":{"MoveWindowToWorkspace":{"window_id":7,"reference":{"Index":2}}}}"#
+        );
+    }
+
+    #[test]
+    fn proportional_width_action_matches_the_ipc_format() {
+        let action = Request::Action(Action::SetWindowWidth {
+            id: Some(9),
+            change: SizeChange::SetProportion(0.01),
+        });
+        assert_eq!(
+            serde_json::to_string(&action).unwrap(),
+            r#"{"Action":{"SetWindowWidth":{"id":9,"change":{"SetProportion":0.01}}}}"#
+        );
+    }
+
+    #[test]
+    fn monitor_focus_actions_serialize_to_the_ipc_format() {
+        for (action, expected) in [
+            (Action::FocusMonitorLeft {}, r#"{"FocusMonitorLeft":{}}"#),
+            (Action::FocusMonitorRight {}, r#"{"FocusMonitorRight":{}}"#),
+            (Action::FocusMonitorUp {}, r#"{"FocusMonitorUp":{}}"#),
+            (Action::FocusMonitorDown {}, r#"{"FocusMonitorDown":{}}"#),
+            (
+                Action::FocusMonitorAtIndex { index: 2 },
+                r#"{"FocusMonitorAtIndex":{"index":2}}"#,
+            ),
+        ] {
+            assert_eq!(serde_json::to_string(&action).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn set_window_open_fullscreen_matches_the_ipc_format() {
+        let action = Request::Action(Action::SetWindowOpenFullscreen {
+            id: Some(42),
+            fullscreen: true,
+        });
+        assert_eq!(
+            serde_json::to_string(&action).unwrap(),
+            r#"{"Action":{"SetWindowOpenFullscreen":{"id":42,"fullscreen":true}}}"#
+        );
+        let action = Request::Action(Action::SetWindowOpenFullscreen {
+            id: None,
+            fullscreen: false,
+        });
+        assert_eq!(
+            serde_json::to_string(&action).unwrap(),
+            r#"{"Action":{"SetWindowOpenFullscreen":{"id":null,"fullscreen":false}}}"#
+        );
+    }
+
+    #[test]
+    fn workspace_created_event_deserializes() {
+        let json = r#"{"WorkspaceCreated":{"workspace":{"id":9,"idx":4,"name":null,"output":"eDP-1","is_active":false,"is_focused":false,"active_window_id":null}}}"#;
+        let event: NiriEvent = serde_json::from_str(json).unwrap();
+        match event {
+            NiriEvent::WorkspaceCreated { workspace } => {
+                assert_eq!(workspace.id, 9);
+                assert_eq!(workspace.idx, 4);
+            }
+            other => panic!("expected WorkspaceCreated, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn window_app_id_changed_event_deserializes() {
+        let json = r#"{"WindowAppIdChanged":{"id":42,"app_id":"org.wezfurlong.wezterm"}}"#;
+        let event: NiriEvent = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            event,
+            NiriEvent::WindowAppIdChanged {
+                id: 42,
+                app_id: "org.wezfurlong.wezterm".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn workspace_destroyed_event_deserializes() {
+        let json = r#"{"WorkspaceDestroyed":{"id":9}}"#;
+        let event: NiriEvent = serde_json::from_str(json).unwrap();
+        assert_eq!(event, NiriEvent::WorkspaceDestroyed { id: 9 });
+    }
+
+    #[test]
+    fn action_success_shapes_all_deserialize_to_handled() {
+        // The shapes different niri versions actually send for actions.
+        for json in [
+            r#"{"Ok":"Handled"}"#,
+            r#"{"Ok":{"Handled":null}}"#,
+            r#"{"Ok":null}"#,
+            // A payload variant we do not model must not fail the decode.
+            r#"{"Ok":{"FocusedWindow":null}}"#,
+        ] {
+            let reply: Reply = serde_json::from_str(json).unwrap();
+            assert_eq!(reply, Reply::Ok(Response::Handled), "shape: {json}");
+        }
+    }
+
+    #[test]
+    fn reply_roundtrips() {
+        let reply = Reply::Ok(Response::Version("25.05".into()));
+        let json = serde_json::to_string(&reply).unwrap();
+        assert_eq!(serde_json::from_str::<Reply>(&json).unwrap(), reply);
+    }
+}
@@ -0,0 +1,276 @@
+//! Event recording and replay for debugging the focus-loop heuristics.
+//!
+//! The recorder writes every received [`NiriEvent`] with a monotonic
+//! timestamp as one JSON line; replay feeds a recording back through the
+//! real monitor logic against an in-process action sink, producing the
+//! action sequence deterministically without a live niri.
+
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing::debug;
+
+use crate::error::{NiriSpacerError, Result};
+use crate::niri::{Action, NiriEvent, Reply, Request, Response};
+
+/// One line of a recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Milliseconds since the recording started.
+    pub t_ms: u64,
+    pub event: NiriEvent,
+}
+
+/// Appends received events to a file as JSON lines.
+pub struct EventRecorder {
+    file: std::io::BufWriter<std::fs::File>,
+    start: Instant,
+}
+
+impl EventRecorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(Self {
+            file: std::io::BufWriter::new(std::fs::File::create(path)?),
+            start: Instant::now(),
+        })
+    }
+
+    /// Writes one event with its offset from the recording start.
+    pub fn record(&mut self, event: &NiriEvent) -> Result<()> {
+        let entry = RecordedEvent {
+            t_ms: self.start.elapsed().as_millis() as u64,
+            event: event.clone(),
+        };
+        serde_json::to_writer(&mut self.file, &entry)?;
+        self.file.write_all(b"\n")?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Loads a recording, skipping malformed lines with a debug log.
+pub fn read_recording(path: &Path) -> Result<Vec<RecordedEvent>> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut events = Vec::new();
+    for line in file.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(event) => events.push(event),
+            Err(e) => debug!(error = %e, "skipping malformed recording line"),
+        }
+    }
+    Ok(events)
+}
+
+/// Replays a recording through the real focus-monitor logic and returns the
+/// actions it fired, in order.
+///
+/// The monitor talks to an in-process socket server that streams the
+/// recorded events, answers state queries with empty lists, and collects
+/// every action — a deterministic repro path for layout-fix heuristics.
+pub async fn replay(
+    path: &Path,
+    spacer_ids: std::collections::HashSet<u64>,
+    options: crate::focus::FocusMonitorOptions,
+) -> Result<Vec<Action>> {
+    let events = read_recording(path)?;
+    let socket = replay_socket_path();
+    let listener = tokio::net::UnixListener::bind(&socket)
+        .map_err(|e| NiriSpacerError::Ipc(format!("replay socket: {e}")))?;
+    let actions = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let done = std::sync::Arc::new(tokio::sync::Notify::new());
+
+    let server = {
+        let actions = std::sync::Arc::clone(&actions);
+        let done = std::sync::Arc::clone(&done);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let events = events.clone();
+                let actions = std::sync::Arc::clone(&actions);
+                let done = std::sync::Arc::clone(&done);
+                tokio::spawn(serve_replay_connection(stream, events, actions, done));
+            }
+        })
+    };
+
+    let monitor = crate::focus::FocusMonitor::spawn_with_options(
+        crate::niri::NiriClient::new(&socket),
+        std::sync::Arc::new(std::sync::RwLock::new(spacer_ids)),
+        options,
+    );
+
+    // The stream connection signals once every event was delivered; give
+    // in-flight action writes a moment to land afterwards.
+    done.notified().await;
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    monitor.abort();
+    server.abort();
+    let _ = std::fs::remove_file(&socket);
+
+    let actions = actions.lock().expect("replay action log poisoned").clone();
+    Ok(actions)
+}
+
+fn replay_socket_path() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "niri-spacer-replay-{}-{:x}.sock",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default()
+    ))
+}
+
+async fn serve_replay_connection(
+    stream: tokio::net::UnixStream,
+    events: Vec<RecordedEvent>,
+    actions: std::sync::Arc<std::sync::Mutex<Vec<Action>>>,
+    done: std::sync::Arc<tokio::sync::Notify>,
+) {
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    if stream.read_line(&mut line).await.is_err() || line.is_empty() {
+        return;
+    }
+    let Ok(request) = serde_json::from_str::<Request>(&line) else {
+        return;
+    };
+
+    let write_reply = |reply: Reply| {
+        let mut out = serde_json::to_string(&reply).unwrap();
+        out.push('\n');
+        out
+    };
+
+    match request {
+        Request::EventStream => {
+            let ack = write_reply(Reply::Ok(Response::Handled));
+            if stream.get_mut().write_all(ack.as_bytes()).await.is_err() {
+                return;
+            }
+            for recorded in &events {
+                let mut out = serde_json::to_string(&recorded.event).unwrap();
+                out.push('\n');
+                if stream.get_mut().write_all(out.as_bytes()).await.is_err() {
+                    return;
+                }
+            }
+            done.notify_waiters();
+            // The monitor sends its redirects on this same connection
+            // (shared-socket writer); keep reading them until it goes away.
+            let mut line = String::new();
+            loop {
+                line.clear();
+                match stream.read_line(&mut line).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(_) => {
+                        if let Ok(Request::Action(action)) =
+                            serde_json::from_str::<Request>(&line)
+                        {
+                            actions
+                                .lock()
+                                .expect("replay action log poisoned")
+                                .push(action);
+                        }
+                    }
+                }
+            }
+        }
+        Request::Action(action) => {
+            actions.lock().expect("replay action log poisoned").push(action);
+            let out = write_reply(Reply::Ok(Response::Handled));
+            let _ = stream.get_mut().write_all(out.as_bytes()).await;
+        }
+        Request::Workspaces => {
+            let out = write_reply(Reply::Ok(Response::Workspaces(Vec::new())));
+            let _ = stream.get_mut().write_all(out.as_bytes()).await;
+        }
+        Request::Windows => {
+            let out = write_reply(Reply::Ok(Response::Windows(Vec::new())));
+            let _ = stream.get_mut().write_all(out.as_bytes()).await;
+        }
+        Request::Outputs => {
+            let out = write_reply(Reply::Ok(Response::Outputs(Vec::new())));
+            let _ = stream.get_mut().write_all(out.as_bytes()).await;
+        }
+        Request::Version => {
+            let out = write_reply(Reply::Ok(Response::Version("replay".to_string())));
+            let _ = stream.get_mut().write_all(out.as_bytes()).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn record_then_replay_reproduces_the_action_sequence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+
+        // A session where the user focuses window 7, then lands on spacer
+        // 50 twice; the monitor should redirect back to 7 both times.
+        {
+            let mut recorder = EventRecorder::create(&path).unwrap();
+            for event in [
+                NiriEvent::WindowFocusChanged { id: Some(7) },
+                NiriEvent::WindowFocusChanged { id: Some(50) },
+                NiriEvent::WindowFocusChanged { id: Some(7) },
+                NiriEvent::WindowFocusChanged { id: Some(50) },
+            ] {
+                recorder.record(&event).unwrap();
+            }
+        }
+
+        let recording = read_recording(&path).unwrap();
+        assert_eq!(recording.len(), 4);
+
+        let actions = replay(
+            &path,
+            [50u64].into_iter().collect(),
+            crate::focus::FocusMonitorOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            actions,
+            vec![Action::FocusWindow { id: 7 }, Action::FocusWindow { id: 7 }]
+        );
+
+        // Determinism: a second replay yields the identical sequence.
+        let again = replay(
+            &path,
+            [50u64].into_iter().collect(),
+            crate::focus::FocusMonitorOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(again, actions);
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        std::fs::write(
+            &path,
+            "{\"t_ms\":0,\"event\":{\"WindowFocusChanged\":{\"id\":1}}}\nnot json\n",
+        )
+        .unwrap();
+
+        let events = read_recording(&path).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].t_ms, 0);
+    }
+}
@@ -0,0 +1,169 @@
+//! Structured diagnostic reports.
+//!
+//! The diagnostic modes (`--check`, `--verify`, `--self-test`) all need the
+//! same two-faced output: a human-readable rendering for the terminal and a
+//! stable JSON form for scripts. The types here own both, so the modes only
+//! differ in which checks they run.
+
+use serde::Serialize;
+
+/// Outcome of one named check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+    /// Extra context: the failure reason, or a detail worth showing even on
+    /// success (e.g. a detected version).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl CheckResult {
+    pub fn pass(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: true,
+            detail: None,
+        }
+    }
+
+    pub fn fail(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: false,
+            detail: Some(detail.into()),
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+fn render_checks(f: &mut std::fmt::Formatter<'_>, checks: &[CheckResult]) -> std::fmt::Result {
+    for check in checks {
+        let mark = if check.passed { "PASS" } else { "FAIL" };
+        match &check.detail {
+            Some(detail) => writeln!(f, "  [{mark}] {:<28} {detail}", check.name)?,
+            None => writeln!(f, "  [{mark}] {}", check.name)?,
+        }
+    }
+    Ok(())
+}
+
+/// Result of an environment/health check run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthReport {
+    pub checks: Vec<CheckResult>,
+    /// True when every check passed.
+    pub ok: bool,
+}
+
+impl HealthReport {
+    pub fn from_checks(checks: Vec<CheckResult>) -> Self {
+        let ok = checks.iter().all(|c| c.passed);
+        Self { checks, ok }
+    }
+
+    /// The stable JSON form, for `--json` consumers.
+    pub fn to_json(&self) -> crate::error::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl std::fmt::Display for HealthReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "health: {}", if self.ok { "ok" } else { "NOT ok" })?;
+        render_checks(f, &self.checks)
+    }
+}
+
+/// Result of verifying the placed spacers against compositor state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifyReport {
+    /// How many spacers were supposed to be in place.
+    pub expected: u32,
+    /// How many were found where they should be.
+    pub verified: u32,
+    pub checks: Vec<CheckResult>,
+    /// True when every spacer verified and every check passed.
+    pub ok: bool,
+}
+
+impl VerifyReport {
+    pub fn new(expected: u32, verified: u32, checks: Vec<CheckResult>) -> Self {
+        let ok = expected == verified && checks.iter().all(|c| c.passed);
+        Self {
+            expected,
+            verified,
+            checks,
+            ok,
+        }
+    }
+
+    /// The stable JSON form, for `--json` consumers.
+    pub fn to_json(&self) -> crate::error::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl std::fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "verify: {} ({}/{} spacers in place)",
+            if self.ok { "ok" } else { "NOT ok" },
+            self.verified,
+            self.expected
+        )?;
+        render_checks(f, &self.checks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_report_renders_pass_and_fail() {
+        let report = HealthReport::from_checks(vec![
+            CheckResult::pass("socket").with_detail("/run/niri.sock"),
+            CheckResult::fail("compositor version", "niri binary not found"),
+        ]);
+
+        assert!(!report.ok);
+        let text = report.to_string();
+        assert!(text.contains("health: NOT ok"));
+        assert!(text.contains("[PASS] socket"));
+        assert!(text.contains("[FAIL] compositor version"));
+        assert!(text.contains("niri binary not found"));
+    }
+
+    #[test]
+    fn all_passing_health_report_is_ok() {
+        let report = HealthReport::from_checks(vec![CheckResult::pass("socket")]);
+        assert!(report.ok);
+        assert!(report.to_string().starts_with("health: ok"));
+    }
+
+    #[test]
+    fn verify_report_requires_full_coverage() {
+        let partial = VerifyReport::new(3, 2, vec![]);
+        assert!(!partial.ok);
+        assert!(partial.to_string().contains("(2/3 spacers in place)"));
+
+        let full = VerifyReport::new(3, 3, vec![CheckResult::pass("workspaces")]);
+        assert!(full.ok);
+    }
+
+    #[test]
+    fn json_form_is_stable() {
+        let report = HealthReport::from_checks(vec![CheckResult::pass("socket")]);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["ok"], true);
+        assert_eq!(json["checks"][0]["name"], "socket");
+        // `detail: None` stays out of the JSON entirely.
+        assert!(json["checks"][0].get("detail").is_none());
+    }
+}
@@ -0,0 +1,537 @@
+//! Validation of the Wayland/niri session environment before we touch the
+//! compositor.
+//!
+//! niri-spacer refuses to start against a socket it cannot actually use;
+//! catching misconfiguration here produces far better error messages than a
+//! failed `connect(2)` deep inside the IPC layer.
+
+use std::env;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use once_cell::sync::Lazy;
+use tracing::{debug, info, warn};
+
+use crate::error::{NiriSpacerError, Result};
+
+/// Compositor version detected once per process; see
+/// [`SessionValidator::detect_compositor_version`].
+static COMPOSITOR_VERSION: Lazy<Option<String>> = Lazy::new(|| {
+    let binary = which::which("niri").ok()?;
+    match version_from_binary(&binary) {
+        Ok(version) => version,
+        Err(e) => {
+            warn!(error = %e, "could not run niri --version");
+            None
+        }
+    }
+});
+
+/// Runs `<binary> --version` and extracts the version line.
+fn version_from_binary(binary: &Path) -> Result<Option<String>> {
+    let output = Command::new(binary).arg("--version").output()?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().next().map(|line| line.trim().to_string()))
+}
+
+/// Waits for a usable niri socket to appear, retrying discovery and a
+/// connection probe with backoff until `wait` elapses.
+///
+/// Autostarted alongside the compositor, niri-spacer often races niri's own
+/// startup; without this the user needs a shell retry loop. Discovery
+/// re-reads `$NIRI_SOCKET` on every attempt since the path may only be
+/// exported once niri is up.
+pub async fn wait_for_niri(
+    wait: std::time::Duration,
+    clock: &dyn crate::clock::Clock,
+) -> Result<SessionValidator> {
+    let deadline = clock.now() + wait;
+    let mut backoff = std::time::Duration::from_millis(200);
+
+    loop {
+        let attempt = async {
+            let validator = SessionValidator::from_env()?;
+            validator.validate()?;
+            // A full round trip proves niri is actually serving.
+            crate::niri::NiriClient::new(validator.socket_path())
+                .version()
+                .await?;
+            Ok(validator)
+        }
+        .await;
+
+        match attempt {
+            Ok(validator) => return Ok(validator),
+            Err(e) if clock.now() >= deadline => return Err(e),
+            Err(e) => {
+                info!(error = %e, retry_in = ?backoff, "niri not ready yet");
+                clock.sleep(backoff).await;
+                backoff = (backoff * 2).min(std::time::Duration::from_secs(2));
+            }
+        }
+    }
+}
+
+/// Environment variable niri uses to advertise its IPC socket.
+pub const NIRI_SOCKET_ENV: &str = "NIRI_SOCKET";
+/// Wayland's per-session runtime directory.
+pub const XDG_RUNTIME_DIR_ENV: &str = "XDG_RUNTIME_DIR";
+/// Name of the Wayland display socket under the runtime directory.
+pub const WAYLAND_DISPLAY_ENV: &str = "WAYLAND_DISPLAY";
+
+/// Checks the Wayland-session environment beyond the niri socket:
+/// `$XDG_RUNTIME_DIR` present, a directory, owned by us, mode 0700, and
+/// `$WAYLAND_DISPLAY` naming a live socket under it. A misconfigured
+/// systemd unit otherwise surfaces as a cryptic Wayland connect failure.
+pub fn environment_report() -> crate::report::HealthReport {
+    use crate::report::CheckResult;
+
+    let mut checks = Vec::new();
+    match env::var_os(XDG_RUNTIME_DIR_ENV) {
+        None => checks.push(CheckResult::fail(
+            "xdg-runtime-dir",
+            format!("{XDG_RUNTIME_DIR_ENV} is not set"),
+        )),
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            checks.extend(runtime_dir_checks(&dir));
+            match env::var(WAYLAND_DISPLAY_ENV) {
+                Err(_) => checks.push(CheckResult::fail(
+                    "wayland-display",
+                    format!("{WAYLAND_DISPLAY_ENV} is not set"),
+                )),
+                Ok(display) => checks.push(wayland_display_check(&dir, &display)),
+            }
+        }
+    }
+    crate::report::HealthReport::from_checks(checks)
+}
+
+/// Remediation hints for the failed checks of an environment report.
+pub fn get_recommendations(report: &crate::report::HealthReport) -> Vec<String> {
+    report
+        .checks
+        .iter()
+        .filter(|check| !check.passed)
+        .map(|check| match check.name.as_str() {
+            "xdg-runtime-dir" => format!(
+                "ensure {XDG_RUNTIME_DIR_ENV} points at your session runtime directory \
+                 (usually /run/user/<uid>); under systemd, order the unit after \
+                 graphical-session.target"
+            ),
+            "xdg-runtime-dir-mode" => {
+                "fix the runtime directory permissions: chmod 0700 \"$XDG_RUNTIME_DIR\""
+                    .to_string()
+            }
+            "xdg-runtime-dir-owner" => {
+                "the runtime directory belongs to another user; do not share or sudo into \
+                 someone else's session"
+                    .to_string()
+            }
+            "wayland-display" => format!(
+                "ensure {WAYLAND_DISPLAY_ENV} is set to the compositor's socket name \
+                 (usually wayland-1) and that the compositor is running"
+            ),
+            other => format!("fix failing check {other}"),
+        })
+        .collect()
+}
+
+/// Turns a connect probe's outcome into a pass or a structured reason.
+/// Separated from the I/O so the decision table is unit-testable with
+/// injected results.
+fn access_decision(probe: std::result::Result<(), std::io::ErrorKind>) -> std::result::Result<(), String> {
+    use std::io::ErrorKind;
+    match probe {
+        Ok(()) => Ok(()),
+        Err(ErrorKind::PermissionDenied) => Err(
+            "access denied connecting to the socket (check ownership/ACLs)".to_string(),
+        ),
+        Err(ErrorKind::ConnectionRefused) => Err(
+            "socket exists but nothing is listening (stale socket from a dead niri?)"
+                .to_string(),
+        ),
+        Err(kind) => Err(format!("socket is not connectable: {kind:?}")),
+    }
+}
+
+/// The directory-shape checks, separated so tests can aim them at
+/// temp directories.
+fn runtime_dir_checks(dir: &Path) -> Vec<crate::report::CheckResult> {
+    use crate::report::CheckResult;
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = match std::fs::metadata(dir) {
+        Err(e) => {
+            return vec![CheckResult::fail(
+                "xdg-runtime-dir",
+                format!("{} is not accessible: {e}", dir.display()),
+            )]
+        }
+        Ok(metadata) => metadata,
+    };
+    let mut checks = Vec::new();
+    if metadata.is_dir() {
+        checks.push(CheckResult::pass("xdg-runtime-dir").with_detail(dir.display().to_string()));
+    } else {
+        checks.push(CheckResult::fail(
+            "xdg-runtime-dir",
+            format!("{} is not a directory", dir.display()),
+        ));
+        return checks;
+    }
+
+    // std has no direct geteuid; the metadata of a file we just created
+    // would, but /proc/self is simpler and avoids the libc dependency in
+    // non-native builds.
+    let our_uid = std::fs::metadata("/proc/self")
+        .map(|m| m.uid())
+        .unwrap_or(u32::MAX);
+    if metadata.uid() == our_uid {
+        checks.push(CheckResult::pass("xdg-runtime-dir-owner"));
+    } else {
+        checks.push(CheckResult::fail(
+            "xdg-runtime-dir-owner",
+            format!("owned by uid {}, we are {our_uid}", metadata.uid()),
+        ));
+    }
+
+    let mode = metadata.mode() & 0o777;
+    if mode == 0o700 {
+        checks.push(CheckResult::pass("xdg-runtime-dir-mode"));
+    } else {
+        checks.push(CheckResult::fail(
+            "xdg-runtime-dir-mode",
+            format!("mode is {mode:o}, expected 700"),
+        ));
+    }
+    checks
+}
+
+/// Whether `$WAYLAND_DISPLAY` names a live socket under the runtime dir.
+fn wayland_display_check(dir: &Path, display: &str) -> crate::report::CheckResult {
+    use crate::report::CheckResult;
+
+    // Absolute display paths are allowed by the protocol.
+    let path = if display.starts_with('/') {
+        PathBuf::from(display)
+    } else {
+        dir.join(display)
+    };
+    match std::fs::metadata(&path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            CheckResult::pass("wayland-display").with_detail(path.display().to_string())
+        }
+        Ok(_) => CheckResult::fail(
+            "wayland-display",
+            format!("{} exists but is not a socket", path.display()),
+        ),
+        Err(_) => CheckResult::fail(
+            "wayland-display",
+            format!("{} does not exist", path.display()),
+        ),
+    }
+}
+
+/// Checks that the environment points at a usable niri IPC socket.
+#[derive(Debug)]
+pub struct SessionValidator {
+    socket_path: PathBuf,
+}
+
+impl SessionValidator {
+    /// Builds a validator for an explicit socket path.
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+        }
+    }
+
+    /// Builds a validator from `$NIRI_SOCKET`.
+    pub fn from_env() -> Result<Self> {
+        let path = env::var(NIRI_SOCKET_ENV)
+            .map_err(|_| NiriSpacerError::MissingEnvVar(NIRI_SOCKET_ENV.to_string()))?;
+        Ok(Self::new(path))
+    }
+
+    /// The socket path this validator was built with.
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Runs every check in order, stopping at the first failure.
+    pub fn validate(&self) -> Result<()> {
+        self.check_exists()?;
+        self.check_permissions()?;
+        if let Some(version) = Self::detect_compositor_version()? {
+            info!(%version, "detected compositor");
+        }
+        let environment = environment_report();
+        if !environment.ok {
+            for hint in get_recommendations(&environment) {
+                warn!("{hint}");
+            }
+        }
+        debug!(path = %self.socket_path.display(), "session validation passed");
+        Ok(())
+    }
+
+    /// Detects the compositor version from the `niri` binary on `$PATH`,
+    /// without an IPC connection. Useful for flagging incompatibility before
+    /// we try to connect. Returns `Ok(None)` when no binary is found -- niri
+    /// may well be running from a non-PATH location. The result is computed
+    /// once per process and cached.
+    pub fn detect_compositor_version() -> Result<Option<String>> {
+        Ok(COMPOSITOR_VERSION.clone())
+    }
+
+    fn check_exists(&self) -> Result<()> {
+        if self.socket_path.exists() {
+            Ok(())
+        } else {
+            Err(self.invalid("path does not exist"))
+        }
+    }
+
+    /// Verifies the path is a Unix domain socket the current user can
+    /// actually use.
+    ///
+    /// The file-type check matters in practice: a common misconfiguration is
+    /// `$NIRI_SOCKET` pointing at a log file or PID file, which has sane
+    /// permission bits but would only fail later on `connect(2)`.
+    ///
+    /// Usability is judged by an attempted connect rather than mode bits:
+    /// owner-bit inspection wrongly rejects sockets granted via group
+    /// permissions or ACLs, and wrongly accepts another user's socket whose
+    /// owner bits merely look right. The connect is exactly the access
+    /// check the kernel will apply for real.
+    pub fn check_permissions(&self) -> Result<()> {
+        let metadata = self.socket_path.metadata()?;
+
+        if !metadata.file_type().is_socket() {
+            return Err(self.invalid("Path exists but is not a Unix socket"));
+        }
+
+        let probe = std::os::unix::net::UnixStream::connect(&self.socket_path)
+            .map(|_| ())
+            .map_err(|e| e.kind());
+        match access_decision(probe) {
+            Ok(()) => Ok(()),
+            Err(reason) => Err(self.invalid(&reason)),
+        }
+    }
+
+    fn invalid(&self, reason: &str) -> NiriSpacerError {
+        NiriSpacerError::InvalidSocketPath {
+            path: self.socket_path.display().to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    fn accepts_real_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri.sock");
+        let _listener = UnixListener::bind(&path).unwrap();
+
+        SessionValidator::new(&path).validate().unwrap();
+    }
+
+    #[test]
+    fn rejects_regular_file_at_socket_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri.sock");
+        fs::write(&path, "not a socket").unwrap();
+
+        let err = SessionValidator::new(&path).check_permissions().unwrap_err();
+        match err {
+            NiriSpacerError::InvalidSocketPath { reason, .. } => {
+                assert_eq!(reason, "Path exists but is not a Unix socket");
+            }
+            other => panic!("expected InvalidSocketPath, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn group_readable_sockets_are_accepted() {
+        // Owner bits alone used to be required; group-granted access (or
+        // any mode under which connect succeeds) must pass now.
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri.sock");
+        let _listener = UnixListener::bind(&path).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o660)).unwrap();
+
+        SessionValidator::new(&path).check_permissions().unwrap();
+    }
+
+    #[test]
+    fn stale_socket_reports_a_structured_reason() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri.sock");
+        // Bind then drop: the filesystem entry stays, nothing listens.
+        drop(UnixListener::bind(&path).unwrap());
+
+        let err = SessionValidator::new(&path).check_permissions().unwrap_err();
+        assert!(
+            err.to_string().contains("nothing is listening"),
+            "got: {err}"
+        );
+    }
+
+    #[test]
+    fn access_decision_table() {
+        use std::io::ErrorKind;
+        assert!(access_decision(Ok(())).is_ok());
+        assert!(access_decision(Err(ErrorKind::PermissionDenied))
+            .unwrap_err()
+            .contains("access denied"));
+        assert!(access_decision(Err(ErrorKind::ConnectionRefused))
+            .unwrap_err()
+            .contains("stale socket"));
+        assert!(access_decision(Err(ErrorKind::TimedOut))
+            .unwrap_err()
+            .contains("not connectable"));
+    }
+
+    #[test]
+    fn runtime_dir_checks_pass_for_a_private_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o700)).unwrap();
+
+        let checks = runtime_dir_checks(dir.path());
+        assert!(checks.iter().all(|c| c.passed), "{checks:?}");
+    }
+
+    #[test]
+    fn runtime_dir_checks_flag_wrong_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+
+        let checks = runtime_dir_checks(dir.path());
+        let mode_check = checks
+            .iter()
+            .find(|c| c.name == "xdg-runtime-dir-mode")
+            .unwrap();
+        assert!(!mode_check.passed);
+        assert!(mode_check.detail.as_deref().unwrap().contains("755"));
+    }
+
+    #[test]
+    fn runtime_dir_checks_flag_a_file_instead_of_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("not-a-dir");
+        fs::write(&file, "x").unwrap();
+
+        let checks = runtime_dir_checks(&file);
+        assert!(!checks[0].passed);
+        assert!(checks[0].detail.as_deref().unwrap().contains("not a directory"));
+    }
+
+    #[test]
+    fn wayland_display_check_distinguishes_missing_and_wrong_type() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let missing = wayland_display_check(dir.path(), "wayland-1");
+        assert!(!missing.passed);
+        assert!(missing.detail.as_deref().unwrap().contains("does not exist"));
+
+        fs::write(dir.path().join("wayland-1"), "x").unwrap();
+        let not_socket = wayland_display_check(dir.path(), "wayland-1");
+        assert!(!not_socket.passed);
+        assert!(not_socket.detail.as_deref().unwrap().contains("not a socket"));
+
+        let sock = dir.path().join("wayland-2");
+        let _listener = UnixListener::bind(&sock).unwrap();
+        assert!(wayland_display_check(dir.path(), "wayland-2").passed);
+    }
+
+    #[test]
+    fn recommendations_cover_failed_checks() {
+        let report = crate::report::HealthReport::from_checks(vec![
+            crate::report::CheckResult::fail("xdg-runtime-dir", "unset"),
+            crate::report::CheckResult::pass("wayland-display"),
+        ]);
+        let hints = get_recommendations(&report);
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].contains("graphical-session.target"), "{hints:?}");
+    }
+
+    #[tokio::test]
+    async fn wait_for_niri_connects_once_the_socket_appears() {
+        let _env = crate::test_support::env_lock().await;
+        let niri = crate::test_support::MockNiri::spawn(
+            crate::test_support::MockNiri::three_workspaces(),
+            vec![],
+        )
+        .await;
+        let socket = niri.socket_path().to_path_buf();
+        // The env var appears only after a delay, as if niri were still
+        // starting up.
+        std::env::remove_var(NIRI_SOCKET_ENV);
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+            std::env::set_var(NIRI_SOCKET_ENV, &socket);
+        });
+
+        let validator = wait_for_niri(
+            std::time::Duration::from_secs(5),
+            &crate::clock::TokioClock,
+        )
+        .await
+        .unwrap();
+        assert_eq!(validator.socket_path(), niri.socket_path());
+    }
+
+    #[tokio::test]
+    async fn wait_for_niri_gives_up_at_the_deadline() {
+        let _env = crate::test_support::env_lock().await;
+        std::env::remove_var(NIRI_SOCKET_ENV);
+
+        let clock = crate::clock::TestClock::new();
+        let err = wait_for_niri(std::time::Duration::from_secs(30), &clock)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, NiriSpacerError::MissingEnvVar(_)), "{err:?}");
+    }
+
+    #[test]
+    fn version_is_read_from_a_mock_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("niri");
+        fs::write(&binary, "#!/bin/sh\necho 'niri 25.05.1 (mock)'\n").unwrap();
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let version = version_from_binary(&binary).unwrap();
+        assert_eq!(version.as_deref(), Some("niri 25.05.1 (mock)"));
+    }
+
+    #[test]
+    fn failing_binary_yields_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("niri");
+        fs::write(&binary, "#!/bin/sh\nexit 1\n").unwrap();
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(version_from_binary(&binary).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_missing_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing.sock");
+
+        assert!(SessionValidator::new(&path).validate().is_err());
+    }
+}
@@ -0,0 +1,4107 @@
+//! The orchestrator: plans spacer placement and drives a backend plus the
+//! niri IPC client to carry the plan out.
+
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+use crate::backend::{Color, NativeConfig, SpacerBackend, DEFAULT_PALETTE};
+use crate::clock::{Clock, TokioClock};
+use crate::error::{NiriSpacerError, Result};
+use crate::focus::SpacerIdSet;
+use crate::native::NativeWindowManager;
+use crate::niri::{Action, NiriClient, SizeChange, WorkspaceReference};
+use crate::session::SessionValidator;
+use crate::window::{SpacerWindow, WindowManager};
+use crate::workspace::WorkspaceManager;
+
+/// How long to wait for a freshly created window to show up in niri.
+const CORRELATION_TIMEOUT: Duration = Duration::from_secs(5);
+/// Poll interval while waiting for correlation.
+const CORRELATION_POLL: Duration = Duration::from_millis(50);
+/// Default budget for one window's move-and-verify sequence.
+const PLACEMENT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Per-window budget for confirming a close during cleanup.
+const CLEANUP_WINDOW_TIMEOUT: Duration = Duration::from_secs(2);
+/// Settle delays between compositor operations.
+///
+/// Workspace focus changes need noticeably longer to settle than
+/// intra-workspace operations, so they get their own knob instead of
+/// inflating the generic delay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timings {
+    /// Pause between intra-workspace operations.
+    pub operation_delay: Duration,
+    /// Pause after a workspace focus change.
+    pub workspace_switch_delay: Duration,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self {
+            operation_delay: Duration::from_millis(50),
+            workspace_switch_delay: Duration::from_millis(50),
+        }
+    }
+}
+
+/// Hard ceiling on concurrently managed spacer windows. Enforced in the
+/// library, not just the CLI, so embedders cannot grow `active_spacers`
+/// without bound.
+pub const MAX_WINDOW_COUNT: u32 = 50;
+/// Safety-net reconciliation period in watch mode, catching missed events.
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
+/// Minimum spacing between config-reload reconciliation passes.
+const CONFIG_RECONCILE_COOLDOWN: Duration = Duration::from_secs(5);
+/// Maximum entries retained in the repositioning audit log.
+const AUDIT_LOG_CAP: usize = 128;
+/// How many recent audit entries ride along in each status snapshot.
+const STATUS_AUDIT_ENTRIES: usize = 10;
+
+/// Top-level configuration for a run.
+#[derive(Debug, Clone)]
+pub struct NiriSpacerConfig {
+    /// Path to the niri IPC socket.
+    pub socket_path: PathBuf,
+    /// How many workspaces (lowest indices first) to cover; `None` = all.
+    pub count: Option<u8>,
+    /// Budget for one window's move-and-verify sequence.
+    pub placement_timeout: Duration,
+    /// What to do when a target workspace is non-empty.
+    pub on_occupied: OccupiedPolicy,
+    /// Keep going past individual spacer failures, collecting them in the
+    /// run report instead of aborting the batch.
+    pub best_effort: bool,
+    /// What to do when correlation times out for one window of a batch.
+    pub on_correlation_fail: CorrelationFailPolicy,
+    /// Manage workspaces on every output instead of only the focused one.
+    pub all_outputs: bool,
+    /// Choose respawn/add targets by scoring (proximity, density, output)
+    /// instead of first-vacant.
+    pub smart_placement: bool,
+    /// Creation order of the planned spacers.
+    pub order: PlacementOrder,
+    /// How post-creation verification treats vanished spacers.
+    pub validation: ValidationPolicy,
+    /// Restrict placement to workspaces with indices in this inclusive
+    /// range.
+    pub workspace_range: Option<(u8, u8)>,
+    /// Clean up leftover spacers from previous transient sessions at
+    /// startup (spacers are always closed on clean exit).
+    pub transient: bool,
+    /// Where to maintain the spacer-number -> niri-window mapping for
+    /// external tools; `None` disables the file.
+    pub mapping_file: Option<PathBuf>,
+    /// Append the niri window ID to each spacer's title after correlation.
+    pub embed_id_in_title: bool,
+    /// Column width to reserve per spacer; defaults to the fixed native
+    /// window width.
+    pub column_width: Option<ColumnWidth>,
+    /// How the minimized width is requested when no explicit column width
+    /// is given.
+    pub width_mode: SpacerWidthMode,
+    /// Settle delays for compositor operations.
+    pub timings: Timings,
+    /// Window appearance settings handed to the backend.
+    pub native: NativeConfig,
+    /// Service name reported on exported spans.
+    #[cfg(feature = "opentelemetry")]
+    pub otel_service_name: String,
+}
+
+impl NiriSpacerConfig {
+    /// The full default configuration for a socket path; the bottom layer
+    /// of the merge chain.
+    pub fn with_defaults(socket_path: impl Into<PathBuf>) -> Self {
+        Self::new(socket_path)
+    }
+
+    /// Applies one overlay layer on top of this configuration: fields the
+    /// overlay leaves `None` keep their current value, set fields replace
+    /// it. Apply the file layer first, then the CLI layer, so CLI beats
+    /// file beats defaults.
+    pub fn merge(mut self, overlay: &ConfigOverlay) -> Result<Self> {
+        if let Some(count) = overlay.count {
+            self.count = Some(count);
+        }
+        if let Some(spacing) = overlay.spacing {
+            self.native.width = spacing;
+        }
+        if let Some(hex) = &overlay.color {
+            self.native.color = Color::from_hex(hex)?;
+        }
+        if let Some(policy) = &overlay.on_occupied {
+            self.on_occupied = policy
+                .parse()
+                .map_err(NiriSpacerError::ConfigParse)?;
+        }
+        if let Some(best_effort) = overlay.best_effort {
+            self.best_effort = best_effort;
+        }
+        if let Some(all_outputs) = overlay.all_outputs {
+            self.all_outputs = all_outputs;
+        }
+        if let Some(smart) = overlay.smart_placement {
+            self.smart_placement = smart;
+        }
+        if let Some(order) = &overlay.order {
+            self.order = order.parse().map_err(NiriSpacerError::ConfigParse)?;
+        }
+        Ok(self)
+    }
+
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+            count: None,
+            placement_timeout: PLACEMENT_TIMEOUT,
+            on_occupied: OccupiedPolicy::default(),
+            best_effort: false,
+            on_correlation_fail: CorrelationFailPolicy::default(),
+            all_outputs: false,
+            smart_placement: false,
+            order: PlacementOrder::default(),
+            validation: ValidationPolicy::default(),
+            workspace_range: None,
+            transient: false,
+            mapping_file: Some(default_mapping_file()),
+            embed_id_in_title: false,
+            column_width: None,
+            width_mode: SpacerWidthMode::default(),
+            timings: Timings::default(),
+            native: NativeConfig::default(),
+            #[cfg(feature = "opentelemetry")]
+            otel_service_name: "niri-spacer".to_string(),
+        }
+    }
+}
+
+/// What to do when a target workspace already has windows on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OccupiedPolicy {
+    /// Log a warning and place the spacer anyway.
+    #[default]
+    Warn,
+    /// Leave the occupied workspace alone and target the next free one.
+    Skip,
+    /// Abort the run.
+    Fail,
+}
+
+impl std::str::FromStr for OccupiedPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(Self::Warn),
+            "skip" => Ok(Self::Skip),
+            "fail" => Ok(Self::Fail),
+            other => Err(format!("unknown policy {other:?}: expected warn, skip, or fail")),
+        }
+    }
+}
+
+/// What to do when a freshly created window cannot be correlated with a
+/// niri window before the timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum CorrelationFailPolicy {
+    /// Abort the batch (default).
+    #[default]
+    Abort,
+    /// Close the orphaned native window and continue with the rest.
+    Skip,
+}
+
+impl std::str::FromStr for CorrelationFailPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "abort" => Ok(Self::Abort),
+            "skip" => Ok(Self::Skip),
+            other => Err(format!(
+                "unknown correlation-fail policy {other:?}: expected abort or skip"
+            )),
+        }
+    }
+}
+
+/// How post-creation verification treats spacers that vanished between
+/// creation and validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ValidationPolicy {
+    /// Log, drop the missing spacer from the active set, and keep the
+    /// rest. The right default for the persistent daemon, which can
+    /// respawn the missing one.
+    #[default]
+    Lenient,
+    /// Fail the run on the first missing spacer.
+    Strict,
+}
+
+impl std::str::FromStr for ValidationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "lenient" => Ok(Self::Lenient),
+            "strict" => Ok(Self::Strict),
+            other => Err(format!(
+                "unknown validation policy {other:?}: expected lenient or strict"
+            )),
+        }
+    }
+}
+
+/// One layer of configuration: every field optional, `None` meaning "not
+/// specified here, use the lower layer's value". Layers stack as
+/// defaults -> config file -> CLI flags via [`NiriSpacerConfig::merge`].
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConfigOverlay {
+    pub count: Option<u8>,
+    /// Spacer width in pixels.
+    pub spacing: Option<u32>,
+    /// Base color as `#rrggbb`.
+    pub color: Option<String>,
+    /// `warn`, `skip`, or `fail`.
+    pub on_occupied: Option<String>,
+    pub best_effort: Option<bool>,
+    pub all_outputs: Option<bool>,
+    pub smart_placement: Option<bool>,
+    /// `forward` or `reverse`.
+    pub order: Option<String>,
+}
+
+impl ConfigOverlay {
+    /// Parses a TOML config file into one overlay layer.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_toml_str(&text)
+    }
+
+    /// Parses TOML text into one overlay layer.
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        toml::from_str(text)
+            .map_err(|e| NiriSpacerError::ConfigParse(format!("invalid config file: {e}")))
+    }
+}
+
+/// Pretty listing of existing workspaces, appended to
+/// [`NiriSpacerError::WorkspaceIndexOutOfRange`] output so the user can
+/// pick a valid range without another query.
+pub struct AvailableWorkspaces<'a>(pub &'a [(u8, Option<String>)]);
+
+impl std::fmt::Display for AvailableWorkspaces<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "available workspaces:")?;
+        for (idx, name) in self.0 {
+            match name {
+                Some(name) => writeln!(f, "  {idx}: {name}")?,
+                None => writeln!(f, "  {idx}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Order in which the plan's spacers are created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum PlacementOrder {
+    /// Lowest workspace index first (default).
+    #[default]
+    Forward,
+    /// Highest workspace index first. Creating bottom-up avoids the cascade
+    /// where each new trailing workspace shifts what "last empty workspace"
+    /// means, and some users simply prefer the visual order.
+    Reverse,
+}
+
+impl std::str::FromStr for PlacementOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "forward" => Ok(Self::Forward),
+            "reverse" => Ok(Self::Reverse),
+            other => Err(format!(
+                "unknown order {other:?}: expected forward or reverse"
+            )),
+        }
+    }
+}
+
+/// How spacers should look while niri's overview is open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OverviewStyle {
+    /// Leave spacers as they are.
+    #[default]
+    Normal,
+    /// Redraw spacers fully transparent while the overview is open.
+    Hide,
+    /// Redraw spacers at low opacity while the overview is open.
+    Dim,
+}
+
+impl std::str::FromStr for OverviewStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Self::Normal),
+            "hide" => Ok(Self::Hide),
+            "dim" => Ok(Self::Dim),
+            other => Err(format!(
+                "unknown overview style {other:?}: expected hide, dim, or normal"
+            )),
+        }
+    }
+}
+
+/// How much niri column width a spacer should reserve: a proportion of the
+/// screen (`0.05`) or a fixed pixel count (`40px`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnWidth {
+    Proportion(f64),
+    FixedPx(i32),
+}
+
+impl ColumnWidth {
+    pub fn to_size_change(self) -> SizeChange {
+        match self {
+            Self::Proportion(p) => SizeChange::SetProportion(p),
+            Self::FixedPx(px) => SizeChange::SetFixed(px),
+        }
+    }
+}
+
+impl std::str::FromStr for ColumnWidth {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if let Some(px) = s.strip_suffix("px") {
+            let px: i32 = px
+                .parse()
+                .map_err(|_| format!("invalid pixel width {s:?}"))?;
+            if px < 1 {
+                return Err(format!("pixel width must be positive, got {px}"));
+            }
+            return Ok(Self::FixedPx(px));
+        }
+        let proportion: f64 = s.parse().map_err(|_| {
+            format!("invalid column width {s:?}: expected a proportion like 0.05 or pixels like 40px")
+        })?;
+        if !(0.0..=1.0).contains(&proportion) || proportion == 0.0 {
+            return Err(format!(
+                "proportion must be within (0, 1], got {proportion}; use e.g. 40px for pixels"
+            ));
+        }
+        Ok(Self::Proportion(proportion))
+    }
+}
+
+/// How the minimized spacer's column width is requested from niri. Some
+/// niri configs ignore a 1px fixed width but honor a small proportion, so
+/// proportion mode is the fallback when fixed doesn't take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SpacerWidthMode {
+    /// `SetFixed` with the configured pixel width (default).
+    #[default]
+    FixedPx,
+    /// `SetProportion` with a small screen proportion.
+    Proportion,
+}
+
+impl std::str::FromStr for SpacerWidthMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "fixed-px" => Ok(Self::FixedPx),
+            "proportion" => Ok(Self::Proportion),
+            other => Err(format!(
+                "unknown width mode {other:?}: expected fixed-px or proportion"
+            )),
+        }
+    }
+}
+
+/// The proportion used by [`SpacerWidthMode::Proportion`]: small enough to
+/// read as a divider, large enough that niri doesn't round it away.
+const MINIMAL_PROPORTION: f64 = 0.01;
+
+/// Opacity used for [`OverviewStyle::Dim`].
+const OVERVIEW_DIM_OPACITY: f64 = 0.25;
+
+/// Default location of the window mapping file:
+/// `$XDG_RUNTIME_DIR/niri-spacer/windows.json`.
+pub fn default_mapping_file() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("niri-spacer")
+        .join("windows.json")
+}
+
+/// One entry of the on-disk window mapping, keyed by spacer number. Shared
+/// by the mapping file and status-style serialization so external tools
+/// only parse one shape.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MappingEntry {
+    pub niri_id: u64,
+    pub workspace_idx: u8,
+    pub app_id: String,
+}
+
+/// What a run achieved: every placement, or a partial set with the failures
+/// itemized (best-effort mode only).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunReport {
+    /// Number of spacers successfully placed.
+    pub placed: u32,
+    /// Workspaces whose spacer could not be created, with the error text.
+    pub failures: Vec<PlacementFailure>,
+    /// Workspaces skipped because correlation timed out (policy `skip`).
+    pub skipped: Vec<PlacementFailure>,
+    /// Spacers dropped by lenient validation because they vanished between
+    /// creation and verification.
+    pub validation_dropped: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlacementFailure {
+    pub workspace_idx: u8,
+    pub error: String,
+}
+
+impl RunReport {
+    pub fn is_full_success(&self) -> bool {
+        self.failures.is_empty() && self.skipped.is_empty()
+    }
+
+    /// Logs the outcome in a human-readable form.
+    pub fn log(&self) {
+        if self.is_full_success() {
+            info!(placed = self.placed, "all spacers placed");
+            return;
+        }
+        warn!(
+            placed = self.placed,
+            failed = self.failures.len(),
+            skipped = self.skipped.len(),
+            "partial success"
+        );
+        for failure in self.failures.iter().chain(&self.skipped) {
+            warn!(
+                workspace = failure.workspace_idx,
+                error = %failure.error,
+                "workspace was skipped"
+            );
+        }
+    }
+}
+
+/// One step of the placement plan: a spacer of `color` on workspace `idx`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub workspace_id: u64,
+    pub workspace_idx: u8,
+    pub color: Color,
+}
+
+impl std::fmt::Display for Placement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "workspace {} <- spacer {}", self.workspace_idx, self.color)
+    }
+}
+
+/// Computes the placement plan for a configuration: one spacer per targeted
+/// workspace (lowest indices first), cycling the default palette. Needs only
+/// the niri socket, so `--dry-run` can use it without a Wayland connection.
+pub async fn compute_plan(config: &NiriSpacerConfig) -> Result<Vec<Placement>> {
+    let client = NiriClient::new(&config.socket_path);
+    let workspaces = WorkspaceManager::new(client.clone()).workspaces_sorted().await?;
+    let workspaces = scope_to_output(workspaces, config.all_outputs);
+    let workspaces = match config.workspace_range {
+        Some((start, end)) => {
+            let available_max = workspaces.iter().map(|ws| ws.idx).max().unwrap_or(0);
+            let in_range: Vec<crate::niri::Workspace> = workspaces
+                .iter()
+                .filter(|ws| (start..=end).contains(&ws.idx))
+                .cloned()
+                .collect();
+            if in_range.is_empty() {
+                return Err(NiriSpacerError::WorkspaceIndexOutOfRange {
+                    requested_start: start,
+                    requested_end: end,
+                    available_max,
+                    available: workspaces
+                        .iter()
+                        .map(|ws| (ws.idx, ws.name.clone()))
+                        .collect(),
+                });
+            }
+            if end > available_max {
+                warn!(end, available_max, "range extends past the last workspace; clamping");
+            }
+            in_range
+        }
+        None => workspaces,
+    };
+    let take = config.count.map(usize::from).unwrap_or(workspaces.len());
+
+    // Occupancy per workspace, for the --on-occupied policy.
+    let mut occupancy: std::collections::HashMap<u64, usize> = std::collections::HashMap::new();
+    for window in client.get_windows().await? {
+        if let Some(ws) = window.workspace_id {
+            *occupancy.entry(ws).or_default() += 1;
+        }
+    }
+
+    let mut plan = Vec::with_capacity(take);
+    for ws in workspaces {
+        if plan.len() == take {
+            break;
+        }
+        let windows = occupancy.get(&ws.id).copied().unwrap_or(0);
+        if windows > 0 {
+            match config.on_occupied {
+                OccupiedPolicy::Warn => {
+                    warn!(idx = ws.idx, windows, "target workspace is not empty");
+                }
+                OccupiedPolicy::Skip => {
+                    debug!(idx = ws.idx, windows, "skipping occupied workspace");
+                    continue;
+                }
+                OccupiedPolicy::Fail => {
+                    return Err(NiriSpacerError::WorkspaceOccupied {
+                        idx: ws.idx,
+                        windows,
+                    });
+                }
+            }
+        }
+        plan.push(Placement {
+            workspace_id: ws.id,
+            workspace_idx: ws.idx,
+            color: DEFAULT_PALETTE[plan.len() % DEFAULT_PALETTE.len()],
+        });
+    }
+    if config.order == PlacementOrder::Reverse {
+        // Colors stay tied to their workspaces; only creation order flips.
+        plan.reverse();
+    }
+    Ok(plan)
+}
+
+/// Lifecycle notifications for external monitoring (status bars, GUI
+/// overlays) without polling. Delivered on a broadcast channel: every
+/// subscriber gets every event, and a slow subscriber lags (dropping its
+/// oldest events) rather than blocking anyone else.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpacerEvent {
+    SpacerCreated(SpacerWindow),
+    SpacerFocused(u64),
+    SpacerFocusRedirected { from: u64, redirected: bool },
+    SpacerMisplaced(u64),
+    SpacerClosed(u64),
+}
+
+/// How a spacer sits in its column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnOccupancy {
+    /// Alone in its column: properly positioned.
+    SingleTile,
+    /// Other windows share the column (squeezed behind a 1-pixel spacer).
+    CoTenants(Vec<u64>),
+    /// Floating or no layout data; column checks do not apply.
+    Floating,
+}
+
+/// Classifies a spacer's column occupancy from a window listing. Checking
+/// the column index alone is not enough: niri can stack another window as a
+/// second tile into the spacer's column, which squeezes it behind a 1-pixel
+/// column while every per-column check still looks fine.
+pub fn column_occupancy(windows: &[crate::niri::Window], spacer_id: u64) -> ColumnOccupancy {
+    let Some(spacer) = windows.iter().find(|w| w.id == spacer_id) else {
+        return ColumnOccupancy::Floating;
+    };
+    let Some((column, _tile)) = spacer
+        .layout
+        .as_ref()
+        .and_then(|l| l.pos_in_scrolling_layout)
+    else {
+        return ColumnOccupancy::Floating;
+    };
+
+    let cotenants: Vec<u64> = windows
+        .iter()
+        .filter(|w| w.id != spacer_id && w.workspace_id == spacer.workspace_id)
+        .filter(|w| {
+            w.layout
+                .as_ref()
+                .and_then(|l| l.pos_in_scrolling_layout)
+                .is_some_and(|(c, _)| c == column)
+        })
+        .map(|w| w.id)
+        .collect();
+    if cotenants.is_empty() {
+        ColumnOccupancy::SingleTile
+    } else {
+        ColumnOccupancy::CoTenants(cotenants)
+    }
+}
+
+/// Restricts a workspace list to the focused output unless `all_outputs`.
+///
+/// Multi-output sessions get one spacer set per instance by default;
+/// mixing outputs placed spacers on monitors the instance was never meant
+/// to manage. The focused output is resolved from the same response, so a
+/// reconnect automatically re-resolves it.
+fn scope_to_output(
+    workspaces: Vec<crate::niri::Workspace>,
+    all_outputs: bool,
+) -> Vec<crate::niri::Workspace> {
+    if all_outputs {
+        return workspaces;
+    }
+    let Some(focused_output) = workspaces
+        .iter()
+        .find(|ws| ws.is_focused)
+        .and_then(|ws| ws.output.clone())
+    else {
+        // No focus or no output info: nothing to scope by.
+        return workspaces;
+    };
+    workspaces
+        .into_iter()
+        .filter(|ws| ws.output.as_deref() == Some(focused_output.as_str()))
+        .collect()
+}
+
+/// Drives one window's placement step by step, verifying each step landed
+/// before starting the next.
+///
+/// niri applies actions asynchronously: a `MoveWindowToWorkspace` that has
+/// been acknowledged may not be reflected in workspace state yet, and acting
+/// on the stale state targets the wrong workspace. Instead of fixed sleeps,
+/// each step polls the compositor until its effect is visible, all within
+/// one per-window deadline. Both the batch creation path and reconciliation
+/// go through this type.
+pub struct PlacementTransaction<'a> {
+    client: &'a NiriClient,
+    windows: &'a WindowManager,
+    timeout: Duration,
+    clock: std::sync::Arc<dyn Clock>,
+}
+
+impl<'a> PlacementTransaction<'a> {
+    pub fn new(client: &'a NiriClient, windows: &'a WindowManager) -> Self {
+        Self {
+            client,
+            windows,
+            timeout: PLACEMENT_TIMEOUT,
+            clock: std::sync::Arc::new(TokioClock),
+        }
+    }
+
+    /// Overrides the per-window deadline.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Overrides the time source (tests use a deterministic clock).
+    pub fn with_clock(mut self, clock: std::sync::Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Moves `window_id` to the placement's workspace and waits until niri
+    /// reports it there.
+    pub async fn place(&self, window_id: u64, placement: &Placement) -> Result<()> {
+        let deadline = self.clock.now() + self.timeout;
+
+        self.client
+            .action(Action::MoveWindowToWorkspace {
+                window_id: Some(window_id),
+                reference: WorkspaceReference::Index(placement.workspace_idx),
+            })
+            .await?;
+        self.verify_on_workspace(window_id, placement.workspace_id, deadline)
+            .await
+    }
+
+    /// Polls until the window's reported workspace matches, or the deadline
+    /// passes, with exponential backoff starting at 10ms: instant moves
+    /// confirm on the first query, loaded systems get progressively gentler
+    /// polling.
+    async fn verify_on_workspace(
+        &self,
+        window_id: u64,
+        workspace_id: u64,
+        deadline: tokio::time::Instant,
+    ) -> Result<()> {
+        let started = self.clock.now();
+        let mut backoff = Duration::from_millis(10);
+        loop {
+            let on_target = self
+                .windows
+                .get_windows()
+                .await?
+                .into_iter()
+                .find(|w| w.id == window_id)
+                .is_some_and(|w| w.workspace_id == Some(workspace_id));
+            if on_target {
+                debug!(
+                    window = window_id,
+                    waited_ms = (self.clock.now() - started).as_millis() as u64,
+                    "workspace move confirmed"
+                );
+                return Ok(());
+            }
+            if self.clock.now() >= deadline {
+                return Err(NiriSpacerError::Ipc(format!(
+                    "window {window_id} did not land on workspace {workspace_id} within {:?}",
+                    self.timeout
+                )));
+            }
+            self.clock.sleep(backoff).await;
+            backoff = (backoff * 2).min(Duration::from_millis(160));
+        }
+    }
+}
+
+/// Which corrective path repositioned a spacer.
+///
+/// When a user reports "my windows keep jumping", this distinguishes our
+/// focus-monitor fix from the reconciliation loop from a respawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepositionTrigger {
+    InitialPlacement,
+    FocusFix,
+    Reconciliation,
+    Respawn,
+}
+
+/// How a repositioning attempt ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepositionOutcome {
+    Success,
+    Failed(String),
+}
+
+/// One entry in the repositioning audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepositionEvent {
+    /// niri window ID of the spacer involved.
+    pub spacer_id: u64,
+    pub trigger: RepositionTrigger,
+    /// Workspace the spacer was on before, when known.
+    pub from: Option<u64>,
+    /// Workspace the spacer was moved to.
+    pub to: u64,
+    pub timestamp: std::time::SystemTime,
+    pub outcome: RepositionOutcome,
+}
+
+/// Outcome of a graceful cleanup pass, suitable for the shutdown log and
+/// exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CleanupReport {
+    /// Windows niri confirmed gone within the deadline.
+    pub confirmed_closed: u32,
+    /// Windows still present when their deadline expired.
+    pub abandoned: u32,
+    /// Windows that were already gone before we asked.
+    pub already_gone: u32,
+}
+
+/// Live status snapshot published on [`NiriSpacer::status`].
+///
+/// Lets the control socket's `status`, a TUI, or any other consumer read
+/// current state cheaply without logging or an IPC round trip to niri.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SpacerStatus {
+    /// Number of currently active spacers.
+    pub spacer_count: usize,
+    /// When a corrective or state-changing action last ran.
+    pub last_fix: Option<std::time::SystemTime>,
+    /// How many times the niri clients have been rebuilt.
+    pub reconnect_count: u32,
+    /// The most recent repositioning audit entries, newest last.
+    pub recent_repositions: Vec<RepositionEvent>,
+}
+
+/// On-disk snapshot of a run, written by [`NiriSpacer::export_state`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ExportedState {
+    pub active_spacers: Vec<SpacerWindow>,
+    pub background_color: Color,
+    pub app_id_prefix: String,
+    pub session_info: ExportedSessionInfo,
+}
+
+/// Session metadata recorded for reference only; none of it survives a
+/// compositor restart, so restoring ignores it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ExportedSessionInfo {
+    pub socket_path: PathBuf,
+}
+
+/// Fluent construction of a [`NiriSpacer`], for library consumers that want
+/// more than the CLI's defaults without hand-assembling a config.
+///
+/// ```no_run
+/// # async fn demo() -> niri_spacer::Result<()> {
+/// use niri_spacer::spacer::{NiriSpacerBuilder, OccupiedPolicy};
+///
+/// let mut spacer = NiriSpacerBuilder::from_env()?
+///     .count(4)
+///     .on_occupied(OccupiedPolicy::Skip)
+///     .best_effort(true)
+///     .build()
+///     .await?;
+/// spacer.run().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct NiriSpacerBuilder {
+    config: NiriSpacerConfig,
+}
+
+impl NiriSpacerBuilder {
+    /// Starts from an explicit socket path.
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            config: NiriSpacerConfig::new(socket_path),
+        }
+    }
+
+    /// Starts from `$NIRI_SOCKET`.
+    pub fn from_env() -> Result<Self> {
+        let validator = SessionValidator::from_env()?;
+        Ok(Self::new(validator.socket_path()))
+    }
+
+    /// Window appearance and app ID settings.
+    pub fn native_config(mut self, native: NativeConfig) -> Self {
+        self.config.native = native;
+        self
+    }
+
+    /// How many workspaces (lowest indices first) to cover.
+    pub fn count(mut self, count: u8) -> Self {
+        self.config.count = Some(count);
+        self
+    }
+
+    /// Policy for non-empty target workspaces.
+    pub fn on_occupied(mut self, policy: OccupiedPolicy) -> Self {
+        self.config.on_occupied = policy;
+        self
+    }
+
+    /// Continue past individual placement failures.
+    pub fn best_effort(mut self, best_effort: bool) -> Self {
+        self.config.best_effort = best_effort;
+        self
+    }
+
+    /// Settle delays between compositor operations.
+    pub fn timings(mut self, timings: Timings) -> Self {
+        self.config.timings = timings;
+        self
+    }
+
+    /// Per-window budget for move-and-verify.
+    pub fn placement_timeout(mut self, timeout: Duration) -> Self {
+        self.config.placement_timeout = timeout;
+        self
+    }
+
+    /// Column width to reserve per spacer.
+    pub fn column_width(mut self, width: ColumnWidth) -> Self {
+        self.config.column_width = Some(width);
+        self
+    }
+
+    /// Where to keep the window mapping file; `None` disables it.
+    pub fn mapping_file(mut self, path: Option<PathBuf>) -> Self {
+        self.config.mapping_file = path;
+        self
+    }
+
+    /// The assembled configuration (what `build` will use).
+    pub fn config(&self) -> &NiriSpacerConfig {
+        &self.config
+    }
+
+    /// Connects the native Wayland backend and builds the orchestrator.
+    pub async fn build(self) -> Result<NiriSpacer<NativeWindowManager>> {
+        NiriSpacer::connect(self.config).await
+    }
+
+    /// Builds over an explicit backend (tests, headless embedders).
+    pub fn build_with_backend<B: SpacerBackend>(self, backend: B) -> Result<NiriSpacer<B>> {
+        NiriSpacer::with_backend(self.config, backend)
+    }
+}
+
+/// Plans and creates spacer windows through a [`SpacerBackend`].
+pub struct NiriSpacer<B: SpacerBackend> {
+    config: NiriSpacerConfig,
+    client: NiriClient,
+    workspaces: WorkspaceManager,
+    windows: WindowManager,
+    backend: B,
+    active_spacers: Vec<SpacerWindow>,
+    spacer_ids: SpacerIdSet,
+    next_number: u32,
+    reconnect_count: u32,
+    status_tx: watch::Sender<SpacerStatus>,
+    audit_log: VecDeque<RepositionEvent>,
+    clock: std::sync::Arc<dyn Clock>,
+    cancel: tokio_util::sync::CancellationToken,
+    /// Monitors owned by this orchestrator, stopped first during cleanup.
+    monitor: Option<crate::focus::FocusMonitor>,
+    events: tokio::sync::broadcast::Sender<SpacerEvent>,
+    /// When the last config-reload reconciliation ran (rate limiting).
+    last_config_reconcile: Option<tokio::time::Instant>,
+    /// Workspaces that are supposed to hold a spacer; shared with the
+    /// focus monitor's missed-close detection.
+    managed_workspaces: SpacerIdSet,
+    /// Respawn requests from the focus monitor, consumed by watch mode.
+    respawn_rx: Option<tokio::sync::mpsc::Receiver<u64>>,
+}
+
+impl NiriSpacer<NativeWindowManager> {
+    /// Connects the native Wayland backend and the niri client.
+    pub async fn connect(config: NiriSpacerConfig) -> Result<Self> {
+        let backend = NativeWindowManager::connect(config.native.clone()).await?;
+        Self::with_backend(config, backend)
+    }
+}
+
+impl<B: SpacerBackend> NiriSpacer<B> {
+    /// Builds an orchestrator without validating the environment or
+    /// touching any socket, for pure unit tests (ours and embedders').
+    ///
+    /// [`Self::with_backend`] is already connection-free -- the IPC client
+    /// only dials on its first request -- so this is a deliberate alias
+    /// that keeps test call sites honest about what they construct.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn new_for_testing(config: NiriSpacerConfig, backend: B) -> Result<Self> {
+        Self::with_backend(config, backend)
+    }
+
+    /// Builds an orchestrator over an already constructed backend. Used
+    /// directly by tests with a mock backend. Connection-free: the niri
+    /// client dials lazily on the first request and no environment
+    /// validation happens here.
+    pub fn with_backend(config: NiriSpacerConfig, backend: B) -> Result<Self> {
+        let client = NiriClient::new(&config.socket_path);
+        Ok(Self {
+            workspaces: WorkspaceManager::new(client.clone()).with_timings(config.timings),
+            windows: WindowManager::new(client.clone())
+                .with_spacer_prefix(config.native.app_id.clone()),
+            client,
+            config,
+            backend,
+            active_spacers: Vec::new(),
+            spacer_ids: SpacerIdSet::default(),
+            next_number: 1,
+            reconnect_count: 0,
+            status_tx: watch::channel(SpacerStatus::default()).0,
+            audit_log: VecDeque::new(),
+            clock: std::sync::Arc::new(TokioClock),
+            cancel: tokio_util::sync::CancellationToken::new(),
+            monitor: None,
+            events: tokio::sync::broadcast::channel(64).0,
+            last_config_reconcile: None,
+            managed_workspaces: SpacerIdSet::default(),
+            respawn_rx: None,
+        })
+    }
+
+    /// The spacers created so far in this run.
+    pub fn active_spacers(&self) -> &[SpacerWindow] {
+        &self.active_spacers
+    }
+
+    /// Overrides the time source; tests inject [`crate::clock::TestClock`]
+    /// so timeout paths run in virtual time. The workspace manager follows
+    /// the same clock.
+    pub fn with_clock(mut self, clock: std::sync::Arc<dyn Clock>) -> Self {
+        let workspaces = std::mem::replace(
+            &mut self.workspaces,
+            WorkspaceManager::new(self.client.clone()),
+        );
+        self.workspaces = workspaces.with_clock(std::sync::Arc::clone(&clock));
+        self.clock = clock;
+        self
+    }
+
+    /// Sets the service name reported on exported spans.
+    #[cfg(feature = "opentelemetry")]
+    pub fn with_otel_service_name(mut self, name: &str) -> Self {
+        self.config.otel_service_name = name.to_string();
+        self
+    }
+
+    /// Subscribes to spacer lifecycle events; see [`SpacerEvent`].
+    pub fn subscribe_to_spacer_events(&self) -> tokio::sync::broadcast::Receiver<SpacerEvent> {
+        self.events.subscribe()
+    }
+
+    /// The event sender, for wiring emitters that live outside the
+    /// orchestrator (e.g. the focus monitor).
+    pub fn spacer_event_sender(&self) -> tokio::sync::broadcast::Sender<SpacerEvent> {
+        self.events.clone()
+    }
+
+    fn emit(&self, event: SpacerEvent) {
+        // Ignore "no subscribers"; events are strictly optional.
+        let _ = self.events.send(event);
+    }
+
+    /// Hands the focus monitor to the orchestrator so cleanup can stop it
+    /// before any window is closed.
+    pub fn attach_monitor(&mut self, monitor: crate::focus::FocusMonitor) {
+        self.monitor = Some(monitor);
+    }
+
+    /// Token cancelling in-flight batch work; hand a clone to the signal
+    /// handler so SIGTERM can interrupt creation cleanly between windows.
+    pub fn cancellation_token(&self) -> tokio_util::sync::CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// The configured Wayland app ID for this orchestrator's spacers.
+    pub fn config_native_app_id(&self) -> String {
+        self.config.native.app_id.clone()
+    }
+
+    /// The niri client this orchestrator uses.
+    pub fn client(&self) -> &NiriClient {
+        &self.client
+    }
+
+    /// Shared set of niri window IDs that are spacers, kept current as
+    /// spacers come and go; hand this to [`crate::focus::FocusMonitor`].
+    pub fn spacer_ids(&self) -> SpacerIdSet {
+        std::sync::Arc::clone(&self.spacer_ids)
+    }
+
+    /// Shared set of workspace IDs that should hold a spacer, for the focus
+    /// monitor's missed-close detection.
+    pub fn managed_workspace_set(&self) -> SpacerIdSet {
+        std::sync::Arc::clone(&self.managed_workspaces)
+    }
+
+    /// Creates (once) the channel on which the focus monitor schedules
+    /// respawns; watch mode drains the receiving end.
+    pub fn respawn_request_sender(&mut self) -> tokio::sync::mpsc::Sender<u64> {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        self.respawn_rx = Some(rx);
+        tx
+    }
+
+    /// Channel carrying the latest [`SpacerStatus`]; updated whenever the
+    /// active spacer set changes or a maintenance cycle runs.
+    pub fn status(&self) -> watch::Receiver<SpacerStatus> {
+        self.status_tx.subscribe()
+    }
+
+    /// Publishes the current status, stamping `last_fix` with now.
+    fn publish_status(&self) {
+        crate::metrics::active_spacers(self.active_spacers.len());
+        // send_replace rather than send: the snapshot must be stored even
+        // before the first consumer subscribes.
+        self.status_tx.send_replace(SpacerStatus {
+            spacer_count: self.active_spacers.len(),
+            last_fix: Some(std::time::SystemTime::now()),
+            reconnect_count: self.reconnect_count,
+            recent_repositions: self
+                .audit_log
+                .iter()
+                .rev()
+                .take(STATUS_AUDIT_ENTRIES)
+                .rev()
+                .cloned()
+                .collect(),
+        });
+    }
+
+    /// The current spacer mapping as written to the mapping file.
+    pub fn window_mapping(&self) -> std::collections::BTreeMap<u32, MappingEntry> {
+        self.active_spacers
+            .iter()
+            .map(|s| {
+                (
+                    s.number,
+                    MappingEntry {
+                        niri_id: s.niri_window_id,
+                        workspace_idx: s.workspace_idx,
+                        app_id: self.config.native.app_id.clone(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Rewrites the mapping file atomically (write-to-temp then rename), so
+    /// readers never observe a half-written JSON document.
+    fn write_mapping_file(&self) {
+        let Some(path) = &self.config.mapping_file else {
+            return;
+        };
+        let result = (|| -> Result<()> {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let tmp = path.with_extension("json.tmp");
+            let file = std::fs::File::create(&tmp)?;
+            serde_json::to_writer_pretty(file, &self.window_mapping())?;
+            std::fs::rename(&tmp, path)?;
+            Ok(())
+        })();
+        if let Err(e) = result {
+            warn!(path = %path.display(), error = %e, "could not write mapping file");
+        }
+    }
+
+    /// The full repositioning audit log, oldest entry first.
+    pub fn audit_log(&self) -> impl Iterator<Item = &RepositionEvent> {
+        self.audit_log.iter()
+    }
+
+    /// Appends one entry to the bounded audit log. Every corrective path
+    /// that moves a spacer goes through here.
+    pub(crate) fn record_reposition(
+        &mut self,
+        spacer_id: u64,
+        trigger: RepositionTrigger,
+        from: Option<u64>,
+        to: u64,
+        outcome: RepositionOutcome,
+    ) {
+        if self.audit_log.len() == AUDIT_LOG_CAP {
+            self.audit_log.pop_front();
+        }
+        self.audit_log.push_back(RepositionEvent {
+            spacer_id,
+            trigger,
+            from,
+            to,
+            timestamp: std::time::SystemTime::now(),
+            outcome,
+        });
+    }
+
+    /// The workspace manager sharing this orchestrator's niri connection.
+    ///
+    /// Borrows `self` mutably so manager operations cannot interleave with a
+    /// placement run on the same orchestrator; library consumers combining
+    /// spacer management with their own workspace queries should use this
+    /// rather than opening a duplicate IPC client.
+    ///
+    /// ```no_run
+    /// # async fn demo() -> niri_spacer::Result<()> {
+    /// use niri_spacer::spacer::{NiriSpacer, NiriSpacerConfig};
+    ///
+    /// let config = NiriSpacerConfig::new(std::env::var("NIRI_SOCKET").unwrap());
+    /// let mut spacer = NiriSpacer::connect(config).await?;
+    /// spacer.run().await?;
+    ///
+    /// // Reuse the same connection for an ad-hoc query.
+    /// let focused = spacer.workspace_manager().get_focused_workspace().await?;
+    /// println!("focused: {focused:?}, spacers: {}", spacer.active_spacers().len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn workspace_manager(&mut self) -> &mut WorkspaceManager {
+        &mut self.workspaces
+    }
+
+    /// The window manager sharing this orchestrator's niri connection; see
+    /// [`Self::workspace_manager`] for the borrowing rules.
+    pub fn window_manager(&mut self) -> &mut WindowManager {
+        &mut self.windows
+    }
+
+    /// Convenience passthrough for [`WorkspaceManager::get_focused_workspace`].
+    pub async fn focused_workspace(&self) -> Result<Option<crate::niri::Workspace>> {
+        self.workspaces.get_focused_workspace().await
+    }
+
+    /// Convenience passthrough for [`WindowManager::get_windows`].
+    pub async fn windows(&self) -> Result<Vec<crate::niri::Window>> {
+        self.windows.get_windows().await
+    }
+
+    /// Computes the placement plan without touching the compositor state.
+    pub async fn compute_plan(&self) -> Result<Vec<Placement>> {
+        compute_plan(&self.config).await
+    }
+
+    /// Computes the plan and carries it out. Without
+    /// [`NiriSpacerConfig::best_effort`], the first failure aborts the
+    /// batch; with it, failures are collected in the report and the
+    /// remaining workspaces still get their spacers.
+    pub async fn run(&mut self) -> Result<RunReport> {
+        if self.config.transient {
+            let cleaned = self.clean_stale_spacers().await?;
+            if cleaned > 0 {
+                info!(cleaned, "removed leftover spacers from a previous session");
+            }
+        }
+        let plan = self.compute_plan().await?;
+        let mut report = self.execute_plan(&plan).await?;
+        report.validation_dropped = self.validate_spacers().await?;
+        Ok(report)
+    }
+
+    /// Verifies every active spacer is still reported by niri. Under the
+    /// lenient policy, missing ones are logged and dropped (returning how
+    /// many); under strict, the first missing spacer fails the call and
+    /// the remaining set is left untouched.
+    pub async fn validate_spacers(&mut self) -> Result<u32> {
+        // One verification pass means one window fetch: both the liveness
+        // set and the misplacement scan read the same snapshot.
+        let windows = self.windows.get_windows().await?;
+        let live: std::collections::HashSet<u64> = windows.iter().map(|w| w.id).collect();
+        // Old spacers sitting on the wrong workspace probably never settled
+        // into position; call those out before handling the missing ones.
+        for spacer in &self.active_spacers {
+            let misplaced = windows
+                .iter()
+                .find(|w| w.id == spacer.niri_window_id)
+                .is_some_and(|w| w.workspace_id != Some(spacer.workspace_id));
+            if misplaced {
+                self.emit(SpacerEvent::SpacerMisplaced(spacer.niri_window_id));
+            }
+            if misplaced && spacer.age() > Duration::from_secs(24 * 3600) {
+                warn!(
+                    number = spacer.number,
+                    age = %crate::window::format_age(spacer.age()),
+                    "old spacer is misplaced; it may never have settled"
+                );
+            }
+        }
+
+        let missing: Vec<SpacerWindow> = self
+            .active_spacers
+            .iter()
+            .filter(|s| !live.contains(&s.niri_window_id))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return Ok(0);
+        }
+
+        if self.config.validation == ValidationPolicy::Strict {
+            return Err(NiriSpacerError::WindowNotFound(missing[0].niri_window_id));
+        }
+        for spacer in &missing {
+            warn!(
+                number = spacer.number,
+                window = spacer.niri_window_id,
+                "spacer vanished before verification; dropping it"
+            );
+        }
+        let gone: std::collections::HashSet<u64> =
+            missing.iter().map(|s| s.niri_window_id).collect();
+        self.active_spacers.retain(|s| !gone.contains(&s.niri_window_id));
+        {
+            let mut ids = self.spacer_ids.write().expect("spacer id set poisoned");
+            for id in &gone {
+                ids.remove(id);
+            }
+        }
+        self.publish_status();
+        self.write_mapping_file();
+        Ok(missing.len() as u32)
+    }
+
+    /// How many more spacers may be created before hitting
+    /// [`MAX_WINDOW_COUNT`].
+    pub fn get_remaining_capacity(&self) -> u32 {
+        MAX_WINDOW_COUNT.saturating_sub(self.active_spacers.len() as u32)
+    }
+
+    /// Fails with [`NiriSpacerError::MaxSpacerCountReached`] if `additional`
+    /// new spacers would exceed the limit.
+    fn check_capacity(&self, additional: u32) -> Result<()> {
+        if additional > self.get_remaining_capacity() {
+            return Err(NiriSpacerError::MaxSpacerCountReached {
+                current: self.active_spacers.len() as u32,
+                maximum: MAX_WINDOW_COUNT,
+            });
+        }
+        Ok(())
+    }
+
+    /// Recreates the spacers recorded in an export, up to the remaining
+    /// capacity (anything beyond it is dropped with a warning) and skipping
+    /// workspaces that no longer exist.
+    pub async fn restore(&mut self, exported: &ExportedState) -> Result<RunReport> {
+        let mut entries: &[SpacerWindow] = &exported.active_spacers;
+        let capacity = self.get_remaining_capacity() as usize;
+        if entries.len() > capacity {
+            warn!(
+                exported = entries.len(),
+                capacity, "export exceeds remaining capacity; restoring a subset"
+            );
+            entries = &entries[..capacity];
+        }
+
+        let workspaces = self.workspaces.workspaces_sorted().await?;
+        let mut plan = Vec::new();
+        for entry in entries {
+            match workspaces.iter().find(|ws| ws.idx == entry.workspace_idx) {
+                Some(ws) => plan.push(Placement {
+                    workspace_id: ws.id,
+                    workspace_idx: ws.idx,
+                    color: entry.color,
+                }),
+                None => warn!(
+                    idx = entry.workspace_idx,
+                    "exported workspace no longer exists; skipping"
+                ),
+            }
+        }
+        self.execute_plan(&plan).await
+    }
+
+    /// Prints the plan and asks for confirmation on `input` before running.
+    ///
+    /// Returns whether anything was created. When stdin is not a TTY the
+    /// answer is taken to be "no" so scripted invocations never hang on the
+    /// prompt.
+    pub async fn run_with_confirmation<R: BufRead>(
+        &mut self,
+        input: R,
+        stdin_is_tty: bool,
+    ) -> Result<bool> {
+        let plan = self.compute_plan().await?;
+        println!("Placement plan:");
+        for placement in &plan {
+            println!("  {placement}");
+        }
+
+        if !confirm(input, stdin_is_tty) {
+            info!("aborted by user; no windows created");
+            return Ok(false);
+        }
+
+        self.execute_plan(&plan).await?;
+        Ok(true)
+    }
+
+    async fn execute_plan(&mut self, plan: &[Placement]) -> Result<RunReport> {
+        // Every creation path funnels through here, so this is where the
+        // library-level spacer limit is enforced — the interactive and
+        // restore flows must not bypass it.
+        self.check_capacity(plan.len() as u32)?;
+        let mut report = RunReport::default();
+        let mut reconnected = false;
+        for placement in plan {
+            if self.cancel.is_cancelled() {
+                // Stop between windows and take the partial batch down with
+                // us so shutdown during startup leaves nothing behind.
+                warn!(placed = report.placed, "batch cancelled; cleaning up partial windows");
+                self.remove_spacers().await?;
+                return Err(NiriSpacerError::Cancelled);
+            }
+            let mut attempt = self
+                .create_spacer(placement, RepositionTrigger::InitialPlacement)
+                .await;
+
+            // A connection loss mid-batch usually means niri restarted; one
+            // reconnect attempt distinguishes that from a genuine error.
+            if let Err(NiriSpacerError::ConnectionLost(reason)) = &attempt {
+                if !reconnected {
+                    warn!(%reason, "connection lost mid-batch; reconnecting once");
+                    reconnected = true;
+                    self.reconnect_after_restart().await?;
+                    attempt = self
+                        .create_spacer(placement, RepositionTrigger::InitialPlacement)
+                        .await;
+                }
+            }
+
+            let spacer = match attempt {
+                Ok(spacer) => spacer,
+                Err(NiriSpacerError::CorrelationTimeout(title))
+                    if self.config.on_correlation_fail == CorrelationFailPolicy::Skip =>
+                {
+                    warn!(
+                        workspace = placement.workspace_idx,
+                        %title,
+                        "correlation timed out; skipping this workspace"
+                    );
+                    report.skipped.push(PlacementFailure {
+                        workspace_idx: placement.workspace_idx,
+                        error: format!("correlation timed out for {title:?}"),
+                    });
+                    continue;
+                }
+                Err(e) if self.config.best_effort => {
+                    warn!(
+                        workspace = placement.workspace_idx,
+                        error = %e,
+                        "spacer creation failed; continuing (best effort)"
+                    );
+                    report.failures.push(PlacementFailure {
+                        workspace_idx: placement.workspace_idx,
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            info!("placed {spacer}");
+            self.spacer_ids
+                .write()
+                .expect("spacer id set poisoned")
+                .insert(spacer.niri_window_id);
+            self.managed_workspaces
+                .write()
+                .expect("managed workspace set poisoned")
+                .insert(spacer.workspace_id);
+            self.emit(SpacerEvent::SpacerCreated(spacer.clone()));
+            self.active_spacers.push(spacer);
+            report.placed += 1;
+        }
+        self.publish_status();
+        self.write_mapping_file();
+        Ok(report)
+    }
+
+    /// Creates one spacer window, waits for niri to report it, and moves it
+    /// to its target workspace.
+    async fn create_spacer(
+        &mut self,
+        placement: &Placement,
+        trigger: RepositionTrigger,
+    ) -> Result<SpacerWindow> {
+        let number = self.next_number;
+        self.next_number += 1;
+        self.create_numbered_spacer(number, placement, trigger).await
+    }
+
+    /// Creates a spacer with an explicit number (respawns reuse the closed
+    /// spacer's number so the number/workspace/color/title mapping users
+    /// rely on stays stable).
+    async fn create_numbered_spacer(
+        &mut self,
+        number: u32,
+        placement: &Placement,
+        trigger: RepositionTrigger,
+    ) -> Result<SpacerWindow> {
+
+        let handle = self.backend.create_window(number, placement.color).await?;
+        let correlation_started = std::time::Instant::now();
+        let niri_window_id = match self.correlate(&handle.title).await {
+            Ok(id) => id,
+            Err(e) => {
+                // Never leave the orphaned native window mapped; close it
+                // before reporting however the policy decides.
+                if let Err(close_err) = self.backend.close_window(&handle).await {
+                    warn!(number, error = %close_err, "could not close orphaned window");
+                }
+                return Err(e);
+            }
+        };
+        crate::metrics::correlation_duration(
+            correlation_started.elapsed().as_secs_f64() * 1000.0,
+        );
+        self.backend.note_correlated(number, niri_window_id);
+        debug!(number, niri_window_id, "correlated spacer with niri window");
+        if self.config.embed_id_in_title {
+            let title = format!("{} [{niri_window_id}]", handle.title);
+            self.backend.set_window_title(number, title).await?;
+        }
+
+        let placed = PlacementTransaction::new(&self.client, &self.windows)
+            .with_timeout(self.config.placement_timeout)
+            .with_clock(std::sync::Arc::clone(&self.clock))
+            .place(niri_window_id, placement)
+            .await;
+        let outcome = match &placed {
+            Ok(()) => RepositionOutcome::Success,
+            Err(e) => RepositionOutcome::Failed(e.to_string()),
+        };
+        self.record_reposition(niri_window_id, trigger, None, placement.workspace_id, outcome);
+        placed?;
+        self.backend.note_placed(number, placement.workspace_id);
+        self.resize_spacer_to_width(niri_window_id).await?;
+
+        crate::metrics::window_created();
+        Ok(SpacerWindow {
+            number,
+            created_at: std::time::Instant::now(),
+            niri_window_id,
+            workspace_id: placement.workspace_id,
+            workspace_idx: placement.workspace_idx,
+            color: placement.color,
+            title: handle.title,
+        })
+    }
+
+    /// Pins the spacer's niri column to the configured width: an explicit
+    /// `--column-width` if given, else the fixed native window width or a
+    /// small proportion per the width mode.
+    async fn resize_spacer_to_width(&self, niri_window_id: u64) -> Result<()> {
+        let change = self
+            .config
+            .column_width
+            .map(ColumnWidth::to_size_change)
+            .unwrap_or(match self.config.width_mode {
+                SpacerWidthMode::FixedPx => SizeChange::SetFixed(self.config.native.width as i32),
+                SpacerWidthMode::Proportion => SizeChange::SetProportion(MINIMAL_PROPORTION),
+            });
+        self.client
+            .action(Action::SetWindowWidth {
+                id: Some(niri_window_id),
+                change,
+            })
+            .await
+    }
+
+    /// Polls niri until a window with the given title appears.
+    async fn correlate(&self, title: &str) -> Result<u64> {
+        let deadline = self.clock.now() + CORRELATION_TIMEOUT;
+        loop {
+            if let Some(window) = self.windows.find_by_title(title).await? {
+                return Ok(window.id);
+            }
+            if self.clock.now() >= deadline {
+                return Err(NiriSpacerError::CorrelationTimeout(title.to_string()));
+            }
+            self.clock.sleep(CORRELATION_POLL).await;
+        }
+    }
+
+    /// Follows niri's overview open/close events, repainting spacers per
+    /// `style` while the overview is open and restoring them afterwards, so
+    /// nine gray rectangles don't dominate the overview. Runs until the
+    /// event stream ends; a no-op for [`OverviewStyle::Normal`].
+    pub async fn watch_overview(&mut self, style: OverviewStyle) -> Result<()> {
+        if style == OverviewStyle::Normal {
+            return Ok(());
+        }
+        let mut events = self.client.subscribe_to_events().await?;
+        let mut overview_open = false;
+
+        while let Some(event) = events.next_event().await? {
+            let crate::niri::NiriEvent::OverviewOpenedOrClosed { is_open } = event else {
+                continue;
+            };
+            if is_open == overview_open {
+                continue; // duplicate notification; nothing to repaint
+            }
+            overview_open = is_open;
+
+            let opacity = match (is_open, style) {
+                (false, _) => 1.0,
+                (true, OverviewStyle::Hide) => 0.0,
+                (true, OverviewStyle::Dim) => OVERVIEW_DIM_OPACITY,
+                (true, OverviewStyle::Normal) => unreachable!("handled above"),
+            };
+            for spacer in self.active_spacers.clone() {
+                if let Err(e) = self
+                    .backend
+                    .redraw_window(spacer.number, spacer.color, opacity)
+                    .await
+                {
+                    warn!(number = spacer.number, error = %e, "overview repaint failed");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Maintains exactly `desired_count` spacers until the event stream
+    /// ends: a closed spacer is respawned on the first vacant workspace,
+    /// and a periodic reconciliation pass (every 30 seconds, on the
+    /// orchestrator's clock) refills or trims the set as a safety net
+    /// against missed events.
+    pub async fn watch_mode(&mut self, desired_count: u32) -> Result<()> {
+        let mut events = self.client.subscribe_to_events().await?;
+        let mut respawn_rx = self.respawn_rx.take();
+        let result = loop {
+            let sleep = self.clock.sleep(RECONCILE_INTERVAL);
+            let respawn_request = async {
+                match &mut respawn_rx {
+                    Some(rx) => rx.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                event = events.next_event() => {
+                    let Some(event) = (match event {
+                        Ok(event) => event,
+                        Err(e) => break Err(e),
+                    }) else {
+                        info!("event stream ended; leaving watch mode");
+                        break Ok(());
+                    };
+                    if let Err(e) = self.handle_watch_event(event).await {
+                        break Err(e);
+                    }
+                }
+                Some(workspace_id) = respawn_request => {
+                    // Scheduled by the focus monitor when focus revealed a
+                    // managed workspace without a live spacer.
+                    if let Err(e) = self.respawn_on_workspace(workspace_id).await {
+                        warn!(workspace = workspace_id, error = %e, "scheduled respawn failed");
+                    }
+                }
+                _ = sleep => {
+                    if let Err(e) = self.reconcile(desired_count).await {
+                        break Err(e);
+                    }
+                }
+            }
+        };
+        self.respawn_rx = respawn_rx;
+        result
+    }
+
+    /// Respawns the spacer a managed workspace should hold: in place when a
+    /// (stale) entry for that workspace exists, otherwise as a fresh spacer
+    /// on it.
+    async fn respawn_on_workspace(&mut self, workspace_id: u64) -> Result<()> {
+        if let Some(original) = self
+            .active_spacers
+            .iter()
+            .find(|s| s.workspace_id == workspace_id)
+            .cloned()
+        {
+            self.respawn_spacer(&original).await?;
+        } else {
+            let Some(target) = self
+                .workspaces
+                .workspaces_sorted()
+                .await?
+                .into_iter()
+                .find(|ws| ws.id == workspace_id)
+            else {
+                warn!(workspace = workspace_id, "respawn target workspace no longer exists");
+                return Ok(());
+            };
+            let placement = Placement {
+                workspace_id: target.id,
+                workspace_idx: target.idx,
+                color: DEFAULT_PALETTE[self.active_spacers.len() % DEFAULT_PALETTE.len()],
+            };
+            let spacer = self.create_spacer(&placement, RepositionTrigger::Respawn).await?;
+            self.spacer_ids
+                .write()
+                .expect("spacer id set poisoned")
+                .insert(spacer.niri_window_id);
+            self.active_spacers.push(spacer.clone());
+            self.emit(SpacerEvent::SpacerCreated(spacer));
+        }
+        self.publish_status();
+        self.write_mapping_file();
+        Ok(())
+    }
+
+    async fn handle_watch_event(&mut self, event: crate::niri::NiriEvent) -> Result<()> {
+        if let crate::niri::NiriEvent::ConfigLoaded { failed } = event {
+            if !failed {
+                self.reconcile_after_config_reload().await?;
+            }
+            return Ok(());
+        }
+        let crate::niri::NiriEvent::WindowClosed { id } = event else {
+            return Ok(());
+        };
+        let closed: Vec<SpacerWindow> = self
+            .active_spacers
+            .iter()
+            .filter(|s| s.niri_window_id == id)
+            .cloned()
+            .collect();
+        if closed.is_empty() {
+            return Ok(());
+        }
+
+        for spacer in closed {
+            warn!(
+                number = spacer.number,
+                workspace = spacer.workspace_idx,
+                "spacer was closed externally; respawning in place"
+            );
+            if let Err(e) = self.respawn_spacer(&spacer).await {
+                warn!(number = spacer.number, error = %e, "respawn failed");
+                // Drop the dead entry; reconciliation will refill later.
+                self.active_spacers.retain(|s| s.niri_window_id != id);
+                self.spacer_ids
+                    .write()
+                    .expect("spacer id set poisoned")
+                    .remove(&id);
+            }
+        }
+        self.publish_status();
+        self.write_mapping_file();
+        Ok(())
+    }
+
+    /// Recreates a closed spacer with its original number, workspace,
+    /// color, and title, swapping the fresh niri window ID into
+    /// `active_spacers` and the focus-monitor set in one step so no stale
+    /// ID survives anywhere.
+    async fn respawn_spacer(&mut self, original: &SpacerWindow) -> Result<SpacerWindow> {
+        let placement = Placement {
+            workspace_id: original.workspace_id,
+            workspace_idx: original.workspace_idx,
+            color: original.color,
+        };
+        let respawned = self
+            .create_numbered_spacer(original.number, &placement, RepositionTrigger::Respawn)
+            .await?;
+
+        {
+            let mut ids = self.spacer_ids.write().expect("spacer id set poisoned");
+            ids.remove(&original.niri_window_id);
+            ids.insert(respawned.niri_window_id);
+        }
+        match self
+            .active_spacers
+            .iter_mut()
+            .find(|s| s.number == original.number)
+        {
+            Some(entry) => *entry = respawned.clone(),
+            None => self.active_spacers.push(respawned.clone()),
+        }
+        self.emit(SpacerEvent::SpacerCreated(respawned.clone()));
+        Ok(respawned)
+    }
+
+    /// Creates one replacement spacer on the lowest-index workspace that has
+    /// none of ours.
+    async fn respawn_on_vacant_workspace(&mut self) -> Result<()> {
+        if self.create_on_vacant_workspace(RepositionTrigger::Respawn).await?.is_none() {
+            warn!("no vacant workspace for a respawned spacer");
+        }
+        Ok(())
+    }
+
+    /// Adds one more spacer to the active set, on the lowest-index
+    /// workspace that does not have one yet.
+    pub async fn add_spacer(&mut self) -> Result<SpacerWindow> {
+        self.check_capacity(1)?;
+        let spacer = self
+            .create_on_vacant_workspace(RepositionTrigger::InitialPlacement)
+            .await?
+            .ok_or_else(|| {
+                NiriSpacerError::Ipc("every workspace already has a spacer".to_string())
+            })?;
+        self.publish_status();
+        self.write_mapping_file();
+        Ok(spacer)
+    }
+
+    /// Places a spacer on the first workspace without one; `None` when all
+    /// workspaces are covered.
+    async fn create_on_vacant_workspace(
+        &mut self,
+        trigger: RepositionTrigger,
+    ) -> Result<Option<SpacerWindow>> {
+        let workspaces = scope_to_output(
+            self.workspaces.workspaces_sorted().await?,
+            self.config.all_outputs,
+        );
+        let occupied: std::collections::HashSet<u64> =
+            self.active_spacers.iter().map(|s| s.workspace_id).collect();
+        let vacant: Vec<crate::niri::Workspace> = workspaces
+            .into_iter()
+            .filter(|ws| !occupied.contains(&ws.id))
+            .collect();
+        let target = if self.config.smart_placement {
+            let windows = self.windows.get_windows().await?;
+            let focused_idx = self
+                .focused_workspace()
+                .await?
+                .map(|ws| ws.idx)
+                .unwrap_or(1);
+            vacant
+                .into_iter()
+                .map(|ws| {
+                    let density = windows
+                        .iter()
+                        .filter(|w| w.workspace_id == Some(ws.id))
+                        .count();
+                    let score =
+                        crate::workspace::score_workspace(ws.idx, focused_idx, density, true);
+                    (score, ws)
+                })
+                .max_by_key(|(score, ws)| (*score, std::cmp::Reverse(ws.idx)))
+                .map(|(_, ws)| ws)
+        } else {
+            vacant.into_iter().next()
+        };
+        let Some(target) = target else {
+            return Ok(None);
+        };
+
+        let placement = Placement {
+            workspace_id: target.id,
+            workspace_idx: target.idx,
+            color: DEFAULT_PALETTE[self.active_spacers.len() % DEFAULT_PALETTE.len()],
+        };
+        let spacer = self.create_spacer(&placement, trigger).await?;
+        self.spacer_ids
+            .write()
+            .expect("spacer id set poisoned")
+            .insert(spacer.niri_window_id);
+        self.managed_workspaces
+            .write()
+            .expect("managed workspace set poisoned")
+            .insert(spacer.workspace_id);
+        self.active_spacers.push(spacer.clone());
+        self.emit(SpacerEvent::SpacerCreated(spacer.clone()));
+        Ok(Some(spacer))
+    }
+
+    /// One-shot pass after a niri config reload: gaps and default column
+    /// widths may have shifted, leaving spacers visibly wide or displaced,
+    /// so re-pin every spacer's width and re-place any that drifted.
+    /// Rate-limited so a burst of reload events costs one pass.
+    async fn reconcile_after_config_reload(&mut self) -> Result<()> {
+        if let Some(last) = self.last_config_reconcile {
+            if self.clock.now() - last < CONFIG_RECONCILE_COOLDOWN {
+                debug!("config reload reconciliation on cooldown");
+                return Ok(());
+            }
+        }
+        self.last_config_reconcile = Some(self.clock.now());
+        info!("niri config reloaded; re-verifying spacer geometry");
+
+        let windows = self.windows.get_windows().await?;
+        for spacer in self.active_spacers.clone() {
+            self.resize_spacer_to_width(spacer.niri_window_id).await?;
+            let misplaced = windows
+                .iter()
+                .find(|w| w.id == spacer.niri_window_id)
+                .is_some_and(|w| w.workspace_id != Some(spacer.workspace_id));
+            if misplaced {
+                self.fix_spacer_position(spacer.number).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Safety net: compares the live spacer set against `desired_count` and
+    /// refills or trims to match.
+    async fn reconcile(&mut self, desired_count: u32) -> Result<()> {
+        // Drop bookkeeping for spacers whose windows quietly disappeared.
+        let live: std::collections::HashSet<u64> = self
+            .windows
+            .get_windows()
+            .await?
+            .into_iter()
+            .map(|w| w.id)
+            .collect();
+        let before = self.active_spacers.len();
+        self.active_spacers.retain(|s| live.contains(&s.niri_window_id));
+        if self.active_spacers.len() != before {
+            let ids: std::collections::HashSet<u64> =
+                self.active_spacers.iter().map(|s| s.niri_window_id).collect();
+            *self.spacer_ids.write().expect("spacer id set poisoned") = ids;
+        }
+
+        while (self.active_spacers.len() as u32) < desired_count {
+            let before = self.active_spacers.len();
+            self.respawn_on_vacant_workspace().await?;
+            if self.active_spacers.len() == before {
+                break; // no vacant workspace; try again next interval
+            }
+        }
+        while (self.active_spacers.len() as u32) > desired_count {
+            let spacer = self.active_spacers.remove(self.active_spacers.len() - 1);
+            self.spacer_ids
+                .write()
+                .expect("spacer id set poisoned")
+                .remove(&spacer.niri_window_id);
+            self.managed_workspaces
+                .write()
+                .expect("managed workspace set poisoned")
+                .remove(&spacer.workspace_id);
+            // Same order as every other close path: ask niri first and wait
+            // for the confirmation, then release the backend window —
+            // closing Wayland-first races niri's unmap handling.
+            match self.close_and_confirm(spacer.niri_window_id).await {
+                Ok(true) => {}
+                Ok(false) => warn!(
+                    number = spacer.number,
+                    "niri did not confirm the trimmed spacer's close in time"
+                ),
+                Err(e) => warn!(number = spacer.number, error = %e, "trim close failed"),
+            }
+            let handle = crate::backend::BackendWindowHandle {
+                number: spacer.number,
+                title: spacer.title.clone(),
+            };
+            if let Err(e) = self.backend.close_window(&handle).await {
+                warn!(number = spacer.number, error = %e, "could not trim extra spacer");
+            }
+            self.emit(SpacerEvent::SpacerClosed(spacer.niri_window_id));
+        }
+        self.publish_status();
+        self.write_mapping_file();
+        Ok(())
+    }
+
+    /// Subscribes to the event stream and forwards every newly created
+    /// workspace on the returned channel.
+    ///
+    /// As a side effect the forwarding task also watches for
+    /// `WorkspaceDestroyed` hitting a workspace that holds one of our
+    /// spacers — niri does not normally delete workspaces out from under
+    /// windows, but if it happens we at least leave a trail in the log.
+    pub async fn listen_for_new_workspaces(
+        &self,
+    ) -> Result<tokio::sync::mpsc::Receiver<crate::niri::Workspace>> {
+        let mut events = self.client.subscribe_to_events().await?;
+        let spacer_workspaces: std::collections::HashSet<u64> = self
+            .active_spacers
+            .iter()
+            .map(|s| s.workspace_id)
+            .collect();
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+
+        tokio::spawn(async move {
+            while let Ok(Some(event)) = events.next_event().await {
+                let created = match event {
+                    crate::niri::NiriEvent::WorkspaceCreated { workspace } => Some(workspace),
+                    crate::niri::NiriEvent::WorkspaceDestroyed { id }
+                        if spacer_workspaces.contains(&id) =>
+                    {
+                        warn!(workspace = id, "workspace holding a spacer was destroyed");
+                        None
+                    }
+                    _ => None,
+                };
+                if let Some(workspace) = created {
+                    if tx.send(workspace).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(rx)
+    }
+
+    /// Reconnects after a suspected niri restart: prefer the socket
+    /// currently named by `$NIRI_SOCKET` (a restarted niri advertises a new
+    /// path), falling back to the configured path when the environment does
+    /// not name one.
+    async fn reconnect_after_restart(&mut self) -> Result<()> {
+        match self.reconnect().await {
+            Ok(()) => Ok(()),
+            Err(NiriSpacerError::MissingEnvVar(_)) => {
+                let path = self.config.socket_path.clone();
+                self.reconnect_to(&path).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Rebuilds every niri-facing client against the socket currently named
+    /// by `$NIRI_SOCKET`, preserving `active_spacers`.
+    ///
+    /// This is the single entry point for SIGHUP-style reconnects: the
+    /// workspace manager, window manager, and the orchestrator's own client
+    /// are all recreated together so no half-swapped state is observable.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let validator = SessionValidator::from_env()?;
+        validator.validate()?;
+        self.reconnect_to(validator.socket_path()).await
+    }
+
+    /// Like [`Self::reconnect`] but against an explicit socket path.
+    pub async fn reconnect_to(&mut self, socket_path: &Path) -> Result<()> {
+        let client = NiriClient::new(socket_path);
+        // Probe before swapping anything in so a dead socket leaves the
+        // old (possibly still working) clients untouched.
+        client.version().await?;
+
+        self.config.socket_path = socket_path.to_path_buf();
+        self.workspaces = WorkspaceManager::new(client.clone())
+            .with_timings(self.config.timings)
+            .with_clock(std::sync::Arc::clone(&self.clock));
+        self.windows = WindowManager::new(client.clone())
+            .with_spacer_prefix(self.config.native.app_id.clone());
+        self.client = client;
+        self.reconnect_count += 1;
+        self.publish_status();
+        info!(socket = %socket_path.display(), "reconnected to niri");
+        Ok(())
+    }
+
+    /// Repaints every active spacer in `color` through the backend's
+    /// redraw path -- no recreation -- and records the color as the new
+    /// base so future respawns and additions use it too.
+    pub async fn recolor_spacers(&mut self, color: Color) -> Result<()> {
+        self.config.native.color = color;
+        let numbers: Vec<u32> = self.active_spacers.iter().map(|s| s.number).collect();
+        for number in numbers {
+            self.recolor_spacer(number, color).await?;
+        }
+        Ok(())
+    }
+
+    /// Repaints a single spacer, updating its recorded color so a respawn
+    /// recreates it in the new color.
+    pub async fn recolor_spacer(&mut self, number: u32, color: Color) -> Result<()> {
+        let spacer = self
+            .active_spacers
+            .iter_mut()
+            .find(|s| s.number == number)
+            .ok_or_else(|| {
+                NiriSpacerError::Ipc(format!("no active spacer numbered {number}"))
+            })?;
+        self.backend.redraw_window(number, color, 1.0).await?;
+        spacer.color = color;
+        self.write_mapping_file();
+        Ok(())
+    }
+
+    /// Serializes the current spacer configuration to a JSON file so it can
+    /// be restored after a reboot without re-running the full creation
+    /// sequence.
+    ///
+    /// The file carries a `_comments` object explaining each field; unknown
+    /// keys are ignored on restore, so hand edits to it are harmless.
+    pub async fn export_state(&self, path: &Path) -> Result<()> {
+        let state = ExportedState {
+            active_spacers: self.active_spacers.clone(),
+            background_color: self.config.native.color,
+            app_id_prefix: self.config.native.app_id.clone(),
+            session_info: ExportedSessionInfo {
+                socket_path: self.config.socket_path.clone(),
+            },
+        };
+
+        let mut value = serde_json::to_value(&state)?;
+        if let Some(map) = value.as_object_mut() {
+            map.insert(
+                "_comments".to_string(),
+                serde_json::json!({
+                    "active_spacers": "spacers that were live at export time, in creation order",
+                    "background_color": "fallback background color for restored spacers",
+                    "app_id_prefix": "Wayland app ID the spacer windows are created with",
+                    "session_info": "metadata about the exporting session; not restorable \
+                                     (the socket path changes between niri sessions)",
+                }),
+            );
+        }
+
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, &value)?;
+        Ok(())
+    }
+
+    /// Reads a file written by [`Self::export_state`].
+    pub fn restore_from_export(path: &Path) -> Result<ExportedState> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Re-places one drifted spacer on its recorded workspace and restores
+    /// the user's focus afterwards.
+    ///
+    /// Restoration prefers niri's focus-previous-workspace action, which
+    /// returns the user wherever they actually were, over re-focusing the
+    /// workspace index captured before the fix — by the time the fix is done
+    /// the captured index may be stale. If focus after the fix is somewhere
+    /// other than the spacer's workspace, the user moved on their own during
+    /// the fix and restoration is skipped entirely.
+    pub async fn fix_spacer_position(&mut self, spacer_number: u32) -> Result<()> {
+        let spacer = self
+            .active_spacers
+            .iter()
+            .find(|s| s.number == spacer_number)
+            .cloned()
+            .ok_or_else(|| {
+                NiriSpacerError::Ipc(format!("no active spacer numbered {spacer_number}"))
+            })?;
+        let captured = self.focused_workspace().await?;
+
+        let placement = Placement {
+            workspace_id: spacer.workspace_id,
+            workspace_idx: spacer.workspace_idx,
+            color: spacer.color,
+        };
+        let placed = PlacementTransaction::new(&self.client, &self.windows)
+            .with_timeout(self.config.placement_timeout)
+            .with_clock(std::sync::Arc::clone(&self.clock))
+            .place(spacer.niri_window_id, &placement)
+            .await;
+        let outcome = match &placed {
+            Ok(()) => RepositionOutcome::Success,
+            Err(e) => RepositionOutcome::Failed(e.to_string()),
+        };
+        self.record_reposition(
+            spacer.niri_window_id,
+            RepositionTrigger::Reconciliation,
+            captured.as_ref().map(|ws| ws.id),
+            spacer.workspace_id,
+            outcome,
+        );
+        placed?;
+
+        let now_focused = self.focused_workspace().await?;
+        if now_focused.as_ref().map(|ws| ws.id) == captured.as_ref().map(|ws| ws.id) {
+            return Ok(()); // the fix did not disturb focus
+        }
+        if now_focused.as_ref().map(|ws| ws.id) != Some(spacer.workspace_id) {
+            debug!("focus moved elsewhere during the fix; not restoring");
+            return Ok(());
+        }
+
+        if let Err(e) = self.client.focus_workspace_previous().await {
+            debug!(error = %e, "focus-previous unsupported; falling back to recorded index");
+            if let Some(captured) = captured {
+                self.client
+                    .action(Action::FocusWorkspace {
+                        reference: WorkspaceReference::Index(captured.idx),
+                    })
+                    .await?;
+            }
+        }
+        self.clock
+            .sleep(self.config.timings.workspace_switch_delay)
+            .await;
+        Ok(())
+    }
+
+    /// Closes every active spacer, leaving the backend up.
+    ///
+    /// Each window is first closed through niri IPC and polled until niri
+    /// confirms it gone (bounded per window), and only then released on the
+    /// backend side. Closing Wayland-first races niri's unmap handling and
+    /// produces ghost columns and protocol warnings.
+    pub async fn remove_spacers(&mut self) -> Result<CleanupReport> {
+        self.spacer_ids
+            .write()
+            .expect("spacer id set poisoned")
+            .clear();
+        self.managed_workspaces
+            .write()
+            .expect("managed workspace set poisoned")
+            .clear();
+
+        let mut report = CleanupReport::default();
+        let live: std::collections::HashSet<u64> = self
+            .windows
+            .get_windows()
+            .await
+            .map(|windows| windows.into_iter().map(|w| w.id).collect())
+            .unwrap_or_default();
+
+        for spacer in std::mem::take(&mut self.active_spacers) {
+            if !live.contains(&spacer.niri_window_id) {
+                report.already_gone += 1;
+            } else {
+                match self.close_and_confirm(spacer.niri_window_id).await {
+                    Ok(true) => report.confirmed_closed += 1,
+                    Ok(false) => {
+                        warn!(
+                            window = spacer.niri_window_id,
+                            "niri did not confirm close in time; abandoning"
+                        );
+                        report.abandoned += 1;
+                    }
+                    Err(e) => {
+                        warn!(window = spacer.niri_window_id, error = %e, "close failed");
+                        report.abandoned += 1;
+                    }
+                }
+            }
+
+            let handle = crate::backend::BackendWindowHandle {
+                number: spacer.number,
+                title: spacer.title.clone(),
+            };
+            if let Err(e) = self.backend.close_window(&handle).await {
+                warn!(number = spacer.number, error = %e, "failed to close spacer");
+            }
+            self.emit(SpacerEvent::SpacerClosed(spacer.niri_window_id));
+        }
+        self.publish_status();
+        self.write_mapping_file();
+        Ok(report)
+    }
+
+    /// Asks niri to close a window and polls until it disappears; returns
+    /// whether the close was confirmed within the deadline.
+    async fn close_and_confirm(&self, window_id: u64) -> Result<bool> {
+        self.client
+            .action(Action::CloseWindow {
+                id: Some(window_id),
+            })
+            .await?;
+        let deadline = self.clock.now() + CLEANUP_WINDOW_TIMEOUT;
+        loop {
+            let gone = !self
+                .windows
+                .get_windows()
+                .await?
+                .iter()
+                .any(|w| w.id == window_id);
+            if gone {
+                return Ok(true);
+            }
+            if self.clock.now() >= deadline {
+                return Ok(false);
+            }
+            self.clock.sleep(CORRELATION_POLL).await;
+        }
+    }
+
+    /// Checks one spacer's column for co-tenant windows and expels them to
+    /// their own columns, so the user's window is not squeezed behind a
+    /// 1-pixel column. Returns the IDs expelled.
+    pub async fn fix_column_cotenancy(&mut self, number: u32) -> Result<Vec<u64>> {
+        let spacer = self
+            .active_spacers
+            .iter()
+            .find(|s| s.number == number)
+            .ok_or_else(|| {
+                NiriSpacerError::Ipc(format!("no active spacer numbered {number}"))
+            })?;
+        let windows = self.windows.get_windows().await?;
+        let ColumnOccupancy::CoTenants(cotenants) =
+            column_occupancy(&windows, spacer.niri_window_id)
+        else {
+            return Ok(Vec::new());
+        };
+
+        for id in &cotenants {
+            warn!(
+                spacer = spacer.niri_window_id,
+                cotenant = id,
+                "window shares the spacer's column; expelling it"
+            );
+            self.client
+                .action(Action::ConsumeOrExpelWindowRight { id: Some(*id) })
+                .await?;
+        }
+        Ok(cotenants)
+    }
+
+    /// Closes windows left behind by a previous session of this instance,
+    /// discovered by app ID. A crashed or SIGKILLed transient session
+    /// cannot clean up after itself; doing it at the next startup
+    /// guarantees no accumulation for users who toggle the tool often.
+    pub async fn clean_stale_spacers(&mut self) -> Result<u32> {
+        let ours: std::collections::HashSet<u64> = self
+            .active_spacers
+            .iter()
+            .map(|s| s.niri_window_id)
+            .collect();
+        let stale: Vec<u64> = self
+            .windows
+            .get_spacer_windows()
+            .await?
+            .into_iter()
+            .filter(|w| !ours.contains(&w.id))
+            .map(|w| w.id)
+            .collect();
+
+        let mut cleaned = 0;
+        for id in stale {
+            match self.close_and_confirm(id).await {
+                Ok(true) => cleaned += 1,
+                Ok(false) => warn!(window = id, "stale spacer did not close in time"),
+                Err(e) => warn!(window = id, error = %e, "could not close stale spacer"),
+            }
+        }
+        Ok(cleaned)
+    }
+
+    /// Tears everything down in a fixed order: stop monitors first (so our
+    /// own teardown churn cannot trigger corrective actions), then close
+    /// windows through niri, then shut the Wayland loop down. Each phase is
+    /// logged; the monitor stop and the per-window closes are time-bounded.
+    pub async fn cleanup(&mut self) -> Result<CleanupReport> {
+        if let Some(monitor) = self.monitor.take() {
+            info!("cleanup phase 1: stopping monitors");
+            monitor.stop().await;
+        }
+
+        info!("cleanup phase 2: closing spacer windows via niri");
+        let report = self.remove_spacers().await?;
+        info!(
+            confirmed = report.confirmed_closed,
+            abandoned = report.abandoned,
+            already_gone = report.already_gone,
+            "spacer windows closed"
+        );
+
+        info!("cleanup phase 3: shutting down the Wayland backend");
+        self.backend.shutdown().await?;
+        Ok(report)
+    }
+}
+
+/// Reads one line and interprets it as a yes/no answer; non-TTY input is
+/// always "no".
+fn confirm<R: BufRead>(mut input: R, stdin_is_tty: bool) -> bool {
+    if !stdin_is_tty {
+        return false;
+    }
+    print!("Proceed? [y/N] ");
+    use std::io::Write;
+    let _ = std::io::stdout().flush();
+
+    let mut answer = String::new();
+    if input.read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::MockBackend;
+    use crate::test_support::MockNiri;
+
+    #[tokio::test]
+    async fn plan_covers_all_workspaces_in_index_order() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let spacer = NiriSpacer::with_backend(
+            NiriSpacerConfig::new(niri.socket_path()),
+            MockBackend::new(),
+        )
+        .unwrap();
+
+        let plan = spacer.compute_plan().await.unwrap();
+        assert_eq!(
+            plan.iter().map(|p| p.workspace_idx).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn run_places_one_spacer_per_workspace() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        spacer.run().await.unwrap();
+        assert_eq!(spacer.active_spacers().len(), 3);
+        let moves = niri
+            .state()
+            .lock()
+            .unwrap()
+            .actions
+            .iter()
+            .filter(|a| matches!(a, Action::MoveWindowToWorkspace { .. }))
+            .count();
+        assert_eq!(moves, 3);
+    }
+
+    #[tokio::test]
+    async fn interactive_no_aborts_without_creating_windows() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        let proceeded = spacer
+            .run_with_confirmation(std::io::Cursor::new(b"n\n"), true)
+            .await
+            .unwrap();
+
+        assert!(!proceeded);
+        assert!(spacer.active_spacers().is_empty());
+        assert!(niri.state().lock().unwrap().actions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn interactive_non_tty_is_treated_as_no() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        // "y" on a non-TTY stdin must still abort.
+        let proceeded = spacer
+            .run_with_confirmation(std::io::Cursor::new(b"y\n"), false)
+            .await
+            .unwrap();
+
+        assert!(!proceeded);
+        assert!(spacer.active_spacers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn managers_share_the_orchestrators_connection() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+
+        let focused = spacer.focused_workspace().await.unwrap().unwrap();
+        assert_eq!(focused.idx, 1);
+        assert_eq!(spacer.windows().await.unwrap().len(), 3);
+        assert_eq!(
+            spacer.workspace_manager().workspaces_sorted().await.unwrap().len(),
+            3
+        );
+        assert!(spacer
+            .window_manager()
+            .find_by_title("niri-spacer-1")
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn listen_for_new_workspaces_forwards_created_events() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        let mut rx = spacer.listen_for_new_workspaces().await.unwrap();
+        niri.wait_for_event_subscriber().await;
+
+        let created = crate::niri::Workspace {
+            id: 10,
+            idx: 4,
+            name: None,
+            output: Some("eDP-1".to_string()),
+            is_active: false,
+            is_focused: false,
+            active_window_id: None,
+        };
+        niri.send_event(crate::niri::NiriEvent::WorkspaceCreated {
+            workspace: created.clone(),
+        });
+        // Unrelated destroy events must not break the forwarding loop.
+        niri.send_event(crate::niri::NiriEvent::WorkspaceDestroyed { id: 10 });
+
+        let received = tokio::time::timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received, created);
+    }
+
+    #[tokio::test]
+    async fn audit_log_records_each_trigger_type() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        spacer.run().await.unwrap();
+        assert!(spacer
+            .audit_log()
+            .all(|e| e.trigger == RepositionTrigger::InitialPlacement
+                && e.outcome == RepositionOutcome::Success));
+        assert_eq!(spacer.audit_log().count(), 3);
+
+        for trigger in [
+            RepositionTrigger::FocusFix,
+            RepositionTrigger::Reconciliation,
+            RepositionTrigger::Respawn,
+        ] {
+            spacer.record_reposition(99, trigger, Some(1), 2, RepositionOutcome::Success);
+            assert_eq!(spacer.audit_log().last().unwrap().trigger, trigger);
+        }
+
+        // The status snapshot carries the most recent entries.
+        spacer.remove_spacers().await.unwrap();
+        let status = spacer.status();
+        let recent = &status.borrow().recent_repositions;
+        assert_eq!(recent.last().unwrap().trigger, RepositionTrigger::Respawn);
+    }
+
+    #[tokio::test]
+    async fn audit_log_respects_its_cap() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        for i in 0..300u64 {
+            spacer.record_reposition(
+                i,
+                RepositionTrigger::Reconciliation,
+                None,
+                1,
+                RepositionOutcome::Success,
+            );
+        }
+        assert_eq!(spacer.audit_log().count(), 128);
+        // Oldest entries were evicted first.
+        assert_eq!(spacer.audit_log().next().unwrap().spacer_id, 300 - 128);
+    }
+
+    /// Runs a full placement, then returns the spacer ready for
+    /// fix_spacer_position tests.
+    async fn spacer_for_fix_tests(niri: &MockNiri) -> NiriSpacer<MockBackend> {
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+        niri.state().lock().unwrap().actions.clear();
+        spacer
+    }
+
+    #[tokio::test]
+    async fn fix_restores_focus_via_focus_previous() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut spacer = spacer_for_fix_tests(&niri).await;
+
+        // The fix's move drags focus to the spacer's workspace (id 2).
+        niri.state().lock().unwrap().focus_after_move = Some(2);
+        spacer.fix_spacer_position(2).await.unwrap();
+
+        let actions = niri.state().lock().unwrap().actions.clone();
+        assert!(
+            actions.contains(&Action::FocusWorkspacePrevious {}),
+            "expected focus-previous restoration, got {actions:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn fix_falls_back_to_recorded_index_when_unsupported() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut spacer = spacer_for_fix_tests(&niri).await;
+        {
+            let state = niri.state();
+            let mut state = state.lock().unwrap();
+            state.focus_after_move = Some(2);
+            state
+                .reject_action_kinds
+                .push("FocusWorkspacePrevious".to_string());
+        }
+
+        spacer.fix_spacer_position(2).await.unwrap();
+
+        let actions = niri.state().lock().unwrap().actions.clone();
+        assert!(
+            actions.contains(&Action::FocusWorkspace {
+                reference: WorkspaceReference::Index(1)
+            }),
+            "expected fallback to the captured index, got {actions:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn fix_skips_restoration_when_user_moved_on() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut spacer = spacer_for_fix_tests(&niri).await;
+        // Focus lands on workspace 3 — not the fixed spacer's workspace 2 —
+        // so the user went somewhere deliberately.
+        niri.state().lock().unwrap().focus_after_move = Some(3);
+
+        spacer.fix_spacer_position(2).await.unwrap();
+
+        let actions = niri.state().lock().unwrap().actions.clone();
+        assert!(
+            !actions.contains(&Action::FocusWorkspacePrevious {})
+                && !actions
+                    .iter()
+                    .any(|a| matches!(a, Action::FocusWorkspace { .. })),
+            "no restoration expected, got {actions:?}"
+        );
+    }
+
+    /// Waits until the shared redraw log reaches `count` entries.
+    async fn wait_for_redraws(redraws: &crate::backend::mock::RedrawLog, count: usize) {
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while redraws.lock().unwrap().len() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("expected redraws never arrived");
+    }
+
+    #[tokio::test]
+    async fn recolor_fans_out_to_every_spacer_and_sticks_for_respawns() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let redraws = std::sync::Arc::clone(&backend.redraws);
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+
+        let red = Color::new(0xff, 0x00, 0x00);
+        spacer.recolor_spacers(red).await.unwrap();
+
+        let log = redraws.lock().unwrap().clone();
+        assert_eq!(
+            log.iter().map(|(n, c, o)| (*n, *c, *o)).collect::<Vec<_>>(),
+            vec![(1, red, 1.0), (2, red, 1.0), (3, red, 1.0)]
+        );
+        assert!(spacer.active_spacers().iter().all(|s| s.color == red));
+
+        // A respawn recreates the window in the new color.
+        let original = spacer.active_spacers()[1].clone();
+        niri.state()
+            .lock()
+            .unwrap()
+            .windows
+            .retain(|w| w.id != original.niri_window_id);
+        let respawned = spacer.respawn_spacer(&original).await.unwrap();
+        assert_eq!(respawned.color, red);
+    }
+
+    #[tokio::test]
+    async fn recolor_single_spacer_targets_only_that_window() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let redraws = std::sync::Arc::clone(&backend.redraws);
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+
+        let blue = Color::new(0x00, 0x00, 0xff);
+        spacer.recolor_spacer(2, blue).await.unwrap();
+
+        let log = redraws.lock().unwrap().clone();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0], (2, blue, 1.0));
+        assert_eq!(spacer.active_spacers()[1].color, blue);
+        assert_ne!(spacer.active_spacers()[0].color, blue);
+
+        assert!(spacer.recolor_spacer(99, blue).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn overview_hide_repaints_transparent_and_restores() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let redraws = std::sync::Arc::clone(&backend.redraws);
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+
+        let watcher = tokio::spawn(async move {
+            let _ = spacer.watch_overview(OverviewStyle::Hide).await;
+        });
+        niri.wait_for_event_subscriber().await;
+
+        niri.send_event(crate::niri::NiriEvent::OverviewOpenedOrClosed { is_open: true });
+        wait_for_redraws(&redraws, 3).await;
+        assert!(redraws.lock().unwrap().iter().all(|(_, _, o)| *o == 0.0));
+
+        // A duplicate open must not trigger another repaint round; closing
+        // restores full opacity.
+        niri.send_event(crate::niri::NiriEvent::OverviewOpenedOrClosed { is_open: true });
+        niri.send_event(crate::niri::NiriEvent::OverviewOpenedOrClosed { is_open: false });
+        wait_for_redraws(&redraws, 6).await;
+        let log = redraws.lock().unwrap().clone();
+        assert_eq!(log.len(), 6, "duplicate open event must be ignored");
+        assert!(log[3..].iter().all(|(_, _, o)| *o == 1.0));
+        watcher.abort();
+    }
+
+    #[tokio::test]
+    async fn overview_dim_uses_reduced_opacity() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let redraws = std::sync::Arc::clone(&backend.redraws);
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.count = Some(1);
+        let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+        spacer.run().await.unwrap();
+
+        let watcher = tokio::spawn(async move {
+            let _ = spacer.watch_overview(OverviewStyle::Dim).await;
+        });
+        niri.wait_for_event_subscriber().await;
+        niri.send_event(crate::niri::NiriEvent::OverviewOpenedOrClosed { is_open: true });
+
+        wait_for_redraws(&redraws, 1).await;
+        let (_, _, opacity) = redraws.lock().unwrap()[0];
+        assert!(opacity > 0.0 && opacity < 1.0, "dim should be partial: {opacity}");
+        watcher.abort();
+    }
+
+    /// Counts the spacer windows the mock currently knows.
+    fn spacer_window_count(niri: &MockNiri) -> usize {
+        niri.state()
+            .lock()
+            .unwrap()
+            .windows
+            .iter()
+            .filter(|w| w.app_id.as_deref() == Some("niri-spacer"))
+            .count()
+    }
+
+    #[tokio::test]
+    async fn config_reload_reapplies_widths_once_per_cooldown() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+        niri.state().lock().unwrap().actions.clear();
+
+        let watcher = tokio::spawn(async move {
+            let _ = spacer.watch_mode(3).await;
+        });
+        niri.wait_for_event_subscriber().await;
+
+        niri.send_event(crate::niri::NiriEvent::ConfigLoaded { failed: false });
+        // A second reload right away must be swallowed by the cooldown, and
+        // a failed reload never triggers a pass.
+        niri.send_event(crate::niri::NiriEvent::ConfigLoaded { failed: false });
+        niri.send_event(crate::niri::NiriEvent::ConfigLoaded { failed: true });
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                let widths = niri
+                    .state()
+                    .lock()
+                    .unwrap()
+                    .actions
+                    .iter()
+                    .filter(|a| matches!(a, Action::SetWindowWidth { .. }))
+                    .count();
+                if widths >= 3 {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("width reapplication never happened");
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let widths = niri
+            .state()
+            .lock()
+            .unwrap()
+            .actions
+            .iter()
+            .filter(|a| matches!(a, Action::SetWindowWidth { .. }))
+            .count();
+        assert_eq!(widths, 3, "exactly one pass within the cooldown window");
+        watcher.abort();
+    }
+
+    #[tokio::test]
+    async fn monitor_scheduled_respawns_execute_in_watch_mode() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+        let victim = spacer.active_spacers()[1].clone();
+        assert!(
+            spacer.managed_workspace_set().read().unwrap().contains(&victim.workspace_id),
+            "placed workspaces must be in the managed set"
+        );
+        let respawn_tx = spacer.respawn_request_sender();
+
+        // A close the event stream missed: the window is simply gone.
+        niri.state()
+            .lock()
+            .unwrap()
+            .windows
+            .retain(|w| w.id != victim.niri_window_id);
+
+        let watcher = tokio::spawn(async move {
+            let _ = spacer.watch_mode(3).await;
+        });
+        niri.wait_for_event_subscriber().await;
+
+        // What the focus monitor would send when focus reveals the gap.
+        respawn_tx.send(victim.workspace_id).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                let respawned = niri
+                    .state()
+                    .lock()
+                    .unwrap()
+                    .windows
+                    .iter()
+                    .any(|w| w.title.as_deref() == Some("niri-spacer-2"));
+                if respawned {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("scheduled respawn never executed");
+        watcher.abort();
+    }
+
+    #[tokio::test]
+    async fn watch_mode_respawns_externally_closed_spacers() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+        let victim = spacer.active_spacers()[1].niri_window_id;
+
+        let watcher = tokio::spawn(async move {
+            let _ = spacer.watch_mode(3).await;
+        });
+        niri.wait_for_event_subscriber().await;
+
+        // The user closes spacer 2 behind our back.
+        niri.state().lock().unwrap().windows.retain(|w| w.id != victim);
+        niri.send_event(crate::niri::NiriEvent::WindowClosed { id: victim });
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while spacer_window_count(&niri) < 3 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("respawned spacer never appeared");
+
+        // The replacement keeps the closed spacer's number and workspace,
+        // and the stale niri ID is gone from the mock's window list.
+        let state = niri.state();
+        let state = state.lock().unwrap();
+        let respawned = state
+            .windows
+            .iter()
+            .find(|w| w.title.as_deref() == Some("niri-spacer-2"))
+            .expect("respawn must reuse the original number");
+        assert_eq!(respawned.workspace_id, Some(2));
+        assert_ne!(respawned.id, victim);
+        assert!(state.windows.iter().all(|w| w.id != victim));
+        drop(state);
+        watcher.abort();
+    }
+
+    #[tokio::test]
+    async fn respawn_swaps_ids_in_every_registry() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+        let original = spacer.active_spacers()[1].clone();
+        let ids = spacer.spacer_ids();
+
+        // Simulate the external close, then respawn in place.
+        niri.state()
+            .lock()
+            .unwrap()
+            .windows
+            .retain(|w| w.id != original.niri_window_id);
+        let respawned = spacer.respawn_spacer(&original).await.unwrap();
+
+        assert_eq!(respawned.number, original.number);
+        assert_eq!(respawned.workspace_idx, original.workspace_idx);
+        assert_eq!(respawned.color, original.color);
+        assert_eq!(respawned.title, original.title);
+        assert_ne!(respawned.niri_window_id, original.niri_window_id);
+
+        let ids = ids.read().unwrap();
+        assert!(!ids.contains(&original.niri_window_id), "stale ID must be gone");
+        assert!(ids.contains(&respawned.niri_window_id));
+        assert_eq!(spacer.active_spacers().len(), 3);
+        assert!(spacer
+            .active_spacers()
+            .iter()
+            .all(|s| s.niri_window_id != original.niri_window_id));
+    }
+
+    #[tokio::test]
+    async fn watch_mode_reconciliation_refills_after_missed_events() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend)
+                .unwrap()
+                .with_clock(std::sync::Arc::new(crate::clock::TestClock::new()));
+        spacer.run().await.unwrap();
+        let victim = spacer.active_spacers()[0].niri_window_id;
+
+        // Window vanishes with no event at all; only reconciliation (whose
+        // 30s interval elapses instantly on the virtual clock) catches it.
+        niri.state().lock().unwrap().windows.retain(|w| w.id != victim);
+
+        let watcher = tokio::spawn(async move {
+            let _ = spacer.watch_mode(3).await;
+        });
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while spacer_window_count(&niri) < 3 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("reconciliation never refilled the missing spacer");
+        watcher.abort();
+    }
+
+    #[tokio::test]
+    async fn mapping_file_is_rewritten_as_the_set_changes() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("niri-spacer").join("windows.json");
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.mapping_file = Some(path.clone());
+        let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+
+        spacer.run().await.unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let mapping: std::collections::BTreeMap<u32, MappingEntry> =
+            serde_json::from_str(&text).unwrap();
+        assert_eq!(mapping.len(), 3);
+        assert_eq!(mapping[&1].workspace_idx, 1);
+        assert_eq!(mapping[&1].app_id, "niri-spacer");
+        // No temp file left behind by the atomic rewrite.
+        assert!(!path.with_extension("json.tmp").exists());
+
+        spacer.remove_spacers().await.unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let mapping: std::collections::BTreeMap<u32, MappingEntry> =
+            serde_json::from_str(&text).unwrap();
+        assert!(mapping.is_empty());
+    }
+
+    #[tokio::test]
+    async fn correlated_id_can_be_embedded_in_the_title() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let titles = std::sync::Arc::clone(&backend.titles);
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.count = Some(1);
+        config.embed_id_in_title = true;
+        let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+
+        spacer.run().await.unwrap();
+
+        let id = spacer.active_spacers()[0].niri_window_id;
+        let titles = titles.lock().unwrap();
+        assert_eq!(titles.len(), 1);
+        assert_eq!(titles[0], (1, format!("niri-spacer-1 [{id}]")));
+    }
+
+    fn many_workspaces(n: u8) -> Vec<crate::niri::Workspace> {
+        (1..=n)
+            .map(|idx| crate::niri::Workspace {
+                id: u64::from(idx),
+                idx,
+                name: None,
+                output: Some("eDP-1".to_string()),
+                is_active: idx == 1,
+                is_focused: idx == 1,
+                active_window_id: None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn batch_survives_one_connection_drop() {
+        let _env = crate::test_support::env_lock().await;
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        // Request 3 is the first per-spacer query; dropping it simulates a
+        // restart mid-batch.
+        niri.state().lock().unwrap().drop_request_numbers.push(3);
+        std::env::set_var("NIRI_SOCKET", niri.socket_path());
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        spacer.run().await.unwrap();
+
+        assert_eq!(spacer.active_spacers().len(), 3);
+        assert_eq!(spacer.status().borrow().reconnect_count, 1);
+    }
+
+    #[tokio::test]
+    async fn add_spacer_grows_the_active_set_until_workspaces_run_out() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        for expected_idx in 1..=3u8 {
+            let added = spacer.add_spacer().await.unwrap();
+            assert_eq!(added.workspace_idx, expected_idx);
+            assert_eq!(spacer.active_spacers().len(), usize::from(expected_idx));
+        }
+
+        // All three workspaces covered: a fourth add has nowhere to go.
+        let err = spacer.add_spacer().await.unwrap_err();
+        assert!(err.to_string().contains("already has a spacer"), "got {err}");
+    }
+
+    #[test]
+    fn merge_layers_cli_over_file_over_defaults() {
+        let file_layer = ConfigOverlay::from_toml_str(
+            "count = 9\non-occupied = \"skip\"\ncolor = \"#112233\"\n",
+        )
+        .unwrap();
+        let cli_layer = ConfigOverlay {
+            count: Some(5),
+            ..ConfigOverlay::default()
+        };
+
+        let config = NiriSpacerConfig::with_defaults("/run/niri.sock")
+            .merge(&file_layer)
+            .unwrap()
+            .merge(&cli_layer)
+            .unwrap();
+
+        // CLI count beats the file's 9, which beat the default of None.
+        assert_eq!(config.count, Some(5));
+        // File-only fields survive the CLI layer's None.
+        assert_eq!(config.on_occupied, OccupiedPolicy::Skip);
+        assert_eq!(config.native.color, Color::new(0x11, 0x22, 0x33));
+        // Untouched fields keep their defaults.
+        assert!(!config.best_effort);
+    }
+
+    #[test]
+    fn merge_rejects_bad_values_and_unknown_keys() {
+        assert!(ConfigOverlay::from_toml_str("no-such-key = 1").is_err());
+        let overlay = ConfigOverlay {
+            on_occupied: Some("explode".to_string()),
+            ..ConfigOverlay::default()
+        };
+        assert!(NiriSpacerConfig::with_defaults("/run/niri.sock")
+            .merge(&overlay)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn construction_needs_no_live_niri() {
+        // No socket exists at this path and no environment variables are
+        // consulted; only the first IPC request would fail.
+        let spacer = NiriSpacer::new_for_testing(
+            NiriSpacerConfig::new("/nonexistent/niri.sock"),
+            MockBackend::new(),
+        )
+        .unwrap();
+        assert!(spacer.active_spacers().is_empty());
+        assert_eq!(spacer.get_remaining_capacity(), MAX_WINDOW_COUNT);
+        assert!(spacer.compute_plan().await.is_err(), "only IPC use may fail");
+    }
+
+    #[tokio::test]
+    async fn builder_assembles_the_configuration() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let builder = NiriSpacerBuilder::new(niri.socket_path())
+            .count(2)
+            .on_occupied(OccupiedPolicy::Skip)
+            .best_effort(true)
+            .placement_timeout(Duration::from_secs(1))
+            .column_width(ColumnWidth::Proportion(0.1))
+            .mapping_file(None)
+            .native_config(crate::backend::NativeConfig::for_instance("builder").unwrap());
+
+        let config = builder.config();
+        assert_eq!(config.count, Some(2));
+        assert_eq!(config.on_occupied, OccupiedPolicy::Skip);
+        assert!(config.best_effort);
+        assert_eq!(config.placement_timeout, Duration::from_secs(1));
+        assert_eq!(config.column_width, Some(ColumnWidth::Proportion(0.1)));
+        assert_eq!(config.mapping_file, None);
+        assert_eq!(config.native.app_id, "niri-spacer-builder");
+
+        let mut backend = MockBackend::with_niri(niri.state());
+        backend.config = config.native.clone();
+        let mut spacer = builder.build_with_backend(backend).unwrap();
+        spacer.run().await.unwrap();
+        assert_eq!(spacer.active_spacers().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn run_rejects_plans_beyond_capacity() {
+        let niri = MockNiri::spawn(many_workspaces(55), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        match spacer.run().await.unwrap_err() {
+            NiriSpacerError::MaxSpacerCountReached { current, maximum } => {
+                assert_eq!(current, 0);
+                assert_eq!(maximum, MAX_WINDOW_COUNT);
+            }
+            other => panic!("expected MaxSpacerCountReached, got {other:?}"),
+        }
+        assert!(spacer.active_spacers().is_empty(), "nothing may be created");
+    }
+
+    #[tokio::test]
+    async fn interactive_runs_are_capacity_checked_too() {
+        let niri = MockNiri::spawn(many_workspaces(55), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        let err = spacer
+            .run_with_confirmation(std::io::Cursor::new(b"y\n"), true)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, NiriSpacerError::MaxSpacerCountReached { .. }),
+            "got {err:?}"
+        );
+        assert!(spacer.active_spacers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn remaining_capacity_tracks_active_spacers() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        assert_eq!(spacer.get_remaining_capacity(), MAX_WINDOW_COUNT);
+        spacer.run().await.unwrap();
+        assert_eq!(spacer.get_remaining_capacity(), MAX_WINDOW_COUNT - 3);
+    }
+
+    #[tokio::test]
+    async fn restore_truncates_to_remaining_capacity() {
+        let niri = MockNiri::spawn(many_workspaces(60), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        // An export claiming 60 spacers, one per workspace.
+        let exported = ExportedState {
+            active_spacers: (1..=60u8)
+                .map(|idx| SpacerWindow {
+                    number: u32::from(idx),
+                    created_at: std::time::Instant::now(),
+                    niri_window_id: 0,
+                    workspace_id: u64::from(idx),
+                    workspace_idx: idx,
+                    color: DEFAULT_PALETTE[0],
+                    title: format!("niri-spacer-{idx}"),
+                })
+                .collect(),
+            background_color: DEFAULT_PALETTE[0],
+            app_id_prefix: "niri-spacer".to_string(),
+            session_info: ExportedSessionInfo {
+                socket_path: niri.socket_path().to_path_buf(),
+            },
+        };
+
+        let report = spacer.restore(&exported).await.unwrap();
+        assert_eq!(report.placed, MAX_WINDOW_COUNT);
+        assert_eq!(spacer.active_spacers().len() as u32, MAX_WINDOW_COUNT);
+        assert_eq!(spacer.get_remaining_capacity(), 0);
+    }
+
+    #[tokio::test]
+    async fn best_effort_continues_past_individual_failures() {
+        let workspaces: Vec<crate::niri::Workspace> = many_workspaces(9);
+        let niri = MockNiri::spawn(workspaces, vec![]).await;
+        let mut backend = MockBackend::with_niri(niri.state());
+        backend.fail_numbers = vec![3];
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.best_effort = true;
+        let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+
+        let report = spacer.run().await.unwrap();
+
+        assert_eq!(report.placed, 8);
+        assert!(!report.is_full_success());
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].workspace_idx, 3);
+        let placed: Vec<u8> = spacer.active_spacers().iter().map(|s| s.workspace_idx).collect();
+        assert_eq!(placed, vec![1, 2, 4, 5, 6, 7, 8, 9]);
+    }
+
+    /// Two outputs: workspaces 1-2 on eDP-1 (1 focused), 3-4 on HDMI-A-1.
+    fn two_output_workspaces() -> Vec<crate::niri::Workspace> {
+        let mut workspaces = many_workspaces(4);
+        for ws in workspaces.iter_mut().skip(2) {
+            ws.output = Some("HDMI-A-1".to_string());
+        }
+        workspaces
+    }
+
+    #[tokio::test]
+    async fn out_of_range_workspace_range_lists_what_exists() {
+        let mut workspaces = MockNiri::three_workspaces();
+        workspaces[0].name = Some("mail".to_string());
+        let niri = MockNiri::spawn(workspaces, vec![]).await;
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.workspace_range = Some((10, 20));
+
+        match compute_plan(&config).await.unwrap_err() {
+            NiriSpacerError::WorkspaceIndexOutOfRange {
+                requested_start,
+                requested_end,
+                available_max,
+                available,
+            } => {
+                assert_eq!((requested_start, requested_end), (10, 20));
+                assert_eq!(available_max, 3);
+                let listing = AvailableWorkspaces(&available).to_string();
+                assert!(listing.contains("1: mail"), "{listing}");
+                assert!(listing.contains("\n  2\n"), "{listing}");
+                assert!(listing.contains("\n  3\n"), "{listing}");
+            }
+            other => panic!("expected WorkspaceIndexOutOfRange, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn partially_overlapping_range_is_clamped() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.workspace_range = Some((2, 10));
+
+        let plan = compute_plan(&config).await.unwrap();
+        assert_eq!(
+            plan.iter().map(|p| p.workspace_idx).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn reverse_order_flips_creation_but_keeps_targets() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.order = PlacementOrder::Reverse;
+
+        let plan = compute_plan(&config).await.unwrap();
+        assert_eq!(
+            plan.iter().map(|p| p.workspace_idx).collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+        // Each workspace keeps the color it would get in forward order.
+        config.order = PlacementOrder::Forward;
+        let forward = compute_plan(&config).await.unwrap();
+        for placement in &plan {
+            let twin = forward
+                .iter()
+                .find(|p| p.workspace_idx == placement.workspace_idx)
+                .unwrap();
+            assert_eq!(placement.color, twin.color);
+        }
+    }
+
+    #[tokio::test]
+    async fn reverse_order_moves_windows_highest_workspace_first() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.order = PlacementOrder::Reverse;
+        let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+
+        spacer.run().await.unwrap();
+
+        let move_targets: Vec<u8> = niri
+            .state()
+            .lock()
+            .unwrap()
+            .actions
+            .iter()
+            .filter_map(|a| match a {
+                Action::MoveWindowToWorkspace {
+                    reference: WorkspaceReference::Index(idx),
+                    ..
+                } => Some(*idx),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(move_targets, vec![3, 2, 1]);
+        // Verification still matched each window to its own workspace.
+        for s in spacer.active_spacers() {
+            assert_eq!(
+                niri.state()
+                    .lock()
+                    .unwrap()
+                    .windows
+                    .iter()
+                    .find(|w| w.id == s.niri_window_id)
+                    .unwrap()
+                    .workspace_id,
+                Some(s.workspace_id)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn plans_cover_only_the_focused_output_by_default() {
+        let niri = MockNiri::spawn(two_output_workspaces(), vec![]).await;
+        let config = NiriSpacerConfig::new(niri.socket_path());
+
+        let plan = compute_plan(&config).await.unwrap();
+        assert_eq!(
+            plan.iter().map(|p| p.workspace_idx).collect::<Vec<_>>(),
+            vec![1, 2],
+            "HDMI workspaces must be out of scope"
+        );
+    }
+
+    #[tokio::test]
+    async fn all_outputs_restores_the_global_scope() {
+        let niri = MockNiri::spawn(two_output_workspaces(), vec![]).await;
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.all_outputs = true;
+
+        let plan = compute_plan(&config).await.unwrap();
+        assert_eq!(
+            plan.iter().map(|p| p.workspace_idx).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4]
+        );
+    }
+
+    #[tokio::test]
+    async fn add_spacer_respects_the_output_scope() {
+        let niri = MockNiri::spawn(two_output_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        spacer.add_spacer().await.unwrap();
+        spacer.add_spacer().await.unwrap();
+        // Both focused-output workspaces covered; the HDMI ones are out of
+        // scope, so the third add fails.
+        assert!(spacer.add_spacer().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn correlation_skip_closes_the_orphan_and_continues() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut backend = MockBackend::with_niri(niri.state());
+        // Window 2 is created but never appears in niri.
+        backend.skip_niri_insert_numbers = vec![2];
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.on_correlation_fail = CorrelationFailPolicy::Skip;
+        let mut spacer = NiriSpacer::with_backend(config, backend)
+            .unwrap()
+            .with_clock(std::sync::Arc::new(crate::clock::TestClock::new()));
+
+        let report = spacer.run().await.unwrap();
+
+        assert_eq!(report.placed, 2);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].workspace_idx, 2);
+        assert!(!report.is_full_success());
+        let placed: Vec<u8> = spacer.active_spacers().iter().map(|s| s.workspace_idx).collect();
+        assert_eq!(placed, vec![1, 3]);
+    }
+
+    #[tokio::test]
+    async fn correlation_abort_still_fails_the_batch() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut backend = MockBackend::with_niri(niri.state());
+        backend.skip_niri_insert_numbers = vec![2];
+        let mut spacer = NiriSpacer::with_backend(
+            NiriSpacerConfig::new(niri.socket_path()),
+            backend,
+        )
+        .unwrap()
+        .with_clock(std::sync::Arc::new(crate::clock::TestClock::new()));
+
+        match spacer.run().await.unwrap_err() {
+            NiriSpacerError::CorrelationTimeout(title) => {
+                assert_eq!(title, "niri-spacer-2");
+            }
+            other => panic!("expected CorrelationTimeout, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn lenient_validation_drops_vanished_spacers_and_keeps_the_rest() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+
+        let victim = spacer.active_spacers()[1].niri_window_id;
+        niri.state().lock().unwrap().windows.retain(|w| w.id != victim);
+
+        let dropped = spacer.validate_spacers().await.unwrap();
+        assert_eq!(dropped, 1);
+        assert_eq!(spacer.active_spacers().len(), 2);
+        assert!(!spacer.spacer_ids().read().unwrap().contains(&victim));
+    }
+
+    #[tokio::test]
+    async fn strict_validation_fails_on_the_first_missing_spacer() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.validation = ValidationPolicy::Strict;
+        let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+        spacer.run().await.unwrap();
+
+        let victim = spacer.active_spacers()[1].niri_window_id;
+        niri.state().lock().unwrap().windows.retain(|w| w.id != victim);
+
+        match spacer.validate_spacers().await.unwrap_err() {
+            NiriSpacerError::WindowNotFound(id) => assert_eq!(id, victim),
+            other => panic!("expected WindowNotFound, got {other:?}"),
+        }
+        // Strict leaves the set untouched for the caller to decide.
+        assert_eq!(spacer.active_spacers().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn cancellation_mid_batch_cleans_up_partial_windows() {
+        let niri = MockNiri::spawn(many_workspaces(6), vec![]).await;
+        let mut backend = MockBackend::with_niri(niri.state());
+        let token = tokio_util::sync::CancellationToken::new();
+        backend.cancel_after_creations = Some((2, token.clone()));
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        // The orchestrator must observe the same token the backend cancels.
+        assert!(!spacer.cancellation_token().is_cancelled());
+        let spacer_token = spacer.cancellation_token();
+        tokio::spawn(async move {
+            token.cancelled().await;
+            spacer_token.cancel();
+        });
+
+        match spacer.run().await.unwrap_err() {
+            NiriSpacerError::Cancelled => {}
+            other => panic!("expected Cancelled, got {other:?}"),
+        }
+
+        // The two already-created spacers were closed again.
+        assert!(spacer.active_spacers().is_empty());
+        let remaining = niri
+            .state()
+            .lock()
+            .unwrap()
+            .windows
+            .iter()
+            .filter(|w| w.app_id.as_deref() == Some("niri-spacer"))
+            .count();
+        assert_eq!(remaining, 0);
+    }
+
+    #[tokio::test]
+    async fn without_best_effort_the_first_failure_aborts() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut backend = MockBackend::with_niri(niri.state());
+        backend.fail_numbers = vec![2];
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        assert!(spacer.run().await.is_err());
+        assert_eq!(spacer.active_spacers().len(), 1);
+    }
+
+    #[test]
+    fn column_width_parsing() {
+        assert_eq!(
+            "0.05".parse::<ColumnWidth>().unwrap(),
+            ColumnWidth::Proportion(0.05)
+        );
+        assert_eq!("40px".parse::<ColumnWidth>().unwrap(), ColumnWidth::FixedPx(40));
+        for bad in ["abc", "1.5", "0", "-3px", "px"] {
+            assert!(bad.parse::<ColumnWidth>().is_err(), "{bad:?} should not parse");
+        }
+    }
+
+    #[tokio::test]
+    async fn proportion_width_mode_is_the_fallback_for_stubborn_configs() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.count = Some(1);
+        config.width_mode = SpacerWidthMode::Proportion;
+        let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+
+        spacer.run().await.unwrap();
+
+        let actions = niri.state().lock().unwrap().actions.clone();
+        assert!(
+            actions.iter().any(|a| matches!(
+                a,
+                Action::SetWindowWidth {
+                    change: SizeChange::SetProportion(p),
+                    ..
+                } if *p == MINIMAL_PROPORTION
+            )),
+            "expected the minimal proportion request, got {actions:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn column_width_proportion_reaches_niri() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.count = Some(1);
+        config.column_width = Some(ColumnWidth::Proportion(0.05));
+        let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+
+        spacer.run().await.unwrap();
+
+        let actions = niri.state().lock().unwrap().actions.clone();
+        assert!(
+            actions.iter().any(|a| matches!(
+                a,
+                Action::SetWindowWidth {
+                    change: SizeChange::SetProportion(p),
+                    ..
+                } if *p == 0.05
+            )),
+            "expected a SetProportion(0.05) width request, got {actions:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn spacing_controls_the_requested_fixed_width() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.count = Some(1);
+        config.native.width = 8;
+        let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+
+        spacer.run().await.unwrap();
+
+        let actions = niri.state().lock().unwrap().actions.clone();
+        assert!(
+            actions.iter().any(|a| matches!(
+                a,
+                Action::SetWindowWidth {
+                    change: SizeChange::SetFixed(8),
+                    ..
+                }
+            )),
+            "expected a SetFixed(8) width request, got {actions:?}"
+        );
+    }
+
+    fn layout_window(id: u64, ws: u64, pos: Option<(usize, usize)>) -> crate::niri::Window {
+        crate::niri::Window {
+            id,
+            title: None,
+            app_id: None,
+            workspace_id: Some(ws),
+            is_focused: false,
+            pid: None,
+            layout: Some(crate::niri::WindowLayout {
+                pos_in_scrolling_layout: pos,
+            }),
+        }
+    }
+
+    #[test]
+    fn column_occupancy_fixtures() {
+        // Single tile: spacer alone in column 0.
+        let windows = vec![
+            layout_window(50, 2, Some((0, 0))),
+            layout_window(8, 2, Some((1, 0))),
+        ];
+        assert_eq!(column_occupancy(&windows, 50), ColumnOccupancy::SingleTile);
+
+        // Co-tenant: window 8 stacked into the spacer's column as tile 1.
+        let windows = vec![
+            layout_window(50, 2, Some((0, 0))),
+            layout_window(8, 2, Some((0, 1))),
+            layout_window(9, 3, Some((0, 0))), // other workspace: irrelevant
+        ];
+        assert_eq!(
+            column_occupancy(&windows, 50),
+            ColumnOccupancy::CoTenants(vec![8])
+        );
+
+        // Floating spacer: no layout position, checks do not apply.
+        let windows = vec![layout_window(50, 2, None)];
+        assert_eq!(column_occupancy(&windows, 50), ColumnOccupancy::Floating);
+    }
+
+    #[tokio::test]
+    async fn spacer_events_reach_multiple_subscribers() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        let mut first = spacer.subscribe_to_spacer_events();
+        let mut second = spacer.subscribe_to_spacer_events();
+
+        spacer.run().await.unwrap();
+        spacer.remove_spacers().await.unwrap();
+
+        for subscriber in [&mut first, &mut second] {
+            let mut created = 0;
+            let mut closed = 0;
+            while let Ok(event) = subscriber.try_recv() {
+                match event {
+                    SpacerEvent::SpacerCreated(_) => created += 1,
+                    SpacerEvent::SpacerClosed(_) => closed += 1,
+                    _ => {}
+                }
+            }
+            assert_eq!(created, 3);
+            assert_eq!(closed, 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_subscribers_lag_without_blocking_fast_ones() {
+        let niri = MockNiri::spawn(many_workspaces(40), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        // One subscriber never reads; the other reads promptly in a task.
+        let mut slow = spacer.subscribe_to_spacer_events();
+        let mut fast = spacer.subscribe_to_spacer_events();
+        let fast_count = tokio::spawn(async move {
+            let mut count = 0;
+            while let Ok(event) = fast.recv().await {
+                if matches!(event, SpacerEvent::SpacerCreated(_)) {
+                    count += 1;
+                }
+            }
+            count
+        });
+
+        spacer.run().await.unwrap();
+        drop(spacer); // closes the channel so the fast reader finishes
+
+        assert_eq!(fast_count.await.unwrap(), 40, "fast subscriber sees everything");
+        // The slow subscriber is lagged or has a backlog, but nothing hung.
+        let mut seen = 0;
+        loop {
+            match slow.try_recv() {
+                Ok(_) => seen += 1,
+                Err(tokio::sync::broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        assert!(seen > 0);
+    }
+
+    #[tokio::test]
+    async fn cotenants_are_expelled() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.count = Some(1);
+        let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+        spacer.run().await.unwrap();
+        let spacer_id = spacer.active_spacers()[0].niri_window_id;
+
+        // Stack a user window into the spacer's column.
+        {
+            let state = niri.state();
+            let mut state = state.lock().unwrap();
+            let ws = state.windows.iter().find(|w| w.id == spacer_id).unwrap().workspace_id;
+            for w in state.windows.iter_mut() {
+                if w.id == spacer_id {
+                    w.layout = Some(crate::niri::WindowLayout {
+                        pos_in_scrolling_layout: Some((0, 0)),
+                    });
+                }
+            }
+            state.windows.push(layout_window(777, ws.unwrap(), Some((0, 1))));
+        }
+
+        let expelled = spacer.fix_column_cotenancy(1).await.unwrap();
+        assert_eq!(expelled, vec![777]);
+        assert!(niri
+            .state()
+            .lock()
+            .unwrap()
+            .actions
+            .contains(&Action::ConsumeOrExpelWindowRight { id: Some(777) }));
+    }
+
+    #[tokio::test]
+    async fn transient_startup_cleans_leftovers_from_a_previous_session() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        // Two spacers linger from a crashed previous transient session, plus
+        // an unrelated window that must be left alone.
+        {
+            let state = niri.state();
+            let mut state = state.lock().unwrap();
+            state.insert_window("niri-spacer".to_string(), "niri-spacer-1".to_string());
+            state.insert_window("niri-spacer".to_string(), "niri-spacer-2".to_string());
+            state.insert_window("firefox".to_string(), "browser".to_string());
+        }
+        let backend = MockBackend::with_niri(niri.state());
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.transient = true;
+        let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+
+        spacer.run().await.unwrap();
+
+        let state = niri.state();
+        let state = state.lock().unwrap();
+        let spacer_windows = state
+            .windows
+            .iter()
+            .filter(|w| w.app_id.as_deref() == Some("niri-spacer"))
+            .count();
+        assert_eq!(spacer_windows, 3, "only this session's spacers remain");
+        assert!(state
+            .windows
+            .iter()
+            .any(|w| w.app_id.as_deref() == Some("firefox")));
+    }
+
+    #[tokio::test]
+    async fn cleanup_stops_the_monitor_before_closing_windows() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+        let spacer_id = spacer.active_spacers()[0].niri_window_id;
+
+        let monitor =
+            crate::focus::FocusMonitor::spawn(spacer.client().clone(), spacer.spacer_ids());
+        spacer.attach_monitor(monitor);
+        niri.wait_for_event_subscriber().await;
+        // Establish focus history so a live monitor would redirect.
+        niri.send_event(crate::niri::NiriEvent::WindowFocusChanged { id: Some(7) });
+
+        spacer.cleanup().await.unwrap();
+        let actions_after_cleanup = niri.state().lock().unwrap().actions.len();
+
+        // Teardown-style churn after cleanup must trigger nothing.
+        niri.send_event(crate::niri::NiriEvent::WindowFocusChanged {
+            id: Some(spacer_id),
+        });
+        niri.send_event(crate::niri::NiriEvent::WindowClosed { id: spacer_id });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(
+            niri.state().lock().unwrap().actions.len(),
+            actions_after_cleanup,
+            "no corrective actions may fire after cleanup began"
+        );
+    }
+
+    #[tokio::test]
+    async fn cleanup_confirms_closes_through_niri() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+
+        let report = spacer.cleanup().await.unwrap();
+        assert_eq!(report.confirmed_closed, 3);
+        assert_eq!(report.abandoned, 0);
+        assert_eq!(report.already_gone, 0);
+        assert!(niri.state().lock().unwrap().windows.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cleanup_counts_already_gone_windows() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+
+        // One spacer vanished behind our back (e.g. the user closed it).
+        let victim = spacer.active_spacers()[1].niri_window_id;
+        niri.state().lock().unwrap().windows.retain(|w| w.id != victim);
+
+        let report = spacer.cleanup().await.unwrap();
+        assert_eq!(report.confirmed_closed, 2);
+        assert_eq!(report.already_gone, 1);
+        assert_eq!(report.abandoned, 0);
+    }
+
+    #[tokio::test]
+    async fn cleanup_abandons_windows_niri_never_closes() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+
+        // From here on, action side effects never land; the confirm loops
+        // burn virtual time only.
+        niri.state().lock().unwrap().move_apply_delay = Some(Duration::from_secs(3600));
+        spacer = spacer.with_clock(std::sync::Arc::new(crate::clock::TestClock::new()));
+
+        let wall = std::time::Instant::now();
+        let report = spacer.cleanup().await.unwrap();
+        assert_eq!(report.confirmed_closed, 0);
+        assert_eq!(report.abandoned, 3);
+        assert!(wall.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn status_watch_tracks_run_and_remove() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        let status = spacer.status();
+
+        assert_eq!(status.borrow().spacer_count, 0);
+        assert!(status.borrow().last_fix.is_none());
+
+        spacer.run().await.unwrap();
+        assert_eq!(status.borrow().spacer_count, 3);
+        assert!(status.borrow().last_fix.is_some());
+
+        spacer.remove_spacers().await.unwrap();
+        assert_eq!(status.borrow().spacer_count, 0);
+        assert_eq!(status.borrow().reconnect_count, 0);
+    }
+
+    /// Puts one unrelated window on workspace 2 of the given mock.
+    fn occupy_workspace_two(niri: &MockNiri) {
+        let state = niri.state();
+        let mut state = state.lock().unwrap();
+        let id = state.insert_window("firefox".to_string(), "browser".to_string());
+        let ws2 = state.workspaces[1].id;
+        state
+            .windows
+            .iter_mut()
+            .find(|w| w.id == id)
+            .unwrap()
+            .workspace_id = Some(ws2);
+    }
+
+    #[tokio::test]
+    async fn occupied_warn_keeps_full_plan() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        occupy_workspace_two(&niri);
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.on_occupied = OccupiedPolicy::Warn;
+
+        let plan = compute_plan(&config).await.unwrap();
+        assert_eq!(
+            plan.iter().map(|p| p.workspace_idx).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn occupied_skip_rederives_the_sequence() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        occupy_workspace_two(&niri);
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.on_occupied = OccupiedPolicy::Skip;
+        config.count = Some(2);
+
+        let plan = compute_plan(&config).await.unwrap();
+        assert_eq!(
+            plan.iter().map(|p| p.workspace_idx).collect::<Vec<_>>(),
+            vec![1, 3],
+            "occupied workspace 2 must be skipped in favor of 3"
+        );
+    }
+
+    #[tokio::test]
+    async fn occupied_fail_aborts() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        occupy_workspace_two(&niri);
+        let mut config = NiriSpacerConfig::new(niri.socket_path());
+        config.on_occupied = OccupiedPolicy::Fail;
+
+        match compute_plan(&config).await.unwrap_err() {
+            NiriSpacerError::WorkspaceOccupied { idx, windows } => {
+                assert_eq!(idx, 2);
+                assert_eq!(windows, 1);
+            }
+            other => panic!("expected WorkspaceOccupied, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_instances_do_not_cross_correlate() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+
+        let run_instance = |name: &str| {
+            let name = name.to_string();
+            let state = niri.state();
+            let socket = niri.socket_path().to_path_buf();
+            async move {
+                let mut config = NiriSpacerConfig::new(socket);
+                config.native = crate::backend::NativeConfig::for_instance(&name).unwrap();
+                let mut backend = MockBackend::with_niri(state);
+                backend.config = config.native.clone();
+                let mut spacer = NiriSpacer::with_backend(config, backend).unwrap();
+                spacer.run().await.unwrap();
+                spacer
+            }
+        };
+
+        let spacer_a = run_instance("a").await;
+        let spacer_b = run_instance("b").await;
+
+        let state = niri.state();
+        let state = state.lock().unwrap();
+        for (spacer, prefix) in [(&spacer_a, "niri-spacer-a"), (&spacer_b, "niri-spacer-b")] {
+            for window in spacer.active_spacers() {
+                assert!(window.title.starts_with(prefix));
+                let niri_window = state
+                    .windows
+                    .iter()
+                    .find(|w| w.id == window.niri_window_id)
+                    .unwrap();
+                // Each instance correlated to its own windows, not the
+                // other instance's.
+                assert_eq!(niri_window.title.as_deref(), Some(window.title.as_str()));
+                assert_eq!(niri_window.app_id.as_deref(), Some(prefix));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn placement_waits_for_delayed_moves() {
+        for delay_ms in [0u64, 40, 120] {
+            let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+            niri.state().lock().unwrap().move_apply_delay =
+                Some(Duration::from_millis(delay_ms));
+            let backend = MockBackend::with_niri(niri.state());
+            let mut spacer =
+                NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend)
+                    .unwrap();
+
+            spacer.run().await.unwrap();
+
+            let state = niri.state();
+            let state = state.lock().unwrap();
+            for spacer_window in spacer.active_spacers() {
+                let window = state
+                    .windows
+                    .iter()
+                    .find(|w| w.id == spacer_window.niri_window_id)
+                    .unwrap();
+                assert_eq!(
+                    window.workspace_id,
+                    Some(spacer_window.workspace_id),
+                    "delay {delay_ms}ms: window must be verified on its workspace"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn placement_times_out_when_move_never_lands() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        // Far beyond the placement timeout: the move never becomes visible.
+        niri.state().lock().unwrap().move_apply_delay = Some(Duration::from_secs(3600));
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer = NiriSpacer::with_backend(
+            NiriSpacerConfig::new(niri.socket_path()),
+            backend,
+        )
+        .unwrap()
+        .with_clock(std::sync::Arc::new(crate::clock::TestClock::new()));
+
+        let wall = std::time::Instant::now();
+        let err = spacer.run().await.unwrap_err();
+        assert!(err.to_string().contains("did not land"), "got: {err}");
+        assert!(
+            wall.elapsed() < Duration::from_secs(2),
+            "virtual-time timeout must not wait in real time"
+        );
+    }
+
+    #[tokio::test]
+    async fn reconnect_follows_a_socket_change() {
+        let _env = crate::test_support::env_lock().await;
+        let old = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(old.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(old.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+        let spacers_before = spacer.active_spacers().to_vec();
+
+        // "Restarted" niri: fewer workspaces, fresh socket.
+        let new = MockNiri::spawn(MockNiri::three_workspaces()[..2].to_vec(), vec![]).await;
+        std::env::set_var("NIRI_SOCKET", new.socket_path());
+        spacer.reconnect().await.unwrap();
+
+        assert_eq!(spacer.active_spacers(), spacers_before.as_slice());
+        let plan = spacer.compute_plan().await.unwrap();
+        assert_eq!(plan.len(), 2, "plan should come from the new socket");
+    }
+
+    #[tokio::test]
+    async fn reconnect_to_dead_socket_keeps_old_clients() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let dead = dir.path().join("dead.sock");
+        assert!(spacer.reconnect_to(&dead).await.is_err());
+
+        // Still serving from the original socket.
+        assert_eq!(spacer.compute_plan().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn export_state_roundtrips_through_restore() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+        spacer.run().await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        spacer.export_state(&path).await.unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("_comments"), "export should carry field docs");
+
+        let restored = NiriSpacer::<MockBackend>::restore_from_export(&path).unwrap();
+        // created_at is not persisted (a restored spacer's age restarts),
+        // so compare everything else.
+        assert_eq!(restored.active_spacers.len(), spacer.active_spacers().len());
+        for (restored, live) in restored.active_spacers.iter().zip(spacer.active_spacers()) {
+            assert_eq!(restored.number, live.number);
+            assert_eq!(restored.niri_window_id, live.niri_window_id);
+            assert_eq!(restored.workspace_id, live.workspace_id);
+            assert_eq!(restored.workspace_idx, live.workspace_idx);
+            assert_eq!(restored.color, live.color);
+            assert_eq!(restored.title, live.title);
+        }
+        assert_eq!(restored.app_id_prefix, "niri-spacer");
+        assert_eq!(
+            restored.session_info.socket_path,
+            niri.socket_path().to_path_buf()
+        );
+    }
+
+    #[tokio::test]
+    async fn interactive_yes_runs_the_plan() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let backend = MockBackend::with_niri(niri.state());
+        let mut spacer =
+            NiriSpacer::with_backend(NiriSpacerConfig::new(niri.socket_path()), backend).unwrap();
+
+        let proceeded = spacer
+            .run_with_confirmation(std::io::Cursor::new(b"y\n"), true)
+            .await
+            .unwrap();
+
+        assert!(proceeded);
+        assert_eq!(spacer.active_spacers().len(), 3);
+    }
+}
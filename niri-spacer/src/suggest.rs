@@ -0,0 +1,61 @@
+//! Suggested niri configuration snippets.
+//!
+//! Users keep asking what to put in their niri config to pair with this
+//! tool; the answer depends on the app ID pattern, spacer width, and column
+//! width actually in use, so generate it from the live configuration.
+
+use crate::backend::NativeConfig;
+use crate::spacer::ColumnWidth;
+
+/// Renders a ready-to-paste KDL `window-rule` block matching the given
+/// configuration.
+pub fn window_rule_snippet(native: &NativeConfig, column_width: Option<ColumnWidth>) -> String {
+    let width_rule = match column_width {
+        Some(ColumnWidth::Proportion(p)) => format!("proportion {p}"),
+        Some(ColumnWidth::FixedPx(px)) => format!("fixed {px}"),
+        None => format!("fixed {}", native.width),
+    };
+    format!(
+        "window-rule {{\n    match app-id=\"^{app_id}$\"\n    default-column-width {{ {width_rule}; }}\n    open-focused false\n}}\n",
+        app_id = native.app_id,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_pins_one_pixel() {
+        let snippet = window_rule_snippet(&NativeConfig::default(), None);
+        assert!(snippet.contains("match app-id=\"^niri-spacer$\""));
+        assert!(snippet.contains("default-column-width { fixed 1; }"));
+        assert!(snippet.contains("open-focused false"));
+    }
+
+    #[test]
+    fn spacing_shows_up_as_fixed_width() {
+        let native = NativeConfig {
+            width: 8,
+            ..NativeConfig::default()
+        };
+        let snippet = window_rule_snippet(&native, None);
+        assert!(snippet.contains("default-column-width { fixed 8; }"));
+    }
+
+    #[test]
+    fn explicit_column_width_wins() {
+        let native = NativeConfig::default();
+        let snippet = window_rule_snippet(&native, Some(ColumnWidth::Proportion(0.05)));
+        assert!(snippet.contains("default-column-width { proportion 0.05; }"));
+        let snippet = window_rule_snippet(&native, Some(ColumnWidth::FixedPx(40)));
+        assert!(snippet.contains("default-column-width { fixed 40; }"));
+    }
+
+    #[test]
+    fn instance_app_ids_are_anchored() {
+        let native = NativeConfig::for_instance("left-monitor").unwrap();
+        let snippet = window_rule_snippet(&native, None);
+        assert!(snippet.contains("match app-id=\"^niri-spacer-left-monitor$\""));
+    }
+}
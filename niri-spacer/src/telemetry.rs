@@ -0,0 +1,83 @@
+//! OpenTelemetry span export, compiled only with the `opentelemetry` feature.
+//!
+//! The binary wires this up at startup when `$OTEL_EXPORTER_OTLP_ENDPOINT`
+//! is set; library consumers can build their own provider and layer.
+
+use std::sync::OnceLock;
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::error::{NiriSpacerError, Result};
+
+/// Environment variable naming the OTLP endpoint to export spans to.
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// Builds a tracer provider batching spans to an OTLP endpoint.
+pub fn otlp_tracer_provider(service_name: &str, endpoint: &str) -> Result<SdkTracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| NiriSpacerError::Ipc(format!("OTLP exporter init failed: {e}")))?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
+}
+
+/// A `tracing` layer bridging spans into the given provider.
+pub fn layer<S>(provider: &SdkTracerProvider) -> impl tracing_subscriber::Layer<S>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer("niri-spacer"))
+}
+
+/// Keeps the provider alive for the rest of the process so the batch
+/// exporter keeps flushing.
+pub fn install(provider: SdkTracerProvider) {
+    let _ = PROVIDER.set(provider);
+}
+
+/// Flushes and shuts down the installed provider, if any.
+pub fn shutdown() {
+    if let Some(provider) = PROVIDER.get() {
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!(error = %e, "OpenTelemetry shutdown failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry_sdk::trace::InMemorySpanExporter;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn spans_reach_the_exporter() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry().with(layer(&provider));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("unit-span").in_scope(|| tracing::info!("hello"));
+        });
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+        assert!(spans.iter().any(|span| span.name == "unit-span"));
+    }
+}
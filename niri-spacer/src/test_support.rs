@@ -0,0 +1,365 @@
+//! A mock niri IPC server for tests.
+//!
+//! Speaks the same line-oriented JSON protocol as the real compositor: one
+//! request per connection, canned state, actions recorded for assertions.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast;
+
+use crate::niri::types::NiriEvent;
+use crate::niri::{Action, Reply, Request, Response, Window, Workspace};
+
+/// Serializes tests that mutate process environment variables (e.g.
+/// `NIRI_SOCKET`); hold the guard for the whole test body. Async so the
+/// guard may live across awaits.
+pub(crate) async fn env_lock() -> tokio::sync::MutexGuard<'static, ()> {
+    static LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+    LOCK.lock().await
+}
+
+/// A plain unfocused workspace for mock state.
+pub(crate) fn fresh_workspace(id: u64, idx: u8) -> Workspace {
+    Workspace {
+        id,
+        idx,
+        name: None,
+        output: Some("eDP-1".to_string()),
+        is_active: false,
+        is_focused: false,
+        active_window_id: None,
+    }
+}
+
+/// Mutable state behind the mock server, shared with tests.
+#[derive(Debug, Default)]
+pub(crate) struct MockState {
+    pub workspaces: Vec<Workspace>,
+    pub windows: Vec<Window>,
+    pub outputs: Vec<crate::niri::Output>,
+    /// Every action received, in order.
+    pub actions: Vec<Action>,
+    /// When set, the observable effect of a move action is applied only
+    /// after this delay, simulating a loaded compositor.
+    pub move_apply_delay: Option<Duration>,
+    /// When set, a successful move also shifts workspace focus to this
+    /// workspace, simulating focus-follows or the user moving mid-fix.
+    pub focus_after_move: Option<u64>,
+    /// Action tags (e.g. "FocusWorkspacePrevious") the mock rejects with an
+    /// error reply, simulating an older niri.
+    pub reject_action_kinds: Vec<String>,
+    /// 1-based request ordinals whose connection is dropped without a
+    /// reply, simulating a niri restart mid-request.
+    pub drop_request_numbers: Vec<u64>,
+    /// Total requests served so far (excluding event-stream subscriptions).
+    pub requests_seen: u64,
+    /// How many `Windows` requests were served.
+    pub windows_requests: u64,
+    next_window_id: u64,
+}
+
+impl MockState {
+    fn set_focused_workspace(&mut self, ws_id: u64) {
+        for ws in &mut self.workspaces {
+            ws.is_focused = ws.id == ws_id;
+            ws.is_active = ws.id == ws_id;
+        }
+    }
+}
+
+impl MockState {
+    /// Registers a window as if a client had just mapped it, returning the
+    /// ID "niri" assigned.
+    pub fn insert_window(&mut self, app_id: String, title: String) -> u64 {
+        self.next_window_id += 1;
+        let id = 1000 + self.next_window_id;
+        self.windows.push(Window {
+            id,
+            title: Some(title),
+            app_id: Some(app_id),
+            workspace_id: self.workspaces.first().map(|ws| ws.id),
+            is_focused: false,
+            pid: None,
+            layout: None,
+        });
+        id
+    }
+}
+
+/// A mock niri listening on a socket in a temp directory.
+pub(crate) struct MockNiri {
+    socket_path: PathBuf,
+    state: Arc<Mutex<MockState>>,
+    events: broadcast::Sender<NiriEvent>,
+    event_subscribers: Arc<AtomicUsize>,
+    _dir: tempfile::TempDir,
+}
+
+impl MockNiri {
+    /// Starts the server with the given initial state.
+    pub async fn spawn(workspaces: Vec<Workspace>, windows: Vec<Window>) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("niri.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let state = Arc::new(Mutex::new(MockState {
+            workspaces,
+            windows,
+            ..MockState::default()
+        }));
+
+        let (events, _) = broadcast::channel(64);
+        let event_subscribers = Arc::new(AtomicUsize::new(0));
+
+        let accept_state = Arc::clone(&state);
+        let accept_events = events.clone();
+        let accept_subscribers = Arc::clone(&event_subscribers);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(
+                    stream,
+                    Arc::clone(&accept_state),
+                    accept_events.clone(),
+                    Arc::clone(&accept_subscribers),
+                ));
+            }
+        });
+
+        Self {
+            socket_path,
+            state,
+            events,
+            event_subscribers,
+            _dir: dir,
+        }
+    }
+
+    /// Pushes an event to every live event-stream subscriber.
+    pub fn send_event(&self, event: NiriEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Waits until at least one event-stream subscription is live.
+    pub async fn wait_for_event_subscriber(&self) {
+        self.wait_for_event_subscribers(1).await;
+    }
+
+    /// Waits until `count` event-stream subscriptions have been opened over
+    /// the server's lifetime.
+    pub async fn wait_for_event_subscribers(&self, count: usize) {
+        let subscribers = Arc::clone(&self.event_subscribers);
+        tokio::time::timeout(Duration::from_secs(2), async move {
+            while subscribers.load(Ordering::SeqCst) < count {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("event stream subscriber never connected");
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    pub fn state(&self) -> Arc<Mutex<MockState>> {
+        Arc::clone(&self.state)
+    }
+
+    /// Three plain workspaces on one output, the first focused.
+    pub fn three_workspaces() -> Vec<Workspace> {
+        (1..=3)
+            .map(|idx| Workspace {
+                id: u64::from(idx),
+                idx,
+                name: None,
+                output: Some("eDP-1".to_string()),
+                is_active: idx == 1,
+                is_focused: idx == 1,
+                active_window_id: None,
+            })
+            .collect()
+    }
+}
+
+async fn serve_connection(
+    stream: UnixStream,
+    state: Arc<Mutex<MockState>>,
+    events: broadcast::Sender<NiriEvent>,
+    event_subscribers: Arc<AtomicUsize>,
+) {
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    if stream.read_line(&mut line).await.is_err() || line.is_empty() {
+        return;
+    }
+
+    let request = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => request,
+        Err(e) => {
+            write_reply(&mut stream, &Reply::Err(format!("bad request: {e}"))).await;
+            return;
+        }
+    };
+
+    if request == Request::EventStream {
+        write_reply(&mut stream, &Reply::Ok(Response::Handled)).await;
+        let mut rx = events.subscribe();
+        event_subscribers.fetch_add(1, Ordering::SeqCst);
+        // Keep serving: events flow out while action requests written on the
+        // same connection (shared-socket writers) are applied as they come.
+        let mut line = String::new();
+        loop {
+            tokio::select! {
+                event = rx.recv() => {
+                    let Ok(event) = event else { break };
+                    let mut out = serde_json::to_string(&event).unwrap();
+                    out.push('\n');
+                    if stream.get_mut().write_all(out.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+                read = stream.read_line(&mut line) => {
+                    match read {
+                        Ok(0) | Err(_) => break,
+                        Ok(_) => {
+                            if let Ok(Request::Action(action)) =
+                                serde_json::from_str::<Request>(&line)
+                            {
+                                let mut state = state.lock().unwrap();
+                                state.actions.push(action.clone());
+                                apply_action(&mut state, &action);
+                            }
+                            line.clear();
+                        }
+                    }
+                }
+            }
+        }
+        return;
+    }
+
+    {
+        let mut state = state.lock().unwrap();
+        state.requests_seen += 1;
+        if state.drop_request_numbers.contains(&state.requests_seen) {
+            // Simulated restart: close without replying.
+            return;
+        }
+    }
+
+    let reply = handle_request(request, &state);
+    write_reply(&mut stream, &reply).await;
+}
+
+async fn write_reply(stream: &mut BufReader<UnixStream>, reply: &Reply) {
+    let mut out = serde_json::to_string(reply).unwrap();
+    out.push('\n');
+    let _ = stream.get_mut().write_all(out.as_bytes()).await;
+}
+
+fn handle_request(request: Request, state_arc: &Arc<Mutex<MockState>>) -> Reply {
+    let mut state = state_arc.lock().unwrap();
+    match request {
+        Request::Workspaces => Reply::Ok(Response::Workspaces(state.workspaces.clone())),
+        Request::Windows => {
+            state.windows_requests += 1;
+            Reply::Ok(Response::Windows(state.windows.clone()))
+        }
+        Request::Outputs => Reply::Ok(Response::Outputs(state.outputs.clone())),
+        Request::Version => Reply::Ok(Response::Version("mock-niri 0.1".to_string())),
+        // Handled before we get here; kept for exhaustiveness.
+        Request::EventStream => Reply::Err("event stream handled elsewhere".to_string()),
+        Request::Action(action) => {
+            let tag = serde_json::to_value(&action)
+                .ok()
+                .and_then(|v| v.as_object().and_then(|o| o.keys().next().cloned()))
+                .unwrap_or_default();
+            if state.reject_action_kinds.contains(&tag) {
+                return Reply::Err(format!("unknown action {tag}"));
+            }
+            state.actions.push(action.clone());
+            match state.move_apply_delay {
+                Some(delay) => {
+                    let state = Arc::clone(state_arc);
+                    tokio::spawn(async move {
+                        tokio::time::sleep(delay).await;
+                        apply_action(&mut state.lock().unwrap(), &action);
+                    });
+                }
+                None => apply_action(&mut state, &action),
+            }
+            Reply::Ok(Response::Handled)
+        }
+    }
+}
+
+/// Applies the observable side effects of the actions tests rely on.
+fn apply_action(state: &mut MockState, action: &Action) {
+    if matches!(action, Action::MoveWorkspaceUp {}) {
+        // Swap the focused workspace's index with its predecessor, keeping
+        // focus on the moved workspace, like niri does.
+        if let Some(focused_idx) = state.workspaces.iter().find(|ws| ws.is_focused).map(|ws| ws.idx)
+        {
+            if focused_idx > 1 {
+                for ws in state.workspaces.iter_mut() {
+                    if ws.idx == focused_idx {
+                        ws.idx -= 1;
+                    } else if ws.idx == focused_idx - 1 {
+                        ws.idx += 1;
+                    }
+                }
+            }
+        }
+        return;
+    }
+    if let Action::CloseWindow { id: Some(id) } = action {
+        state.windows.retain(|w| w.id != *id);
+        return;
+    }
+    if let Action::FocusWorkspace {
+        reference: crate::niri::WorkspaceReference::Index(idx),
+    } = action
+    {
+        // Like niri, focusing one index past the end materializes a fresh
+        // dynamic workspace.
+        let max_idx = state.workspaces.iter().map(|ws| ws.idx).max().unwrap_or(0);
+        if *idx == max_idx + 1 {
+            let max_id = state.workspaces.iter().map(|ws| ws.id).max().unwrap_or(0);
+            state.workspaces.push(fresh_workspace(max_id + 1, *idx));
+        }
+        if let Some(ws) = state.workspaces.iter().find(|ws| ws.idx == *idx) {
+            let id = ws.id;
+            state.set_focused_workspace(id);
+        }
+        return;
+    }
+    if let Action::MoveWindowToWorkspace {
+        window_id: Some(id),
+        reference,
+    } = action
+    {
+        let target = match reference {
+            crate::niri::WorkspaceReference::Id(ws_id) => Some(*ws_id),
+            crate::niri::WorkspaceReference::Index(idx) => state
+                .workspaces
+                .iter()
+                .find(|ws| ws.idx == *idx)
+                .map(|ws| ws.id),
+            crate::niri::WorkspaceReference::Name(name) => state
+                .workspaces
+                .iter()
+                .find(|ws| ws.name.as_deref() == Some(name))
+                .map(|ws| ws.id),
+        };
+        if let Some(window) = state.windows.iter_mut().find(|w| w.id == *id) {
+            window.workspace_id = target;
+        }
+        if let Some(focus) = state.focus_after_move {
+            state.set_focused_workspace(focus);
+        }
+    }
+}
@@ -0,0 +1,371 @@
+//! Spacer window bookkeeping.
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::Color;
+use crate::error::{NiriSpacerError, Result};
+use crate::niri::{NiriClient, Window};
+
+/// One way of matching a string field.
+#[derive(Debug, Clone)]
+pub enum MatchPattern {
+    Exact(String),
+    Prefix(String),
+    Substring(String),
+    Regex(regex::Regex),
+}
+
+impl MatchPattern {
+    /// Compiles a regex pattern, reporting invalid syntax as a
+    /// configuration error.
+    pub fn regex(pattern: &str) -> Result<Self> {
+        regex::Regex::new(pattern)
+            .map(Self::Regex)
+            .map_err(|e| NiriSpacerError::ConfigParse(format!("invalid regex {pattern:?}: {e}")))
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Self::Exact(s) => value == s,
+            Self::Prefix(s) => value.starts_with(s.as_str()),
+            Self::Substring(s) => value.contains(s.as_str()),
+            Self::Regex(re) => re.is_match(value),
+        }
+    }
+}
+
+/// Matches niri windows on title and/or app ID; every set field must match,
+/// and a constrained field that the window lacks is a non-match. This is
+/// the shared primitive for discovery, spawn correlation, and adopting
+/// externally created placeholder windows.
+#[derive(Debug, Clone, Default)]
+pub struct WindowMatcher {
+    pub title: Option<MatchPattern>,
+    pub app_id: Option<MatchPattern>,
+}
+
+impl WindowMatcher {
+    pub fn by_title(pattern: MatchPattern) -> Self {
+        Self {
+            title: Some(pattern),
+            ..Self::default()
+        }
+    }
+
+    pub fn by_app_id(pattern: MatchPattern) -> Self {
+        Self {
+            app_id: Some(pattern),
+            ..Self::default()
+        }
+    }
+
+    /// Adds an app ID constraint to an existing matcher.
+    pub fn and_app_id(mut self, pattern: MatchPattern) -> Self {
+        self.app_id = Some(pattern);
+        self
+    }
+
+    pub fn matches(&self, window: &Window) -> bool {
+        let field_ok = |pattern: &Option<MatchPattern>, value: &Option<String>| match pattern {
+            None => true,
+            Some(p) => value.as_deref().is_some_and(|v| p.matches(v)),
+        };
+        field_ok(&self.title, &window.title) && field_ok(&self.app_id, &window.app_id)
+    }
+}
+
+impl SpacerWindow {
+    /// How long this spacer has been alive (since successful correlation).
+    pub fn age(&self) -> std::time::Duration {
+        self.created_at.elapsed()
+    }
+}
+
+/// The canonical one-line form used by every status output, so spacers
+/// always read the same everywhere.
+impl std::fmt::Display for SpacerWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "spacer {}: window {} on workspace {} ({})",
+            self.number, self.niri_window_id, self.workspace_idx, self.color
+        )
+    }
+}
+
+/// Renders a duration the way a human reads spacer ages: "2h 15m",
+/// "3m 20s", or "45s".
+pub fn format_age(age: std::time::Duration) -> String {
+    let total = age.as_secs();
+    let (hours, minutes, seconds) = (total / 3600, (total % 3600) / 60, total % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+/// Whether a live window is one of ours: exact-prefix app ID matching (the
+/// prefix itself, or prefix followed by a `-` suffix, so a foreign
+/// `niri-spacerX` never matches), plus PID verification when an expected
+/// PID is given. Adopted/stable-app-id mode passes `None` and skips the
+/// PID check.
+pub fn is_our_spacer(window: &Window, prefix: &str, expected_pid: Option<u32>) -> bool {
+    let app_id_ours = window.app_id.as_deref().is_some_and(|app_id| {
+        app_id == prefix
+            || app_id
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('-'))
+    });
+    if !app_id_ours {
+        return false;
+    }
+    match expected_pid {
+        None => true,
+        Some(pid) => window.pid == Some(pid),
+    }
+}
+
+/// Read-side helper for window state, mirroring
+/// [`crate::workspace::WorkspaceManager`].
+pub struct WindowManager {
+    client: NiriClient,
+    spacer_app_id_prefix: String,
+    /// When set, a window must also carry this PID to count as ours.
+    verify_pid: Option<u32>,
+}
+
+impl WindowManager {
+    pub fn new(client: NiriClient) -> Self {
+        Self {
+            client,
+            spacer_app_id_prefix: "niri-spacer".to_string(),
+            verify_pid: None,
+        }
+    }
+
+    /// Overrides the app ID prefix identifying our spacer windows.
+    pub fn with_spacer_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.spacer_app_id_prefix = prefix.into();
+        self
+    }
+
+    /// Requires windows to carry this PID to count as ours (off in
+    /// adopted/stable-app-id mode).
+    pub fn with_pid_verification(mut self, pid: Option<u32>) -> Self {
+        self.verify_pid = pid;
+        self
+    }
+
+    /// The canonical "all live windows that are ours" query; every feature
+    /// that filters for spacers (adopt, clean, stats, maintain) goes
+    /// through this one predicate.
+    pub async fn get_spacer_windows(&self) -> Result<Vec<Window>> {
+        Ok(self
+            .get_windows()
+            .await?
+            .into_iter()
+            .filter(|w| is_our_spacer(w, &self.spacer_app_id_prefix, self.verify_pid))
+            .collect())
+    }
+
+    /// The client used for window queries.
+    pub fn client(&self) -> &NiriClient {
+        &self.client
+    }
+
+    /// Fetches all windows.
+    pub async fn get_windows(&self) -> Result<Vec<Window>> {
+        self.client.get_windows().await
+    }
+
+    /// Finds a window by exact title, if present.
+    pub async fn find_by_title(&self, title: &str) -> Result<Option<Window>> {
+        let matcher = WindowMatcher::by_title(MatchPattern::Exact(title.to_string()));
+        Ok(self.find_matching(&matcher).await?.into_iter().next())
+    }
+
+    /// All windows matching the given matcher.
+    pub async fn find_matching(&self, matcher: &WindowMatcher) -> Result<Vec<Window>> {
+        Ok(self
+            .get_windows()
+            .await?
+            .into_iter()
+            .filter(|w| matcher.matches(w))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod ours_tests {
+    use super::*;
+
+    fn window(app_id: Option<&str>, pid: Option<u32>) -> Window {
+        Window {
+            id: 1,
+            title: None,
+            app_id: app_id.map(String::from),
+            workspace_id: None,
+            is_focused: false,
+            pid,
+            layout: None,
+        }
+    }
+
+    #[test]
+    fn ours_predicate_matrix() {
+        let prefix = "niri-spacer";
+        // Our prefix, no PID requirement.
+        assert!(is_our_spacer(&window(Some("niri-spacer"), None), prefix, None));
+        assert!(is_our_spacer(&window(Some("niri-spacer-left"), None), prefix, None));
+        // Foreign prefix that merely contains ours.
+        assert!(!is_our_spacer(&window(Some("niri-spacerd"), None), prefix, None));
+        assert!(!is_our_spacer(&window(Some("niri-spacer2"), None), prefix, None));
+        // Our prefix but the wrong (or missing) PID when one is required.
+        assert!(!is_our_spacer(&window(Some("niri-spacer"), Some(7)), prefix, Some(8)));
+        assert!(!is_our_spacer(&window(Some("niri-spacer"), None), prefix, Some(8)));
+        assert!(is_our_spacer(&window(Some("niri-spacer"), Some(8)), prefix, Some(8)));
+        // Adopted stable-app-id mode: PID ignored entirely.
+        assert!(is_our_spacer(&window(Some("niri-spacer"), Some(999)), prefix, None));
+        // No app ID at all.
+        assert!(!is_our_spacer(&window(None, None), prefix, None));
+    }
+}
+
+#[cfg(test)]
+mod age_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn age_grows_after_creation() {
+        let spacer = SpacerWindow {
+            number: 1,
+            created_at: std::time::Instant::now(),
+            niri_window_id: 1,
+            workspace_id: 1,
+            workspace_idx: 1,
+            color: crate::backend::Color::new(0, 0, 0),
+            title: "niri-spacer-1".to_string(),
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(spacer.age() >= std::time::Duration::from_millis(20));
+    }
+
+    #[test]
+    fn display_is_the_canonical_one_liner() {
+        let spacer = SpacerWindow {
+            number: 2,
+            created_at: std::time::Instant::now(),
+            niri_window_id: 1002,
+            workspace_id: 4,
+            workspace_idx: 2,
+            color: crate::backend::Color::new(0x28, 0x2a, 0x36),
+            title: "niri-spacer-2".to_string(),
+        };
+        assert_eq!(
+            spacer.to_string(),
+            "spacer 2: window 1002 on workspace 2 (#282a36)"
+        );
+    }
+
+    #[test]
+    fn ages_format_like_humans_read_them() {
+        use std::time::Duration;
+        assert_eq!(format_age(Duration::from_secs(45)), "45s");
+        assert_eq!(format_age(Duration::from_secs(3 * 60 + 20)), "3m 20s");
+        assert_eq!(format_age(Duration::from_secs(2 * 3600 + 15 * 60)), "2h 15m");
+        assert_eq!(format_age(Duration::from_secs(0)), "0s");
+    }
+}
+
+#[cfg(test)]
+mod matcher_tests {
+    use super::*;
+
+    fn window(title: &str, app_id: &str) -> Window {
+        Window {
+            id: 1,
+            title: Some(title.to_string()),
+            app_id: Some(app_id.to_string()),
+            workspace_id: None,
+            is_focused: false,
+            pid: None,
+            layout: None,
+        }
+    }
+
+    #[test]
+    fn each_match_mode_works() {
+        let w = window("niri-spacer-7", "niri-spacer");
+        assert!(WindowMatcher::by_title(MatchPattern::Exact("niri-spacer-7".into())).matches(&w));
+        assert!(!WindowMatcher::by_title(MatchPattern::Exact("niri-spacer".into())).matches(&w));
+        assert!(WindowMatcher::by_title(MatchPattern::Prefix("niri-".into())).matches(&w));
+        assert!(WindowMatcher::by_title(MatchPattern::Substring("spacer-7".into())).matches(&w));
+        assert!(
+            WindowMatcher::by_title(MatchPattern::regex(r"^niri-spacer-\d+$").unwrap())
+                .matches(&w)
+        );
+        assert!(
+            !WindowMatcher::by_title(MatchPattern::regex(r"^\d+$").unwrap()).matches(&w)
+        );
+    }
+
+    #[test]
+    fn combined_constraints_must_all_match() {
+        let w = window("niri-spacer-7", "niri-spacer");
+        let matcher = WindowMatcher::by_title(MatchPattern::Prefix("niri-".into()))
+            .and_app_id(MatchPattern::Exact("niri-spacer".into()));
+        assert!(matcher.matches(&w));
+
+        let wrong_app = window("niri-spacer-7", "firefox");
+        assert!(!matcher.matches(&wrong_app));
+    }
+
+    #[test]
+    fn missing_fields_fail_their_constraint() {
+        let untitled = Window {
+            id: 1,
+            title: None,
+            app_id: Some("niri-spacer".to_string()),
+            workspace_id: None,
+            is_focused: false,
+            pid: None,
+            layout: None,
+        };
+        assert!(!WindowMatcher::by_title(MatchPattern::Prefix("x".into())).matches(&untitled));
+        assert!(WindowMatcher::by_app_id(MatchPattern::Exact("niri-spacer".into()))
+            .matches(&untitled));
+        // No constraints at all matches everything.
+        assert!(WindowMatcher::default().matches(&untitled));
+    }
+
+    #[test]
+    fn invalid_regex_is_a_config_error() {
+        match MatchPattern::regex("([unclosed").unwrap_err() {
+            NiriSpacerError::ConfigParse(message) => {
+                assert!(message.contains("invalid regex"), "{message}");
+            }
+            other => panic!("expected ConfigParse, got {other:?}"),
+        }
+    }
+}
+
+/// One spacer we have created and placed, as tracked by the orchestrator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpacerWindow {
+    /// Backend sequence number (also embedded in the window title).
+    pub number: u32,
+    /// When correlation succeeded for this spacer. Not persisted: a
+    /// restored spacer's age starts at its restore time.
+    #[serde(skip, default = "std::time::Instant::now")]
+    pub created_at: std::time::Instant,
+    /// The window ID niri assigned after correlation.
+    pub niri_window_id: u64,
+    /// Workspace the spacer was placed on.
+    pub workspace_id: u64,
+    pub workspace_idx: u8,
+    pub color: Color,
+    pub title: String,
+}
@@ -0,0 +1,808 @@
+//! Workspace queries and bookkeeping on top of the niri IPC client.
+
+use tracing::{debug, info};
+
+use crate::clock::{Clock, TokioClock};
+use crate::error::Result;
+use crate::niri::{Action, NiriClient, Workspace, WorkspaceReference};
+use crate::spacer::Timings;
+use crate::window::SpacerWindow;
+
+/// Result of a [`WorkspaceManager::merge_sparse_workspaces`] pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeReport {
+    pub moved_windows: u32,
+    pub freed_workspaces: u32,
+    /// Workspace the windows were consolidated onto. Only meaningful when
+    /// `moved_windows > 0`.
+    pub target_workspace_id: u64,
+}
+
+/// Per-workspace occupancy summary for `--stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceStatsRow {
+    pub workspace_id: u64,
+    pub idx: u8,
+    pub window_count: usize,
+    pub spacer_count: usize,
+    pub is_focused: bool,
+    pub is_active: bool,
+}
+
+/// Snapshot of workspace occupancy, renderable as an aligned table.
+///
+/// Rows are ordered by workspace index (the position users see in niri),
+/// never by ID: IDs are non-sequential internal identifiers and sorting by
+/// them produces an order that matches nothing on screen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspaceStats {
+    pub rows: Vec<WorkspaceStatsRow>,
+}
+
+impl WorkspaceStats {
+    /// Renders the stats as a table bounded to `width` columns; when `None`
+    /// the terminal width is auto-detected (falling back to 80 columns off
+    /// a TTY).
+    pub fn render_table(&self, width: Option<u16>) -> comfy_table::Table {
+        use comfy_table::{presets, Cell, Table};
+
+        let width = width.or_else(|| {
+            terminal_size::terminal_size().map(|(terminal_size::Width(w), _)| w)
+        });
+
+        let mut table = Table::new();
+        table
+            .load_preset(presets::UTF8_FULL_CONDENSED)
+            .set_width(width.unwrap_or(80))
+            .set_header(["Workspace ID", "Index", "Windows", "Spacers", "Status"]);
+        for row in &self.rows {
+            let status = if row.is_focused {
+                "\u{25b6} Focused"
+            } else if row.is_active {
+                "Active"
+            } else {
+                "Idle"
+            };
+            table.add_row([
+                Cell::new(row.workspace_id),
+                Cell::new(row.idx),
+                Cell::new(row.window_count),
+                Cell::new(row.spacer_count),
+                Cell::new(status),
+            ]);
+        }
+        table
+    }
+
+    /// Prints the table to stdout.
+    pub fn print_table(&self, width: Option<u16>) {
+        println!("{}", self.render_table(width));
+    }
+}
+
+/// Read-side helper for workspace state.
+pub struct WorkspaceManager {
+    client: NiriClient,
+    /// App ID prefix identifying our own spacer windows, which maintenance
+    /// operations must leave alone.
+    spacer_app_id_prefix: String,
+    timings: Timings,
+    clock: std::sync::Arc<dyn Clock>,
+}
+
+impl WorkspaceManager {
+    pub fn new(client: NiriClient) -> Self {
+        Self {
+            client,
+            spacer_app_id_prefix: "niri-spacer".to_string(),
+            timings: Timings::default(),
+            clock: std::sync::Arc::new(TokioClock),
+        }
+    }
+
+    /// Overrides the app ID prefix used to recognize spacer windows.
+    pub fn with_spacer_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.spacer_app_id_prefix = prefix.into();
+        self
+    }
+
+    /// Overrides the settle delays.
+    pub fn with_timings(mut self, timings: Timings) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    /// Overrides the time source (tests use a deterministic clock).
+    pub fn with_clock(mut self, clock: std::sync::Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Consolidates fragmented layouts: every non-focused workspace holding
+    /// fewer than `max_windows_to_merge` windows gets its windows moved to
+    /// one consolidated workspace (the lowest-index sparse one). Spacer
+    /// windows are left where they are.
+    pub async fn merge_sparse_workspaces(
+        &mut self,
+        max_windows_to_merge: u32,
+    ) -> Result<MergeReport> {
+        let workspaces = self.workspaces_sorted().await?;
+        let windows = self.client.get_windows().await?;
+
+        // Windows we may move, grouped per workspace.
+        let movable_on = |ws_id: u64| {
+            windows
+                .iter()
+                .filter(|w| w.workspace_id == Some(ws_id))
+                .filter(|w| {
+                    !w.app_id
+                        .as_deref()
+                        .is_some_and(|id| id.starts_with(&self.spacer_app_id_prefix))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let sparse: Vec<(u64, Vec<&crate::niri::Window>)> = workspaces
+            .iter()
+            .filter(|ws| !ws.is_focused)
+            .map(|ws| (ws.id, movable_on(ws.id)))
+            .filter(|(_, movable)| {
+                !movable.is_empty() && (movable.len() as u32) < max_windows_to_merge
+            })
+            .collect();
+
+        let Some(((target_id, _), sources)) = sparse.split_first() else {
+            debug!("no sparse workspaces to merge");
+            return Ok(MergeReport {
+                moved_windows: 0,
+                freed_workspaces: 0,
+                target_workspace_id: 0,
+            });
+        };
+
+        let mut moved_windows = 0;
+        let mut freed_workspaces = 0;
+        for (_, movable) in sources {
+            for window in movable {
+                self.client
+                    .action(Action::MoveWindowToWorkspace {
+                        window_id: Some(window.id),
+                        reference: WorkspaceReference::Id(*target_id),
+                    })
+                    .await?;
+                moved_windows += 1;
+            }
+            freed_workspaces += 1;
+        }
+
+        info!(moved_windows, freed_workspaces, target = target_id, "merged sparse workspaces");
+        Ok(MergeReport {
+            moved_windows,
+            freed_workspaces,
+            target_workspace_id: *target_id,
+        })
+    }
+
+    /// The client used for workspace queries.
+    pub fn client(&self) -> &NiriClient {
+        &self.client
+    }
+
+    /// Fetches all workspaces sorted by index.
+    pub async fn workspaces_sorted(&self) -> Result<Vec<Workspace>> {
+        let mut workspaces = self.client.get_workspaces().await?;
+        workspaces.sort_by_key(|ws| ws.idx);
+        Ok(workspaces)
+    }
+
+    /// Makes sure at least `count` workspaces exist, materializing missing
+    /// ones the way a user would: focusing one index past the end spawns a
+    /// fresh dynamic workspace in niri. The originally focused workspace is
+    /// re-focused afterwards.
+    pub async fn ensure_workspaces(&mut self, count: u8) -> Result<()> {
+        let existing = self.workspaces_sorted().await?;
+        let have = existing.iter().map(|ws| ws.idx).max().unwrap_or(0);
+        if have >= count {
+            return Ok(());
+        }
+        let original_focus = existing.iter().find(|ws| ws.is_focused).map(|ws| ws.idx);
+
+        for idx in (have + 1)..=count {
+            debug!(idx, "materializing workspace");
+            self.focus_workspace_index(idx).await?;
+        }
+
+        if let Some(idx) = original_focus {
+            self.focus_workspace_index(idx).await?;
+        }
+        info!(from = have, to = count, "created missing workspaces");
+        Ok(())
+    }
+
+    /// Collects per-workspace occupancy, counting our spacers separately
+    /// from real windows. Rows come back ordered by workspace index.
+    pub async fn collect_stats(&self) -> Result<WorkspaceStats> {
+        let workspaces = self.workspaces_sorted().await?;
+        let windows = self.client.get_windows().await?;
+
+        let rows = workspaces
+            .into_iter()
+            .map(|ws| {
+                let (spacers, others): (Vec<_>, Vec<_>) = windows
+                    .iter()
+                    .filter(|w| w.workspace_id == Some(ws.id))
+                    .partition(|w| {
+                        w.app_id
+                            .as_deref()
+                            .is_some_and(|id| id.starts_with(&self.spacer_app_id_prefix))
+                    });
+                WorkspaceStatsRow {
+                    workspace_id: ws.id,
+                    idx: ws.idx,
+                    window_count: others.len(),
+                    spacer_count: spacers.len(),
+                    is_focused: ws.is_focused,
+                    is_active: ws.is_active,
+                }
+            })
+            .collect();
+        Ok(WorkspaceStats { rows })
+    }
+
+    /// Finds the lowest starting index of `count` consecutive workspace
+    /// indices that are all unused.
+    ///
+    /// This deliberately works on `workspace.idx`, not `workspace.id`: IDs
+    /// are opaque internal values with no adjacency guarantees, so scanning
+    /// them for "gaps" finds one immediately and always lands on the
+    /// initial value. Indices are the dense, per-output ordering that
+    /// placement actually targets.
+    pub async fn find_workspace_sequence(&self, count: u8) -> Result<u8> {
+        let used: std::collections::HashSet<u8> = self
+            .client
+            .get_workspaces()
+            .await?
+            .into_iter()
+            .map(|ws| ws.idx)
+            .collect();
+        Ok(first_free_run(&used, count))
+    }
+
+    /// Picks the best workspace for a new window by scoring every existing
+    /// workspace: indices near the focused workspace score higher, dense
+    /// workspaces are penalized, and `preferred_output` earns a bonus. This
+    /// is the `--smart-placement` alternative to the plain
+    /// [`Self::find_workspace_sequence`] first-fit.
+    pub async fn find_best_workspace_for_window(
+        &mut self,
+        preferred_output: Option<&str>,
+    ) -> Result<u8> {
+        let workspaces = self.workspaces_sorted().await?;
+        let windows = self.client.get_windows().await?;
+        let focused_idx = workspaces
+            .iter()
+            .find(|ws| ws.is_focused)
+            .map(|ws| ws.idx)
+            .unwrap_or(1);
+
+        let best = workspaces
+            .iter()
+            .map(|ws| {
+                let density = windows
+                    .iter()
+                    .filter(|w| w.workspace_id == Some(ws.id))
+                    .count();
+                let on_preferred = match preferred_output {
+                    Some(output) => ws.output.as_deref() == Some(output),
+                    None => true,
+                };
+                (score_workspace(ws.idx, focused_idx, density, on_preferred), ws.idx)
+            })
+            // Highest score wins; ties go to the lowest index.
+            .max_by_key(|(score, idx)| (*score, std::cmp::Reverse(*idx)))
+            .map(|(_, idx)| idx)
+            .unwrap_or(1);
+        Ok(best)
+    }
+
+    /// Focuses a workspace by index and waits out the workspace-switch
+    /// settle delay, which is deliberately separate from (and typically
+    /// longer than) the intra-workspace operation delay.
+    async fn focus_workspace_index(&self, idx: u8) -> Result<()> {
+        self.client
+            .action(Action::FocusWorkspace {
+                reference: WorkspaceReference::Index(idx),
+            })
+            .await?;
+        self.clock.sleep(self.timings.workspace_switch_delay).await;
+        Ok(())
+    }
+
+    /// The active window of the given workspace, straight from the
+    /// workspace response's `active_window_id` -- one small request instead
+    /// of a full window scan.
+    pub async fn active_window_in(&self, workspace_id: u64) -> Result<Option<u64>> {
+        Ok(self
+            .client
+            .get_workspaces()
+            .await?
+            .into_iter()
+            .find(|ws| ws.id == workspace_id)
+            .and_then(|ws| ws.active_window_id))
+    }
+
+    /// Reorders workspaces to match `desired_order` (workspace IDs,
+    /// first-to-last) using adjacent swaps: focus the workspace, move it up
+    /// one slot, repeat. IDs missing from `desired_order` keep their
+    /// relative order after the listed ones. The bubble pass performs
+    /// exactly one move per inversion, the minimum for adjacent swaps.
+    pub async fn reorder_workspaces(&mut self, desired_order: Vec<u64>) -> Result<()> {
+        let workspaces = self.workspaces_sorted().await?;
+        // Per-position desired rank; unlisted IDs sort after the listed
+        // ones, keeping their current relative order.
+        let mut ranks: Vec<usize> = workspaces
+            .iter()
+            .enumerate()
+            .map(|(position, ws)| {
+                desired_order
+                    .iter()
+                    .position(|&d| d == ws.id)
+                    .unwrap_or(desired_order.len() + position)
+            })
+            .collect();
+
+        // Bubble sort over the live ordering; position i corresponds to
+        // workspace index i + 1 (indices are contiguous per output).
+        loop {
+            let mut swapped = false;
+            for i in 1..ranks.len() {
+                if ranks[i] < ranks[i - 1] {
+                    let idx = (i + 1) as u8;
+                    self.client
+                        .action(Action::FocusWorkspace {
+                            reference: WorkspaceReference::Index(idx),
+                        })
+                        .await?;
+                    self.client.action(Action::MoveWorkspaceUp {}).await?;
+                    ranks.swap(i, i - 1);
+                    swapped = true;
+                }
+            }
+            if !swapped {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves every workspace holding one of the given spacers in front of
+    /// the user's occupied workspaces, preserving each group's internal
+    /// order.
+    pub async fn move_spacers_to_front(&mut self, spacers: &[SpacerWindow]) -> Result<()> {
+        let spacer_workspaces: std::collections::HashSet<u64> =
+            spacers.iter().map(|s| s.workspace_id).collect();
+        let workspaces = self.workspaces_sorted().await?;
+        let desired: Vec<u64> = workspaces
+            .iter()
+            .filter(|ws| spacer_workspaces.contains(&ws.id))
+            .chain(workspaces.iter().filter(|ws| !spacer_workspaces.contains(&ws.id)))
+            .map(|ws| ws.id)
+            .collect();
+        self.reorder_workspaces(desired).await
+    }
+
+    /// Joins outputs with the workspaces currently living on each, for the
+    /// `--list-outputs` diagnostic.
+    pub async fn list_outputs(&self) -> Result<Vec<(crate::niri::Output, Vec<Workspace>)>> {
+        let outputs = self.client.get_outputs().await?;
+        let workspaces = self.workspaces_sorted().await?;
+        Ok(outputs
+            .into_iter()
+            .map(|output| {
+                let on_output: Vec<Workspace> = workspaces
+                    .iter()
+                    .filter(|ws| ws.output.as_deref() == Some(output.name.as_str()))
+                    .cloned()
+                    .collect();
+                (output, on_output)
+            })
+            .collect())
+    }
+
+    /// The workspace currently holding keyboard focus, if any.
+    pub async fn get_focused_workspace(&self) -> Result<Option<Workspace>> {
+        Ok(self
+            .client
+            .get_workspaces()
+            .await?
+            .into_iter()
+            .find(|ws| ws.is_focused))
+    }
+}
+
+/// Scores a workspace as a placement target: distance from the focused
+/// index costs 10 per step, each resident window costs 5, and sitting on
+/// the preferred output earns 20.
+pub(crate) fn score_workspace(
+    idx: u8,
+    focused_idx: u8,
+    window_count: usize,
+    on_preferred_output: bool,
+) -> i64 {
+    let distance = i64::from(idx.abs_diff(focused_idx));
+    let density = window_count as i64;
+    -(distance * 10) - density * 5 + if on_preferred_output { 20 } else { 0 }
+}
+
+/// First index starting a run of `count` consecutive indices absent from
+/// `used`. Terminates because every index past the maximum used one is free.
+fn first_free_run(used: &std::collections::HashSet<u8>, count: u8) -> u8 {
+    let count = count.max(1);
+    let mut start = 1u8;
+    loop {
+        if (start..start.saturating_add(count)).all(|idx| !used.contains(&idx)) {
+            return start;
+        }
+        start += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockNiri;
+
+    /// One window on each of workspaces 2 and 3, two on the focused
+    /// workspace 1, plus a spacer on workspace 3.
+    async fn fragmented_niri() -> MockNiri {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        {
+            let state = niri.state();
+            let mut state = state.lock().unwrap();
+            for (app, title, ws) in [
+                ("firefox", "a", 1),
+                ("emacs", "b", 1),
+                ("foot", "c", 2),
+                ("mpv", "d", 3),
+                ("niri-spacer", "niri-spacer-1", 3),
+            ] {
+                let id = state.insert_window(app.to_string(), title.to_string());
+                state
+                    .windows
+                    .iter_mut()
+                    .find(|w| w.id == id)
+                    .unwrap()
+                    .workspace_id = Some(ws);
+            }
+        }
+        niri
+    }
+
+    #[tokio::test]
+    async fn merges_sparse_workspaces_onto_the_lowest() {
+        let niri = fragmented_niri().await;
+        let mut manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        let report = manager.merge_sparse_workspaces(2).await.unwrap();
+        assert_eq!(report.moved_windows, 1);
+        assert_eq!(report.freed_workspaces, 1);
+        assert_eq!(report.target_workspace_id, 2);
+
+        let state = niri.state();
+        let state = state.lock().unwrap();
+        // mpv moved from workspace 3 to 2; the spacer stayed put.
+        let mpv = state.windows.iter().find(|w| w.app_id.as_deref() == Some("mpv")).unwrap();
+        assert_eq!(mpv.workspace_id, Some(2));
+        let spacer = state
+            .windows
+            .iter()
+            .find(|w| w.app_id.as_deref() == Some("niri-spacer"))
+            .unwrap();
+        assert_eq!(spacer.workspace_id, Some(3));
+    }
+
+    #[tokio::test]
+    async fn focused_workspace_is_never_merged() {
+        let niri = fragmented_niri().await;
+        let mut manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        // With a high threshold even the 2-window focused workspace would
+        // qualify as sparse; it must still be skipped.
+        let report = manager.merge_sparse_workspaces(10).await.unwrap();
+        assert_eq!(report.target_workspace_id, 2);
+
+        let state = niri.state();
+        let state = state.lock().unwrap();
+        for app in ["firefox", "emacs"] {
+            let w = state.windows.iter().find(|w| w.app_id.as_deref() == Some(app)).unwrap();
+            assert_eq!(w.workspace_id, Some(1), "{app} must stay on the focused workspace");
+        }
+    }
+
+    #[tokio::test]
+    async fn ensure_workspaces_materializes_and_restores_focus() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces()[..2].to_vec(), vec![]).await;
+        // Mark workspace 1 focused in the truncated list.
+        niri.state().lock().unwrap().workspaces[0].is_focused = true;
+        let mut manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        manager.ensure_workspaces(5).await.unwrap();
+
+        let state = niri.state();
+        let state = state.lock().unwrap();
+        let indices: Vec<u8> = state.workspaces.iter().map(|ws| ws.idx).collect();
+        assert_eq!(indices, vec![1, 2, 3, 4, 5]);
+        let focused: Vec<u8> = state
+            .workspaces
+            .iter()
+            .filter(|ws| ws.is_focused)
+            .map(|ws| ws.idx)
+            .collect();
+        assert_eq!(focused, vec![1], "original focus must be restored");
+    }
+
+    #[tokio::test]
+    async fn reorder_uses_the_minimum_number_of_adjacent_moves() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        // Desired [3, 1, 2] from [1, 2, 3]: two inversions, so two moves.
+        manager.reorder_workspaces(vec![3, 1, 2]).await.unwrap();
+
+        let state = niri.state();
+        let state = state.lock().unwrap();
+        let mut order: Vec<(u8, u64)> =
+            state.workspaces.iter().map(|ws| (ws.idx, ws.id)).collect();
+        order.sort();
+        assert_eq!(
+            order.iter().map(|(_, id)| *id).collect::<Vec<_>>(),
+            vec![3, 1, 2]
+        );
+        let moves = state
+            .actions
+            .iter()
+            .filter(|a| matches!(a, Action::MoveWorkspaceUp {}))
+            .count();
+        assert_eq!(moves, 2, "one move per inversion");
+    }
+
+    #[tokio::test]
+    async fn spacers_move_to_the_front() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+        let spacer = SpacerWindow {
+            number: 1,
+            created_at: std::time::Instant::now(),
+            niri_window_id: 1001,
+            workspace_id: 3,
+            workspace_idx: 3,
+            color: crate::backend::Color::new(0, 0, 0),
+            title: "niri-spacer-1".to_string(),
+        };
+
+        manager.move_spacers_to_front(&[spacer]).await.unwrap();
+
+        let state = niri.state();
+        let state = state.lock().unwrap();
+        let mut order: Vec<(u8, u64)> =
+            state.workspaces.iter().map(|ws| (ws.idx, ws.id)).collect();
+        order.sort();
+        assert_eq!(
+            order.iter().map(|(_, id)| *id).collect::<Vec<_>>(),
+            vec![3, 1, 2],
+            "the spacer workspace leads, others keep their order"
+        );
+    }
+
+    #[tokio::test]
+    async fn outputs_join_their_workspaces() {
+        let mut workspaces = MockNiri::three_workspaces();
+        workspaces[2].output = Some("HDMI-A-1".to_string());
+        let niri = MockNiri::spawn(workspaces, vec![]).await;
+        niri.state().lock().unwrap().outputs = vec![
+            crate::niri::Output {
+                name: "eDP-1".to_string(),
+                make: Some("BOE".to_string()),
+                model: None,
+                logical: Some(crate::niri::LogicalOutput {
+                    x: 0,
+                    y: 0,
+                    width: 2256,
+                    height: 1504,
+                }),
+            },
+            crate::niri::Output {
+                name: "HDMI-A-1".to_string(),
+                make: None,
+                model: None,
+                logical: None,
+            },
+        ];
+        let manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        let listing = manager.list_outputs().await.unwrap();
+        assert_eq!(listing.len(), 2);
+        assert_eq!(listing[0].0.name, "eDP-1");
+        assert_eq!(
+            listing[0].1.iter().map(|ws| ws.idx).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            listing[1].1.iter().map(|ws| ws.idx).collect::<Vec<_>>(),
+            vec![3]
+        );
+    }
+
+    #[tokio::test]
+    async fn active_window_is_read_from_workspace_data() {
+        let mut workspaces = MockNiri::three_workspaces();
+        workspaces[1].active_window_id = Some(77);
+        let niri = MockNiri::spawn(workspaces, vec![]).await;
+        let manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        assert_eq!(manager.active_window_in(2).await.unwrap(), Some(77));
+        assert_eq!(manager.active_window_in(1).await.unwrap(), None);
+        assert_eq!(manager.active_window_in(999).await.unwrap(), None);
+        // Only the one workspace query went over IPC; no window scan.
+        assert!(niri.state().lock().unwrap().windows.is_empty());
+    }
+
+    #[test]
+    fn scoring_prefers_near_sparse_same_output_workspaces() {
+        // Focused idx 2. An adjacent empty workspace beats a distant one...
+        assert!(score_workspace(1, 2, 0, true) > score_workspace(4, 2, 0, true));
+        // ...and beats an adjacent but crowded one.
+        assert!(score_workspace(1, 2, 0, true) > score_workspace(3, 2, 5, true));
+        // The output bonus can outweigh one step of distance.
+        assert!(score_workspace(4, 2, 0, true) > score_workspace(3, 2, 0, false));
+    }
+
+    #[tokio::test]
+    async fn best_workspace_balances_distance_and_density() {
+        // Focused ws 1; ws2 crowded, ws3 empty.
+        let niri = fragmented_niri().await;
+        {
+            let state = niri.state();
+            let mut state = state.lock().unwrap();
+            // Pile more windows onto workspace 2.
+            for i in 0..6 {
+                let id = state.insert_window("foot".to_string(), format!("sh{i}"));
+                state.windows.iter_mut().find(|w| w.id == id).unwrap().workspace_id = Some(2);
+            }
+        }
+        let mut manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        // ws1: d0 but 2 windows; ws2: d1, 7 windows; ws3: d2, 2 windows.
+        // Scores: ws1 = -10? no: d0, density 2 -> -10 + 20; ws3 = -20 - 10 + 20.
+        let best = manager.find_best_workspace_for_window(None).await.unwrap();
+        assert_eq!(best, 1);
+
+        // Preferring an output that only workspace 3 sits on flips it
+        // (after its spacer closes, leaving one resident window there).
+        {
+            let state = niri.state();
+            let mut state = state.lock().unwrap();
+            state.workspaces[2].output = Some("HDMI-A-1".to_string());
+            state
+                .windows
+                .retain(|w| w.app_id.as_deref() != Some("niri-spacer"));
+        }
+        let best = manager
+            .find_best_workspace_for_window(Some("HDMI-A-1"))
+            .await
+            .unwrap();
+        assert_eq!(best, 3);
+    }
+
+    #[test]
+    fn free_runs_are_found_on_indices() {
+        use std::collections::HashSet;
+        let used: HashSet<u8> = [1, 2, 3].into_iter().collect();
+        assert_eq!(first_free_run(&used, 2), 4);
+
+        let used: HashSet<u8> = [1, 3].into_iter().collect();
+        assert_eq!(first_free_run(&used, 1), 2);
+
+        // A one-index hole is not enough for a run of two.
+        let used: HashSet<u8> = [1, 2, 4, 5].into_iter().collect();
+        assert_eq!(first_free_run(&used, 2), 6);
+
+        assert_eq!(first_free_run(&HashSet::new(), 3), 1);
+    }
+
+    #[tokio::test]
+    async fn find_workspace_sequence_ignores_opaque_ids() {
+        // Workspaces with wildly non-consecutive IDs but indices 1..=3: the
+        // sequence search must key off indices and return 4.
+        let mut workspaces = MockNiri::three_workspaces();
+        for (ws, id) in workspaces.iter_mut().zip([1000u64, 7, 40004]) {
+            ws.id = id;
+        }
+        let niri = MockNiri::spawn(workspaces, vec![]).await;
+        let manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        assert_eq!(manager.find_workspace_sequence(2).await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn workspace_switches_use_the_dedicated_settle_delay() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces()[..2].to_vec(), vec![]).await;
+        niri.state().lock().unwrap().workspaces[0].is_focused = true;
+        let clock = std::sync::Arc::new(crate::clock::TestClock::new());
+        let timings = Timings {
+            workspace_switch_delay: std::time::Duration::from_secs(7),
+            ..Timings::default()
+        };
+        let mut manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()))
+            .with_timings(timings)
+            .with_clock(clock.clone());
+
+        let before = clock.now();
+        manager.ensure_workspaces(4).await.unwrap();
+
+        // Two materializations plus the focus restore: three switches, each
+        // waiting the dedicated 7s delay (in virtual time).
+        let elapsed = clock.now() - before;
+        assert_eq!(elapsed, std::time::Duration::from_secs(21));
+    }
+
+    #[tokio::test]
+    async fn ensure_workspaces_is_a_noop_when_enough_exist() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        manager.ensure_workspaces(2).await.unwrap();
+        assert!(niri.state().lock().unwrap().actions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stats_are_ordered_by_index_not_id() {
+        // IDs deliberately shuffled against the index order.
+        let mut workspaces = MockNiri::three_workspaces();
+        for (ws, id) in workspaces.iter_mut().zip([900u64, 4, 77]) {
+            ws.id = id;
+        }
+        let niri = MockNiri::spawn(workspaces, vec![]).await;
+        let manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        let stats = manager.collect_stats().await.unwrap();
+        let indices: Vec<u8> = stats.rows.iter().map(|r| r.idx).collect();
+        assert_eq!(indices, vec![1, 2, 3], "rows must follow idx order");
+        let ids: Vec<u64> = stats.rows.iter().map(|r| r.workspace_id).collect();
+        assert_eq!(ids, vec![900, 4, 77], "ids ride along but never drive the order");
+
+        // The rendered table shows positions in the same order.
+        let rendered = stats.render_table(Some(80)).to_string();
+        let pos_900 = rendered.find("900").unwrap();
+        let pos_77 = rendered.find("77").unwrap();
+        assert!(pos_900 < pos_77);
+    }
+
+    #[tokio::test]
+    async fn stats_table_lists_workspaces_and_marks_focus() {
+        let niri = fragmented_niri().await;
+        let manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        let stats = manager.collect_stats().await.unwrap();
+        assert_eq!(stats.rows.len(), 3);
+        assert_eq!(stats.rows[2].spacer_count, 1);
+        assert_eq!(stats.rows[2].window_count, 1);
+
+        let rendered = stats.render_table(Some(80)).to_string();
+        for id in ["1", "2", "3"] {
+            assert!(rendered.contains(id), "missing workspace {id} in:\n{rendered}");
+        }
+        assert!(rendered.contains('\u{25b6}'), "focused marker missing:\n{rendered}");
+        assert_eq!(rendered.matches('\u{25b6}').count(), 1);
+    }
+
+    #[tokio::test]
+    async fn nothing_to_merge_reports_zeros() {
+        let niri = MockNiri::spawn(MockNiri::three_workspaces(), vec![]).await;
+        let mut manager = WorkspaceManager::new(NiriClient::new(niri.socket_path()));
+
+        let report = manager.merge_sparse_workspaces(3).await.unwrap();
+        assert_eq!(report.moved_windows, 0);
+        assert_eq!(report.freed_workspaces, 0);
+    }
+}